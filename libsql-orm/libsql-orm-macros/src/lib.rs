@@ -14,12 +14,14 @@
 //! use libsql_orm::Model;
 //! use serde::{Serialize, Deserialize};
 //!
-//! #[derive(Model, Serialize, Deserialize)]
+//! #[derive(Model, Clone, Serialize, Deserialize)]
 //! struct User {
 //!     pub id: Option<i64>,
 //!     pub name: String,
 //!     pub email: String,
 //! }
+//!
+//! assert_eq!(User::table_name(), "user");
 //! ```
 //!
 //! # Attribute Macros
@@ -33,12 +35,14 @@
 //! use libsql_orm::Model;
 //! use serde::{Serialize, Deserialize};
 //!
-//! #[derive(Model, Serialize, Deserialize)]
+//! #[derive(Model, Clone, Serialize, Deserialize)]
 //! #[table_name("custom_users")]
 //! struct User {
 //!     pub id: Option<i64>,
 //!     pub name: String,
 //! }
+//!
+//! assert_eq!(User::table_name(), "custom_users");
 //! ```
 //!
 //! ## `#[orm_column(...)]`
@@ -46,10 +50,10 @@
 //! Specifies custom column properties for database fields.
 //!
 //! ```rust
-//! use libsql_orm::Model;
+//! use libsql_orm::{orm_column, Model};
 //! use serde::{Serialize, Deserialize};
 //!
-//! #[derive(Model, Serialize, Deserialize)]
+//! #[derive(Model, Clone, Serialize, Deserialize)]
 //! struct User {
 //!     #[orm_column(type = "INTEGER PRIMARY KEY AUTOINCREMENT")]
 //!     pub id: Option<i64>,
@@ -66,9 +70,11 @@
 //!
 //! ## `generate_migration!(Model)`
 //!
-//! Generates a database migration from a model definition.
+//! Generates a database migration from a model definition. The expansion uses
+//! `libsql_orm::MigrationManager`, which this version of libsql-orm doesn't
+//! ship, so the example isn't compiled.
 //!
-//! ```rust
+//! ```rust,ignore
 //! use libsql_orm::{generate_migration, MigrationManager};
 //!
 //! let migration = generate_migration!(User);
@@ -94,7 +100,10 @@ use syn::{parse_macro_input, Attribute, Data, DeriveInput, Field, Fields, Lit, T
 /// # Examples:
 ///
 /// ```rust
-/// #[derive(Model)]
+/// use libsql_orm::{orm_column, Model};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Model, Clone, Serialize, Deserialize)]
 /// struct User {
 ///     #[orm_column(type = "INTEGER PRIMARY KEY AUTOINCREMENT")]
 ///     pub id: Option<i64>,
@@ -129,13 +138,15 @@ pub fn orm_column(_args: TokenStream, input: TokenStream) -> TokenStream {
 /// use libsql_orm::Model;
 /// use serde::{Serialize, Deserialize};
 ///
-/// #[derive(Model, Serialize, Deserialize)]
+/// #[derive(Model, Clone, Serialize, Deserialize)]
 /// #[table_name("users")]
 /// struct User {
 ///     pub id: Option<i64>,
 ///     pub name: String,
 ///     pub email: String,
 /// }
+///
+/// assert_eq!(User::columns(), vec!["id", "name", "email"]);
 /// ```
 #[proc_macro_derive(Model, attributes(table_name, orm_column))]
 pub fn derive_model(input: TokenStream) -> TokenStream {
@@ -387,10 +398,12 @@ fn is_boolean_type(ty: &Type) -> bool {
 ///
 /// Creates a migration instance from a model's schema definition. The migration
 /// will contain the SQL necessary to create the table for the model.
+/// It needs `libsql_orm::MigrationManager`, which this version of libsql-orm
+/// doesn't ship, so the example isn't compiled.
 ///
 /// # Examples:
 ///
-/// ```rust
+/// ```rust,ignore
 /// use libsql_orm::{generate_migration, MigrationManager};
 ///
 /// // Generate migration for User model
@@ -0,0 +1,146 @@
+//! Transparent encryption of individual columns
+//!
+//! The crate brings no cryptography of its own: the caller implements
+//! [`FieldCipher`] on top of the library and keys of their choice and marks
+//! the columns it applies to with [`EncryptedColumns`]. A model returning them
+//! from [`crate::Model::encrypted_columns`] has them encrypted before they are
+//! bound on every create, update and upsert, and decrypted on every read
+//! through its methods, so the plaintext never reaches the database. Other
+//! columns are left alone. Builders used directly decrypt with
+//! [`crate::QueryBuilder::encrypted`].
+//!
+//! Encrypted columns hold BLOBs and can't be filtered, sorted or searched on
+//! by their plaintext.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use std::collections::HashMap;
+//! use std::sync::{Arc, LazyLock};
+//! use libsql_orm::{Database, EncryptedColumns, FieldCipher, Model, Value};
+//! # use serde::{Deserialize, Serialize};
+//! # struct AesGcm;
+//! # impl FieldCipher for AesGcm {
+//! #     fn encrypt(&self, plaintext: &[u8]) -> libsql_orm::Result<Vec<u8>> { Ok(plaintext.to_vec()) }
+//! #     fn decrypt(&self, ciphertext: &[u8]) -> libsql_orm::Result<Vec<u8>> { Ok(ciphertext.to_vec()) }
+//! # }
+//! # fn my_aes_gcm_cipher() -> AesGcm { AesGcm }
+//! # #[derive(Clone, Serialize, Deserialize)]
+//! # struct Note {
+//! #     id: Option<i64>,
+//! #     notes: Option<String>,
+//! # }
+//!
+//! static CIPHERS: LazyLock<EncryptedColumns> = LazyLock::new(|| {
+//!     EncryptedColumns::new().column("notes", Arc::new(my_aes_gcm_cipher()))
+//! });
+//!
+//! impl Model for Note {
+//!     fn encrypted_columns() -> EncryptedColumns {
+//!         CIPHERS.clone()
+//!     }
+//!     // ...
+//! #   fn table_name() -> &'static str { "notes" }
+//! #   fn get_primary_key(&self) -> Option<i64> { self.id }
+//! #   fn set_primary_key(&mut self, id: i64) { self.id = Some(id) }
+//! #   fn columns() -> Vec<&'static str> { vec!["id", "notes"] }
+//! #   fn migration_sql() -> String { String::new() }
+//! #   fn to_map(&self) -> libsql_orm::Result<HashMap<String, Value>> {
+//! #       let notes = self.notes.clone().map_or(Value::Null, Value::Text);
+//! #       Ok(HashMap::from([("notes".to_string(), notes)]))
+//! #   }
+//! #   fn from_map(map: HashMap<String, Value>) -> libsql_orm::Result<Self> {
+//! #       let id = match map.get("id") { Some(Value::Integer(id)) => Some(*id), _ => None };
+//! #       let notes = match map.get("notes") { Some(Value::Text(t)) => Some(t.clone()), _ => None };
+//! #       Ok(Note { id, notes })
+//! #   }
+//! }
+//!
+//! # async fn example(note: Note, db: &Database) -> libsql_orm::Result<()> {
+//! let note = note.create(db).await?;
+//! let note = Note::find_by_id(note.id.unwrap(), db).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{Error, Result};
+
+/// Encrypts and decrypts column values with a key held by the caller
+///
+/// Implementations should use authenticated encryption with a fresh nonce per
+/// value, stored inside the returned bytes.
+pub trait FieldCipher: Send + Sync {
+    fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>>;
+
+    fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Columns stored encrypted, with the cipher of each
+///
+/// Only text values can be encrypted; `NULL` stays `NULL`.
+#[derive(Clone, Default)]
+pub struct EncryptedColumns {
+    ciphers: HashMap<String, Arc<dyn FieldCipher>>,
+}
+
+impl EncryptedColumns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks `column` as encrypted with `cipher`
+    pub fn column(mut self, column: &str, cipher: Arc<dyn FieldCipher>) -> Self {
+        self.ciphers.insert(column.to_string(), cipher);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ciphers.is_empty()
+    }
+
+    /// Whether `column` is marked as encrypted
+    pub fn contains(&self, column: &str) -> bool {
+        self.ciphers.contains_key(column)
+    }
+
+    /// Encrypts `value` when `column` is marked, other columns pass through
+    pub fn encrypt(&self, column: &str, value: libsql::Value) -> Result<libsql::Value> {
+        let Some(cipher) = self.ciphers.get(column) else {
+            return Ok(value);
+        };
+        match value {
+            libsql::Value::Null => Ok(libsql::Value::Null),
+            libsql::Value::Text(text) => Ok(libsql::Value::Blob(cipher.encrypt(text.as_bytes())?)),
+            _ => Err(Error::Validation(format!(
+                "Encrypted column {column} only holds text"
+            ))),
+        }
+    }
+
+    /// Decrypts `value` of a marked `column` back to text
+    ///
+    /// A plaintext value in a marked column is an error rather than passed on,
+    /// it means the row was written without encryption.
+    pub fn decrypt(&self, column: &str, value: libsql::Value) -> Result<libsql::Value> {
+        let Some(cipher) = self.ciphers.get(column) else {
+            return Ok(value);
+        };
+        match value {
+            libsql::Value::Null => Ok(libsql::Value::Null),
+            libsql::Value::Blob(bytes) => {
+                let plaintext = cipher.decrypt(&bytes)?;
+                String::from_utf8(plaintext)
+                    .map(libsql::Value::Text)
+                    .map_err(|_| {
+                        Error::Validation(format!("Decrypted {column} is not valid UTF-8"))
+                    })
+            }
+            _ => Err(Error::Validation(format!(
+                "Encrypted column {column} holds an unencrypted value"
+            ))),
+        }
+    }
+}
@@ -3,7 +3,9 @@
 //! This module handles the connection to libsql databases and provides
 //! query execution capabilities for Cloudflare Workers.
 
+use crate::FromSql;
 use libsql::{Builder, Connection, Rows};
+use std::collections::HashMap;
 
 /// Database connection wrapper for libsql in Cloudflare Workers
 ///
@@ -67,6 +69,32 @@ impl Database {
         }
     }
 
+    /// Opens a local libsql database file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the database file, or `":memory:"` for an in-memory database
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the `Database` instance or a `libsql::Error`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let db = Database::new_local(":memory:").await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn new_local(path: &str) -> std::result::Result<Self, libsql::Error> {
+        let db = Builder::new_local(path).build().await?;
+        let conn = db.connect()?;
+        Ok(Database { inner: conn })
+    }
+
     /// Gets a reference to the underlying libsql connection
     ///
     /// This method provides direct access to the libsql connection for advanced use cases
@@ -110,4 +138,365 @@ impl Database {
     ) -> Result<Rows, libsql::Error> {
         self.inner.query(sql, params).await
     }
+
+    /// Executes a SQL statement with parameters
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - The SQL statement string
+    /// * `params` - Vector of parameters to bind to the statement
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the number of affected rows or a `libsql::Error`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn execute_example(db: &Database) -> Result<(), Box<dyn std::error::Error>> {
+    ///     let affected = db.execute(
+    ///         "UPDATE users SET is_active = 0 WHERE id = ?",
+    ///         vec![libsql::Value::Integer(1)]
+    ///     ).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn execute(
+        &self,
+        sql: &str,
+        params: Vec<libsql::Value>,
+    ) -> Result<u64, libsql::Error> {
+        self.inner.execute(sql, params).await
+    }
+
+    /// Executes a SQL query with named parameters (`:name`, `@name` or `$name`)
+    ///
+    /// Keys without a prefix get `:`. Unlike [`Database::query`], a missing,
+    /// unused or positional (`?`) parameter is an error instead of silently
+    /// binding the wrong value or `NULL`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::collections::HashMap;
+    /// use libsql_orm::Database;
+    ///
+    /// async fn named_example(db: &Database) -> libsql_orm::Result<()> {
+    ///     let params = HashMap::from([
+    ///         ("owner_id".to_string(), libsql::Value::Text("42".to_string())),
+    ///         ("completed".to_string(), libsql::Value::Integer(0)),
+    ///     ]);
+    ///     let rows = db
+    ///         .query_named(
+    ///             "SELECT * FROM items WHERE owner_id = :owner_id AND completed = :completed",
+    ///             params,
+    ///         )
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_named(
+        &self,
+        sql: &str,
+        params: HashMap<String, libsql::Value>,
+    ) -> crate::Result<Rows> {
+        // Prepared only to read the placeholder names
+        let statement = self.inner.prepare(sql).await?;
+        let mut values: HashMap<String, libsql::Value> = params
+            .into_iter()
+            .map(|(name, value)| {
+                if name.starts_with([':', '@', '$']) {
+                    (name, value)
+                } else {
+                    (format!(":{name}"), value)
+                }
+            })
+            .collect();
+
+        let mut named = Vec::with_capacity(values.len());
+        for index in 1..=statement.parameter_count() {
+            let Some(name) = statement.parameter_name(index as i32) else {
+                return Err(crate::Error::Validation(format!(
+                    "Parameter {index} is positional in a named query"
+                )));
+            };
+            let value = values.remove(name).ok_or_else(|| {
+                crate::Error::Validation(format!("No value for parameter {name}"))
+            })?;
+            named.push((name.to_string(), value));
+        }
+        if let Some(name) = values.keys().next() {
+            return Err(crate::Error::Validation(format!(
+                "Unknown parameter {name}"
+            )));
+        }
+        drop(statement);
+        Ok(self.inner.query(sql, named).await?)
+    }
+
+    /// Attaches another database under `schema_name`
+    ///
+    /// Tables of the attached database can then be referenced as `schema.table`,
+    /// e.g. in `QueryBuilder::join`. Call [`Database::detach`] when done, the
+    /// attachment lives as long as the connection otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn attach_example(db: &Database) -> libsql_orm::Result<()> {
+    ///     db.attach("catalog.db", "catalog").await?;
+    ///     let rows = db.query("SELECT * FROM catalog.products", vec![]).await?;
+    ///     db.detach("catalog").await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn attach(&self, path_or_url: &str, schema_name: &str) -> crate::Result<()> {
+        validate_schema_name(schema_name)?;
+        self.inner
+            .execute(
+                &format!("ATTACH DATABASE ? AS {schema_name}"),
+                vec![libsql::Value::Text(path_or_url.to_string())],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Detaches a database previously attached with [`Database::attach`]
+    pub async fn detach(&self, schema_name: &str) -> crate::Result<()> {
+        validate_schema_name(schema_name)?;
+        self.inner
+            .execute(&format!("DETACH DATABASE {schema_name}"), ())
+            .await?;
+        Ok(())
+    }
+
+    /// Opens a savepoint, a nested unit of work inside the current transaction
+    ///
+    /// Unlike `BEGIN`, this also works when a transaction is already open, so
+    /// helpers can roll back their own changes without aborting the caller's
+    /// transaction. Outside a transaction it behaves like `BEGIN`. End it with
+    /// [`Database::release_savepoint`] or [`Database::rollback_to_savepoint`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn savepoint_example(db: &Database) -> libsql_orm::Result<()> {
+    ///     db.savepoint("merge").await?;
+    ///     match db.execute("DELETE FROM items WHERE id = ?", vec![3.into()]).await {
+    ///         Ok(_) => db.release_savepoint("merge").await?,
+    ///         Err(_) => db.rollback_to_savepoint("merge").await?,
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn savepoint(&self, name: &str) -> crate::Result<()> {
+        validate_savepoint_name(name)?;
+        self.inner.execute(&format!("SAVEPOINT {name}"), ()).await?;
+        Ok(())
+    }
+
+    /// Keeps the changes made since [`Database::savepoint`] and closes it
+    pub async fn release_savepoint(&self, name: &str) -> crate::Result<()> {
+        validate_savepoint_name(name)?;
+        self.inner.execute(&format!("RELEASE {name}"), ()).await?;
+        Ok(())
+    }
+
+    /// Discards the changes made since [`Database::savepoint`] and closes it
+    ///
+    /// Changes made before the savepoint, e.g. by an outer transaction, are kept.
+    pub async fn rollback_to_savepoint(&self, name: &str) -> crate::Result<()> {
+        validate_savepoint_name(name)?;
+        self.inner
+            .execute_batch(&format!("ROLLBACK TO {name}; RELEASE {name};"))
+            .await?;
+        Ok(())
+    }
+
+    /// Rebuilds the database file, reclaiming the space of deleted rows and
+    /// defragmenting tables and indexes
+    ///
+    /// SQLite can't vacuum inside a transaction, so this fails while one (or a
+    /// [`Database::savepoint`]) is open. It rewrites the whole file and holds
+    /// an exclusive lock meanwhile, so run it during quiet times.
+    pub async fn vacuum(&self) -> crate::Result<()> {
+        self.inner.execute("VACUUM", ()).await?;
+        Ok(())
+    }
+
+    /// Writes a consistent snapshot of the database to a new file at `path`
+    ///
+    /// Uses `VACUUM INTO`, which reads the database inside one read
+    /// transaction: the copy holds exactly the committed state at its start,
+    /// without changes made while it runs. Writers aren't blocked in WAL mode,
+    /// in rollback-journal mode they wait until the copy is done. The copy is
+    /// compacted as by [`Database::vacuum`] and can be opened with
+    /// [`Database::new_local`].
+    ///
+    /// Fails with SQLite's error if `path` already exists, a backup never
+    /// overwrites a file, and like any VACUUM it can't run inside a
+    /// transaction. On a remote connection the path is on the server's side.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn nightly_backup(db: &Database) -> libsql_orm::Result<()> {
+    ///     let name = format!("backups/lists-{}.db", chrono::Utc::now().format("%Y%m%d"));
+    ///     db.backup_to(name).await
+    /// }
+    /// ```
+    pub async fn backup_to(&self, path: impl AsRef<std::path::Path>) -> crate::Result<()> {
+        let path = path.as_ref();
+        let path = path.to_str().ok_or_else(|| {
+            crate::Error::Validation(format!("Backup path {} is not UTF-8", path.display()))
+        })?;
+        self.inner
+            .execute("VACUUM INTO ?", vec![libsql::Value::Text(path.to_string())])
+            .await?;
+        Ok(())
+    }
+
+    /// Rebuilds the FTS5 index of the virtual table `fts_table` from its content
+    ///
+    /// Run it after bulk-loading the content table of an external-content
+    /// index without its sync triggers, or when ranking got off after many
+    /// changes. Fails with `Error::NotFound` when there is no such FTS5 table,
+    /// rather than silently doing nothing.
+    pub async fn rebuild_fts(&self, fts_table: &str) -> crate::Result<()> {
+        if !is_identifier(fts_table) {
+            return Err(crate::Error::Validation(format!(
+                "Invalid table name: {fts_table:?}"
+            )));
+        }
+        let definition: Option<String> = self
+            .query_scalar(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
+                vec![libsql::Value::Text(fts_table.to_string())],
+            )
+            .await?;
+        let is_fts5 = definition.is_some_and(|sql| {
+            let sql = sql.to_lowercase();
+            sql.starts_with("create virtual table") && sql.contains("using fts5")
+        });
+        if !is_fts5 {
+            return Err(crate::Error::NotFound(format!(
+                "No FTS5 table named {fts_table}"
+            )));
+        }
+        self.inner
+            .execute(
+                &format!("INSERT INTO {fts_table} ({fts_table}) VALUES ('rebuild')"),
+                (),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Gathers table and index statistics into `sqlite_stat1`
+    ///
+    /// The query planner uses them to pick between indexes; without them it
+    /// guesses. Rerun after the data changed a lot.
+    pub async fn analyze(&self) -> crate::Result<()> {
+        self.inner.execute("ANALYZE", ()).await?;
+        Ok(())
+    }
+
+    /// Cancels the statement currently running on this connection
+    ///
+    /// The interrupted call fails with [`crate::Error::Interrupted`]. Meant to be
+    /// called from another task or thread than the one waiting on the query,
+    /// e.g. when the user navigates away; the connection can be shared for that
+    /// (`Arc<Database>`), SQLite's interrupt is thread-safe. If nothing is
+    /// running it does nothing, so it can't cancel a statement started later.
+    /// Only local databases support it, remote connections return an error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use libsql_orm::Database;
+    ///
+    /// async fn cancel_example(db: Arc<Database>) -> libsql_orm::Result<()> {
+    ///     let handle = db.clone();
+    ///     std::thread::spawn(move || handle.interrupt());
+    ///     let result = db.query_scalar::<i64>("SELECT COUNT(*) FROM big_table", vec![]).await;
+    ///     assert!(matches!(result, Err(libsql_orm::Error::Interrupted) | Ok(_)));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn interrupt(&self) -> crate::Result<()> {
+        self.inner.interrupt()?;
+        Ok(())
+    }
+
+    /// Executes a query and returns the first column of the first row
+    ///
+    /// Returns `Ok(None)` when the query produces no rows or the value is `NULL`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - The SQL query string
+    /// * `params` - Vector of parameters to bind to the query
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::Database;
+    ///
+    /// async fn scalar_example(db: &Database) -> libsql_orm::Result<()> {
+    ///     let max_id: Option<i64> = db.query_scalar("SELECT MAX(id) FROM users", vec![]).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_scalar<T: FromSql>(
+        &self,
+        sql: &str,
+        params: Vec<libsql::Value>,
+    ) -> crate::Result<Option<T>> {
+        let mut rows = self.inner.query(sql, params).await?;
+        match rows.next().await? {
+            Some(row) => match row.get_value(0)? {
+                libsql::Value::Null => Ok(None),
+                value => T::from_sql(value).map(Some),
+            },
+            None => Ok(None),
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn validate_savepoint_name(name: &str) -> crate::Result<()> {
+    if is_identifier(name) {
+        Ok(())
+    } else {
+        Err(crate::Error::Validation(format!(
+            "Invalid savepoint name: {name:?}"
+        )))
+    }
+}
+
+fn validate_schema_name(schema_name: &str) -> crate::Result<()> {
+    if is_identifier(schema_name) && !matches!(schema_name.to_lowercase().as_str(), "main" | "temp")
+    {
+        Ok(())
+    } else {
+        Err(crate::Error::Validation(format!(
+            "Invalid schema name: {schema_name:?}"
+        )))
+    }
 }
@@ -13,6 +13,8 @@
 //! - **Not Found Errors**: Resource not found
 //! - **Pagination Errors**: Pagination parameter issues
 //! - **Query Errors**: Query building problems
+//! - **Deserialize Errors**: Result rows that do not match the target type
+//! - **Interrupted Errors**: Statements cancelled with `Database::interrupt`
 //!
 //! # Examples
 //!
@@ -35,7 +37,7 @@ use std::fmt;
 ///
 /// Provides comprehensive error handling for all database and ORM operations.
 /// All variants include descriptive messages to aid in debugging and error handling.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Error {
     /// Database connection error
     Connection(String),
@@ -57,6 +59,14 @@ pub enum Error {
     DatabaseError(String),
     /// Generic error
     Generic(String),
+    /// A result row could not be deserialized into the target type
+    Deserialize {
+        message: String,
+        /// Column names present in the offending row
+        columns: Vec<String>,
+    },
+    /// The statement was cancelled by `Database::interrupt`
+    Interrupted,
 }
 
 impl std::error::Error for Error {}
@@ -74,13 +84,57 @@ impl fmt::Display for Error {
             Error::AnyhowError(msg) => write!(f, "Anyhow error: {msg}"),
             Error::DatabaseError(msg) => write!(f, "Database error: {msg}"),
             Error::Generic(msg) => write!(f, "Error: {msg}"),
+            Error::Deserialize { message, columns } => write!(
+                f,
+                "Deserialization error: {message} (row columns: {})",
+                columns.join(", ")
+            ),
+            Error::Interrupted => write!(f, "Query interrupted"),
         }
     }
 }
 
+impl Error {
+    /// Prefixes the message with `context`, keeping the variant
+    ///
+    /// Used when a nested builder fails, so the error says which part of the
+    /// query it came from, e.g. `UNION ALL member 2: ...`.
+    pub fn with_context(self, context: &str) -> Self {
+        let prefix = |msg: String| format!("{context}: {msg}");
+        match self {
+            Error::Connection(msg) => Error::Connection(prefix(msg)),
+            Error::Sql(msg) => Error::Sql(prefix(msg)),
+            Error::Serialization(msg) => Error::Serialization(prefix(msg)),
+            Error::Validation(msg) => Error::Validation(prefix(msg)),
+            Error::NotFound(msg) => Error::NotFound(prefix(msg)),
+            Error::Pagination(msg) => Error::Pagination(prefix(msg)),
+            Error::Query(msg) => Error::Query(prefix(msg)),
+            Error::AnyhowError(msg) => Error::AnyhowError(prefix(msg)),
+            Error::DatabaseError(msg) => Error::DatabaseError(prefix(msg)),
+            Error::Generic(msg) => Error::Generic(prefix(msg)),
+            Error::Deserialize { message, columns } => Error::Deserialize {
+                message: prefix(message),
+                columns,
+            },
+            Error::Interrupted => Error::Interrupted,
+        }
+    }
+}
+
+/// SQLite result code of an interrupted statement
+const SQLITE_INTERRUPT: i32 = 9;
+
 impl From<libsql::Error> for Error {
     fn from(err: libsql::Error) -> Self {
-        Error::Sql(err.to_string())
+        match err {
+            libsql::Error::SqliteFailure(code, _) if code & 0xff == SQLITE_INTERRUPT => {
+                Error::Interrupted
+            }
+            libsql::Error::RemoteSqliteFailure(_, code, _) if code & 0xff == SQLITE_INTERRUPT => {
+                Error::Interrupted
+            }
+            err => Error::Sql(err.to_string()),
+        }
     }
 }
 
@@ -80,7 +80,7 @@ use serde::{Deserialize, Serialize};
 /// # Examples
 ///
 /// ```rust
-/// use libsql_orm::{FilterOperator, Filter};
+/// use libsql_orm::{FilterOperator, Filter, Value};
 ///
 /// // Single condition
 /// let single = FilterOperator::Single(Filter::eq("status", "active"));
@@ -99,6 +99,13 @@ use serde::{Deserialize, Serialize};
 ///
 /// // Negation
 /// let not_filter = FilterOperator::Not(Box::new(single));
+///
+/// // Inclusive range
+/// let range = FilterOperator::Between(
+///     "created_at".to_string(),
+///     Value::Text("2024-01-01".to_string()),
+///     Value::Text("2024-12-31".to_string()),
+/// );
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FilterOperator {
@@ -110,8 +117,31 @@ pub enum FilterOperator {
     Or(Vec<FilterOperator>),
     /// NOT filter
     Not(Box<FilterOperator>),
+    /// Inclusive range check, rendered as `column BETWEEN ? AND ?`
+    Between(String, Value, Value),
+    /// Whether the column contains the text, rendered as
+    /// `column LIKE ? ESCAPE '\'` with `%`, `_` and `\` of the text escaped
+    ///
+    /// Built by [`crate::QueryBuilder::search`].
+    Contains(String, String),
     /// Custom SQL condition
     Custom(String),
+    /// Raw SQL condition with a parameter for each of its `?` placeholders
+    Raw(String, Vec<Value>),
+    /// Whether the subquery returns any row, rendered as `EXISTS (...)`
+    ///
+    /// Usually correlated with the outer query through a
+    /// `FilterOperator::Custom` column comparison. Not serializable, like the
+    /// [`crate::QueryBuilder`] it holds.
+    #[serde(skip)]
+    Exists(Box<crate::QueryBuilder>),
+    /// Whether the subquery returns no row, rendered as `NOT EXISTS (...)`
+    #[serde(skip)]
+    NotExists(Box<crate::QueryBuilder>),
+    /// Whether the column's value is among the subquery's rows, rendered as
+    /// `column IN (...)`, see [`crate::QueryBuilder::where_in`]
+    #[serde(skip)]
+    InSubquery(String, Box<crate::QueryBuilder>),
 }
 
 /// Individual filter condition
@@ -483,6 +513,9 @@ pub struct Sort {
     pub column: String,
     /// Sort order
     pub order: crate::SortOrder,
+    /// Placement of `NULL`s, SQLite's default when `None`
+    #[serde(default)]
+    pub nulls: Option<crate::NullsOrder>,
 }
 
 impl Sort {
@@ -491,19 +524,18 @@ impl Sort {
         Self {
             column: column.into(),
             order,
+            nulls: None,
         }
     }
 
     /// Create a new sort with boolean flag for ascending
     pub fn new_bool(column: impl Into<String>, ascending: bool) -> Self {
-        Self {
-            column: column.into(),
-            order: if ascending {
-                crate::SortOrder::Asc
-            } else {
-                crate::SortOrder::Desc
-            },
-        }
+        let order = if ascending {
+            crate::SortOrder::Asc
+        } else {
+            crate::SortOrder::Desc
+        };
+        Self::new(column, order)
     }
 
     /// Create an ascending sort
@@ -515,4 +547,16 @@ impl Sort {
     pub fn desc(column: impl Into<String>) -> Self {
         Self::new(column, crate::SortOrder::Desc)
     }
+
+    /// Sort `NULL`s before all other values
+    pub fn nulls_first(mut self) -> Self {
+        self.nulls = Some(crate::NullsOrder::First);
+        self
+    }
+
+    /// Sort `NULL`s after all other values
+    pub fn nulls_last(mut self) -> Self {
+        self.nulls = Some(crate::NullsOrder::Last);
+        self
+    }
 }
@@ -86,48 +86,76 @@
 //!
 //! Smart create-or-update operations for efficient data management:
 //!
-//! ```rust
-//! use libsql_orm::{Model, Database};
-//!
+//! ```rust,no_run
+//! # use libsql_orm::{Model, Database};
+//! # use serde::{Deserialize, Serialize};
+//! # #[derive(Model, Clone, Serialize, Deserialize)]
+//! # struct User {
+//! #     pub id: Option<i64>,
+//! #     pub name: String,
+//! #     pub email: String,
+//! #     pub username: String,
+//! # }
+//! # async fn example(db: &Database) -> libsql_orm::Result<()> {
 //! // Method 1: create_or_update (based on primary key)
-//! let user = User { id: Some(123), name: "John".to_string(), ... };
-//! let saved = user.create_or_update(&db).await?;  // Updates if ID exists, creates if not
+//! let user = User {
+//!     id: Some(123),
+//!     name: "John".to_string(),
+//!     email: "john@example.com".to_string(),
+//!     username: "john".to_string(),
+//! };
+//! let saved = user.create_or_update(db).await?;  // Updates if ID exists, creates if not
 //!
 //! // Method 2: upsert (based on unique constraints)
-//! let user = User { id: None, email: "john@example.com".to_string(), ... };
-//! let saved = user.upsert(&["email"], &db).await?;  // Updates if email exists, creates if not
+//! let user = User { id: None, ..saved };
+//! let saved = user.upsert(&["email"], db).await?;  // Updates if email exists, creates if not
 //!
 //! // Multiple unique constraints
-//! let saved = user.upsert(&["email", "username"], &db).await?;
+//! let saved = saved.upsert(&["email", "username"], db).await?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! ## 📝 Built-in Logging
 //!
 //! Comprehensive logging for debugging and monitoring:
 //!
-//! ```rust
+//! ```rust,no_run
+//! # use libsql_orm::{Model, Database};
+//! # use serde::{Deserialize, Serialize};
+//! # #[derive(Model, Clone, Serialize, Deserialize)]
+//! # #[table_name("users")]
+//! # struct User {
+//! #     pub id: Option<i64>,
+//! #     pub name: String,
+//! #     pub email: String,
+//! # }
+//! # async fn example(db: &Database) -> libsql_orm::Result<()> {
 //! // All database operations are automatically logged
 //! // Logs appear in browser console (WASM) or standard logging (native)
 //!
-//! let user = User::new("John", "john@example.com");
+//! let user = User { id: None, name: "John".into(), email: "john@example.com".into() };
 //!
 //! // Logs: [INFO] users: Creating record in table: users
 //! // Logs: [DEBUG] users: SQL: INSERT INTO users (...) VALUES (...)
-//! let saved = user.create(&db).await?;
+//! let saved = user.create(db).await?;
 //!
 //! // Logs: [DEBUG] users: Finding record by ID: 123
-//! let found = User::find_by_id(123, &db).await?;
+//! let found = User::find_by_id(123, db).await?;
 //!
 //! // Logs: [INFO] users: Updating record with ID: 123
-//! let updated = found.unwrap().update(&db).await?;
+//! let updated = found.unwrap().update(db).await?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! ## 📚 Advanced Usage
 //!
 //! ### Custom Table Names and Boolean Type Safety
 //!
-//! ```rust
+//! ```rust,no_run
 //! use libsql_orm::{Model, orm_column, deserialize_bool};
+//! use libsql_orm::{Database, Filter, FilterOperator};
 //! use serde::{Serialize, Deserialize};
 //!
 //! #[derive(Model, Debug, Clone, Serialize, Deserialize)]
@@ -150,32 +178,43 @@
 //!     pub status: String,
 //! }
 //!
+//! # async fn example(db: &Database) -> libsql_orm::Result<()> {
 //! // Boolean filtering works seamlessly
 //! let active_users = User::find_where(
-//!     FilterOperator::Eq("is_active".to_string(), Value::Boolean(true)),
-//!     &db
+//!     FilterOperator::Single(Filter::eq("is_active", true)),
+//!     db
 //! ).await?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! ### Query Builder
 //!
 //! ```rust
-//! use libsql_orm::{QueryBuilder, FilterOperator, Sort, SortOrder, Pagination};
+//! use libsql_orm::{Filter, FilterOperator, QueryBuilder, Sort, SortOrder};
 //!
 //! // Complex query with filtering and pagination
 //! let query = QueryBuilder::new("users")
-//!     .select(&["id", "name", "email"])
-//!     .r#where(FilterOperator::Gte("age".to_string(), Value::Integer(18)))
+//!     .select(vec!["id", "name", "email"])
+//!     .r#where(FilterOperator::Single(Filter::ge("age", 18i64)))
 //!     .order_by(Sort::new("created_at", SortOrder::Desc))
 //!     .limit(10)
 //!     .offset(20);
 //!
-//! let (sql, params) = query.build()?;
+//! let (sql, params) = query.build().unwrap();
+//! assert_eq!(
+//!     sql,
+//!     "SELECT id, name, email FROM users WHERE age >= ? ORDER BY created_at DESC LIMIT 10 OFFSET 20"
+//! );
+//! assert_eq!(params, vec![libsql::Value::Integer(18)]);
 //! ```
 //!
 //! ### Cloudflare Workers Integration
 //!
-//! ```rust
+//! Needs the `worker` crate, which libsql-orm doesn't depend on, so the example
+//! isn't compiled.
+//!
+//! ```rust,ignore
 //! use worker::*;
 //! use libsql_orm::{Model, Database, MigrationManager, generate_migration};
 //!
@@ -194,6 +233,7 @@
 //!     Response::from_json(&users)
 //! }
 //! ```
+pub mod cipher;
 pub mod database;
 pub mod error;
 pub mod filters;
@@ -206,16 +246,17 @@ pub mod types;
 #[cfg(test)]
 mod tests;
 
+pub use cipher::{EncryptedColumns, FieldCipher};
 pub use database::Database;
 pub use error::{Error, Result};
 pub use filters::{Filter, FilterOperator, SearchFilter, Sort};
 pub use model::Model;
 pub use pagination::{CursorPaginatedResult, CursorPagination, PaginatedResult, Pagination};
-pub use query::{QueryBuilder, QueryResult};
+pub use query::{CaseExpr, QueryBuilder, QueryResult};
 pub use types::*;
 
-// Export the boolean deserializer
-pub use types::deserialize_bool;
+// Export the boolean and timestamp deserializers
+pub use types::{deserialize_bool, deserialize_datetime};
 
 // Re-export commonly used types
 pub use chrono;
@@ -14,7 +14,7 @@
 //! use libsql_orm::Model;
 //! use serde::{Serialize, Deserialize};
 //!
-//! #[derive(Model, Serialize, Deserialize)]
+//! #[derive(Model, Clone, Serialize, Deserialize)]
 //! #[table_name("user_accounts")]  // Custom table name
 //! struct User {
 //!     pub id: Option<i64>,
@@ -22,11 +22,13 @@
 //!     pub is_active: bool,     // ✅ Automatic boolean conversion
 //!     pub is_verified: bool,   // ✅ Type-safe operations
 //! }
+//!
+//! assert_eq!(User::table_name(), "user_accounts");
 //! ```
 
 use crate::{
-    Aggregate, Database, Error, FilterOperator, PaginatedResult, Pagination, QueryBuilder, Result,
-    SearchFilter, Sort,
+    Aggregate, Database, EncryptedColumns, Error, FilterOperator, PaginatedResult, Pagination,
+    QueryBuilder, Result, SearchFilter, Sort,
 };
 use std::collections::HashMap;
 
@@ -43,6 +45,81 @@ fn mask_id(id: i64) -> String {
     format!("{}{}", &id_str[..visible_digits], "*".repeat(masked_digits))
 }
 
+/// Query over the model's table, decrypting its encrypted columns
+fn model_query<M: Model>() -> QueryBuilder {
+    QueryBuilder::new(M::table_name()).encrypted(M::encrypted_columns())
+}
+
+/// Inserts `model` without the `omit` columns, with `computed` ones set from
+/// subqueries and the `encrypted` ones encrypted, see
+/// [`Model::create_with_defaults`], [`Model::create_computed`] and
+/// [`Model::create_encrypted`]
+async fn insert_model<M: Model>(
+    model: &M,
+    omit: &[&str],
+    computed: &[(&str, QueryBuilder)],
+    encrypted: &EncryptedColumns,
+    db: &Database,
+) -> Result<M> {
+    let mut map = model.to_map()?;
+    let known = |column: &str, map: &mut HashMap<String, crate::Value>| {
+        map.remove(column).map(|_| ()).ok_or_else(|| {
+            Error::Validation(format!(
+                "Unknown column {column} for table {}",
+                M::table_name()
+            ))
+        })
+    };
+    for column in omit {
+        known(column, &mut map)?;
+    }
+    for (column, _) in computed {
+        known(column, &mut map)?;
+    }
+
+    let mut columns: Vec<String> = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+    let mut params: Vec<libsql::Value> = Vec::new();
+    for (column, value) in &map {
+        columns.push(column.clone());
+        values.push("?".to_string());
+        params.push(encrypted.encrypt(column, M::value_to_libsql_value(value))?);
+    }
+    for (column, subquery) in computed {
+        let (subquery_sql, subquery_params) =
+            subquery.build_nested(|| format!("computed column {column}"))?;
+        columns.push(column.to_string());
+        values.push(format!("({subquery_sql})"));
+        params.extend(subquery_params);
+    }
+
+    let sql = if columns.is_empty() {
+        format!("INSERT INTO {} DEFAULT VALUES", M::table_name())
+    } else {
+        format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            M::table_name(),
+            columns.join(", "),
+            values.join(", ")
+        )
+    };
+
+    M::log_info(&format!("Creating record in table: {}", M::table_name()));
+    M::log_debug(&format!("SQL: {sql}"));
+
+    db.inner.execute(&sql, params).await?;
+    let id = db.inner.last_insert_rowid();
+
+    let mut result = model.clone();
+    result.set_primary_key(id);
+
+    M::log_info(&format!(
+        "Successfully created record with ID: {}",
+        mask_id(id)
+    ));
+    Ok(result)
+}
+
 /// Core trait for all database models
 #[allow(async_fn_in_trait)]
 pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
@@ -72,38 +149,76 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
     /// Create a model from a HashMap
     fn from_map(map: HashMap<String, crate::Value>) -> Result<Self>;
 
+    /// Columns stored encrypted, with the cipher of each
+    ///
+    /// Every write of the model encrypts them and every read through its
+    /// methods decrypts them, so no path stores plaintext. Empty by default;
+    /// implementations usually clone a set built once from the app's keys.
+    fn encrypted_columns() -> EncryptedColumns {
+        EncryptedColumns::default()
+    }
+
     /// Create a new record in the database
     async fn create(&self, db: &Database) -> Result<Self> {
-        let map = self.to_map()?;
-        let columns: Vec<String> = map.keys().cloned().collect();
-        let values: Vec<String> = map.keys().map(|_| "?".to_string()).collect();
-
-        let sql = format!(
-            "INSERT INTO {} ({}) VALUES ({})",
-            Self::table_name(),
-            columns.join(", "),
-            values.join(", ")
-        );
-
-        Self::log_info(&format!("Creating record in table: {}", Self::table_name()));
-        Self::log_debug(&format!("SQL: {sql}"));
-
-        let params: Vec<libsql::Value> = map
-            .values()
-            .map(|v| Self::value_to_libsql_value(v))
-            .collect();
-
-        db.inner.execute(&sql, params).await?;
-        let id = db.inner.last_insert_rowid();
-
-        let mut result = self.clone();
-        result.set_primary_key(id);
+        insert_model(self, &[], &[], &Self::encrypted_columns(), db).await
+    }
+
+    /// Create a new record, leaving `db_defaults` out of the INSERT so their
+    /// column `DEFAULT` applies
+    ///
+    /// E.g. `&["created_at"]` with `created_at DEFAULT CURRENT_TIMESTAMP` takes
+    /// the time from the database instead of the app server's clock. SQLite has
+    /// no `DEFAULT` keyword for VALUES, omitting the column is how it's spelled.
+    /// The returned model still holds the Rust values of the omitted fields,
+    /// reload it with [`Model::find_by_id`] to see the defaults. Note that
+    /// `CURRENT_TIMESTAMP` is written as `YYYY-MM-DD HH:MM:SS`; read such
+    /// `DateTime<Utc>` fields with [`crate::deserialize_datetime`].
+    async fn create_with_defaults(&self, db_defaults: &[&str], db: &Database) -> Result<Self> {
+        insert_model(self, db_defaults, &[], &Self::encrypted_columns(), db).await
+    }
+
+    /// Create a new record, setting `computed` columns from scalar subqueries
+    ///
+    /// Each builder becomes `column = (SELECT ...)` inside the same INSERT, with
+    /// its params bound in place, so e.g. appending at the end of a manually
+    /// ordered list can't race another append. The Rust values of those fields
+    /// are ignored and not updated in the returned model.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use libsql_orm::{Database, Filter, FilterOperator, Model, QueryBuilder};
+    /// # use serde::{Deserialize, Serialize};
+    /// # #[derive(Model, Clone, Serialize, Deserialize)]
+    /// # #[table_name("items")]
+    /// # struct Item {
+    /// #     id: Option<i64>,
+    /// #     owner_id: String,
+    /// #     position: Option<i64>,
+    /// # }
+    /// # async fn append(item: Item, db: &Database) -> libsql_orm::Result<Item> {
+    /// let next_position = QueryBuilder::new("items")
+    ///     .select(vec!["COALESCE(MAX(position), 0) + 1"])
+    ///     .r#where(FilterOperator::Single(Filter::eq("owner_id", item.owner_id.clone())));
+    /// let item = item.create_computed(vec![("position", next_position)], db).await?;
+    /// # Ok(item)
+    /// # }
+    /// ```
+    async fn create_computed(
+        &self,
+        computed: Vec<(&str, QueryBuilder)>,
+        db: &Database,
+    ) -> Result<Self> {
+        insert_model(self, &[], &computed, &Self::encrypted_columns(), db).await
+    }
 
-        Self::log_info(&format!(
-            "Successfully created record with ID: {}",
-            mask_id(id)
-        ));
-        Ok(result)
+    /// Create a new record, encrypting the `encrypted` columns before binding
+    ///
+    /// For models whose [`Model::encrypted_columns`] are empty, which the other
+    /// methods then don't decrypt. The returned model keeps the plaintext. Read
+    /// the rows back with [`QueryBuilder::encrypted`].
+    async fn create_encrypted(&self, encrypted: &EncryptedColumns, db: &Database) -> Result<Self> {
+        insert_model(self, &[], &[], encrypted, db).await
     }
 
     /// Create or update a record based on whether it has a primary key
@@ -136,8 +251,17 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
     }
 
     /// Create or update a record based on unique constraints
+    ///
+    /// The unique columns can't be encrypted ones, their ciphertext differs
+    /// on every write.
     async fn upsert(&self, unique_columns: &[&str], db: &Database) -> Result<Self> {
         let map = self.to_map()?;
+        let encrypted = Self::encrypted_columns();
+        if let Some(column) = unique_columns.iter().find(|c| encrypted.contains(c)) {
+            return Err(Error::Validation(format!(
+                "Encrypted column {column} can't identify records for upsert"
+            )));
+        }
 
         // Build WHERE clause for unique columns
         let mut where_conditions = Vec::new();
@@ -204,6 +328,7 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         }
 
         let mut results = Vec::new();
+        let encrypted = Self::encrypted_columns();
         // Note: Manual transaction handling for WASM
         db.inner
             .execute("BEGIN", vec![libsql::Value::Null; 0])
@@ -221,10 +346,19 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
                 values.join(", ")
             );
 
-            let params: Vec<libsql::Value> = map
-                .values()
-                .map(|v| Self::value_to_libsql_value(v))
-                .collect();
+            let params = map
+                .iter()
+                .map(|(k, v)| encrypted.encrypt(k, Self::value_to_libsql_value(v)))
+                .collect::<Result<Vec<_>>>();
+            let params = match params {
+                Ok(params) => params,
+                Err(err) => {
+                    db.inner
+                        .execute("ROLLBACK", vec![libsql::Value::Null; 0])
+                        .await?;
+                    return Err(err);
+                }
+            };
 
             db.inner.execute(&sql, params).await?;
             let id = db.inner.last_insert_rowid();
@@ -257,7 +391,14 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
             .await?;
 
         if let Some(row) = rows.next().await? {
-            let map = Self::row_to_map(&row)?;
+            let encrypted = Self::encrypted_columns();
+            let map = Self::row_to_map(&row)?
+                .into_iter()
+                .map(|(k, v)| {
+                    let value = encrypted.decrypt(&k, Self::value_to_libsql_value(&v))?;
+                    Ok((k, Self::libsql_value_to_value(&value)))
+                })
+                .collect::<Result<HashMap<_, _>>>()?;
             Self::log_debug(&format!("Found record with ID: {}", mask_id(id)));
             Ok(Some(Self::from_map(map)?))
         } else {
@@ -268,9 +409,7 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
 
     /// Find a single record by a specific condition
     async fn find_one(filter: FilterOperator, db: &Database) -> Result<Option<Self>> {
-        let builder = QueryBuilder::new(Self::table_name())
-            .r#where(filter)
-            .limit(1);
+        let builder = model_query::<Self>().r#where(filter).limit(1);
 
         let results = builder.execute::<Self>(db).await?;
         Ok(results.into_iter().next())
@@ -278,13 +417,13 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
 
     /// Find all records
     async fn find_all(db: &Database) -> Result<Vec<Self>> {
-        let builder = QueryBuilder::new(Self::table_name());
+        let builder = model_query::<Self>();
         builder.execute::<Self>(db).await
     }
 
     /// Find records with a filter
     async fn find_where(filter: FilterOperator, db: &Database) -> Result<Vec<Self>> {
-        let builder = QueryBuilder::new(Self::table_name()).r#where(filter);
+        let builder = model_query::<Self>().r#where(filter);
         builder.execute::<Self>(db).await
     }
 
@@ -293,7 +432,7 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: &Pagination,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        let builder = QueryBuilder::new(Self::table_name());
+        let builder = model_query::<Self>();
         builder.execute_paginated::<Self>(db, pagination).await
     }
 
@@ -303,7 +442,7 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: &Pagination,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        let builder = QueryBuilder::new(Self::table_name()).r#where(filter);
+        let builder = model_query::<Self>().r#where(filter);
         builder.execute_paginated::<Self>(db, pagination).await
     }
 
@@ -380,11 +519,12 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         Self::log_info(&format!("Updating record with ID: {}", mask_id(id)));
         Self::log_debug(&format!("SQL: {sql}"));
 
-        let mut params: Vec<libsql::Value> = map
+        let encrypted = Self::encrypted_columns();
+        let mut params = map
             .iter()
             .filter(|(k, _)| k != &Self::primary_key())
-            .map(|(_, v)| Self::value_to_libsql_value(v))
-            .collect();
+            .map(|(k, v)| encrypted.encrypt(k, Self::value_to_libsql_value(v)))
+            .collect::<Result<Vec<_>>>()?;
         params.push(libsql::Value::Integer(id));
 
         db.inner.execute(&sql, params).await?;
@@ -481,7 +621,7 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: Option<&Pagination>,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        let mut builder = QueryBuilder::new(Self::table_name());
+        let mut builder = model_query::<Self>();
 
         if let Some(sorts) = sort {
             builder = builder.order_by_multiple(sorts);
@@ -498,7 +638,7 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: Option<&Pagination>,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        let mut builder = QueryBuilder::new(Self::table_name()).r#where(filter);
+        let mut builder = model_query::<Self>().r#where(filter);
 
         if let Some(sorts) = sort {
             builder = builder.order_by_multiple(sorts);
@@ -509,8 +649,14 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
     }
 
     /// Execute a custom query
+    ///
+    /// The model's encrypted columns are decrypted unless the builder was
+    /// given its own with [`QueryBuilder::encrypted`].
     async fn query(builder: QueryBuilder, db: &Database) -> Result<Vec<Self>> {
-        builder.execute::<Self>(db).await
+        builder
+            .encrypted_or(Self::encrypted_columns())
+            .execute::<Self>(db)
+            .await
     }
 
     /// Execute a custom query with pagination
@@ -519,7 +665,10 @@ pub trait Model: Serialize + DeserializeOwned + Send + Sync + Clone {
         pagination: &Pagination,
         db: &Database,
     ) -> Result<PaginatedResult<Self>> {
-        builder.execute_paginated::<Self>(db, pagination).await
+        builder
+            .encrypted_or(Self::encrypted_columns())
+            .execute_paginated::<Self>(db, pagination)
+            .await
     }
 
     /// Get aggregate value
@@ -7,8 +7,14 @@
 //!
 //! # Offset-based Pagination
 //!
-//! ```rust
-//! use libsql_orm::{Pagination, PaginatedResult, Model};
+//! ```rust,no_run
+//! use libsql_orm::{Database, Pagination, PaginatedResult, Model};
+//! # use serde::{Deserialize, Serialize};
+//! # #[derive(Model, Clone, Serialize, Deserialize)]
+//! # struct User {
+//! #     id: Option<i64>,
+//! #     name: String,
+//! # }
 //!
 //! async fn paginate_users(db: &Database) -> Result<(), Box<dyn std::error::Error>> {
 //!     let pagination = Pagination::new(1, 10); // Page 1, 10 items per page
@@ -27,11 +33,11 @@
 //!
 //! # Cursor-based Pagination
 //!
-//! ```rust
-//! use libsql_orm::{CursorPagination, CursorPaginatedResult};
+//! ```rust,no_run
+//! use libsql_orm::{CursorPagination, Database};
 //!
 //! async fn cursor_paginate(db: &Database) -> Result<(), Box<dyn std::error::Error>> {
-//!     let mut cursor_pagination = CursorPagination::new(10);
+//!     let cursor_pagination = CursorPagination::new(10);
 //!     
 //!     loop {
 //!         // Implement cursor-based pagination logic here
@@ -87,9 +93,24 @@ impl Pagination {
         }
     }
 
-    /// Get the offset for SQL LIMIT/OFFSET
+    /// Create a pagination instance from a 1-based page number
+    ///
+    /// Page `0` is treated as the first page.
+    pub fn page(page: u32, per_page: u32) -> Self {
+        Self::new(page.max(1), per_page)
+    }
+
+    /// Get the 1-based page number derived from the offset and limit
+    pub fn current_page(&self) -> u32 {
+        if self.limit() == 0 {
+            return self.page.max(1);
+        }
+        self.offset() / self.limit() + 1
+    }
+
+    /// Get the offset for SQL LIMIT/OFFSET, `u32::MAX` for pages beyond it
     pub fn offset(&self) -> u32 {
-        (self.page - 1) * self.per_page
+        self.page.saturating_sub(1).saturating_mul(self.per_page)
     }
 
     /// Get the limit for SQL LIMIT/OFFSET
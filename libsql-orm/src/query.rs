@@ -7,32 +7,46 @@
 //! # Basic Usage
 //!
 //! ```rust
-//! use libsql_orm::{QueryBuilder, FilterOperator, Sort, SortOrder};
+//! use libsql_orm::{Filter, FilterOperator, QueryBuilder, Sort, SortOrder};
 //!
 //! let query = QueryBuilder::new("users")
 //!     .select(vec!["id", "name", "email"])
-//!     .r#where(FilterOperator::Eq("is_active".to_string(), Value::Boolean(true)))
+//!     .r#where(FilterOperator::Single(Filter::eq("is_active", true)))
 //!     .order_by(Sort::new("name", SortOrder::Asc))
 //!     .limit(10);
 //!
-//! let (sql, params) = query.build()?;
+//! let (sql, params) = query.build().unwrap();
+//! assert_eq!(
+//!     sql,
+//!     "SELECT id, name, email FROM users WHERE is_active = ? ORDER BY name ASC LIMIT 10"
+//! );
+//! assert_eq!(params, vec![libsql::Value::Integer(1)]);
 //! ```
 //!
 //! # Complex Queries
 //!
-//! ```rust
-//! use libsql_orm::{QueryBuilder, JoinType, FilterOperator, Aggregate};
+//! ```rust,no_run
+//! use libsql_orm::{Aggregate, Database, Filter, FilterOperator, JoinType, QueryBuilder, Sort};
+//! # use serde::Deserialize;
+//! # #[derive(Deserialize)]
+//! # struct OrderWithUser {
+//! #     name: String,
+//! #     order_count: i64,
+//! # }
+//! # async fn example(db: &Database) -> libsql_orm::Result<()> {
 //!
 //! let complex_query = QueryBuilder::new("orders")
 //!     .select(vec!["orders.id", "users.name", "products.title"])
 //!     .join(JoinType::Inner, "users", "users.id = orders.user_id")
 //!     .join(JoinType::Inner, "products", "products.id = orders.product_id")
-//!     .r#where(FilterOperator::Gte("orders.created_at".to_string(), Value::Text("2024-01-01".to_string())))
+//!     .r#where(FilterOperator::Single(Filter::ge("orders.created_at", "2024-01-01")))
 //!     .group_by(vec!["users.id"])
 //!     .aggregate(Aggregate::Count, "orders.id", Some("order_count"))
 //!     .order_by(Sort::desc("order_count"));
 //!
-//! let results = complex_query.execute::<OrderWithUser>(&db).await?;
+//! let results = complex_query.execute::<OrderWithUser>(db).await?;
+//! # Ok(())
+//! # }
 //! ```
 
 use crate::filters::FilterValue;
@@ -41,6 +55,10 @@ use crate::{
 };
 use std::collections::HashMap;
 
+/// Rows sampled by `count_distinct_estimate`; up to this many non-`NULL`
+/// values the count is exact
+const DISTINCT_SAMPLE_ROWS: u64 = 10_000;
+
 /// Query result wrapper
 ///
 /// Contains query results with optional total count for pagination support.
@@ -85,12 +103,12 @@ impl<T> QueryResult<T> {
 /// # Examples
 ///
 /// ```rust
-/// use libsql_orm::{QueryBuilder, FilterOperator, Sort, SortOrder, JoinType};
+/// use libsql_orm::{Aggregate, Filter, FilterOperator, JoinType, QueryBuilder, Sort, SortOrder};
 ///
 /// // Basic query
 /// let query = QueryBuilder::new("users")
 ///     .select(vec!["id", "name", "email"])
-///     .r#where(FilterOperator::Eq("is_active".to_string(), Value::Boolean(true)))
+///     .r#where(FilterOperator::Single(Filter::eq("is_active", true)))
 ///     .order_by(Sort::new("name", SortOrder::Asc))
 ///     .limit(10);
 ///
@@ -98,26 +116,136 @@ impl<T> QueryResult<T> {
 /// let joined_query = QueryBuilder::new("posts")
 ///     .select(vec!["posts.title", "users.name"])
 ///     .join(JoinType::Inner, "users", "users.id = posts.user_id")
-///     .r#where(FilterOperator::Eq("posts.published".to_string(), Value::Boolean(true)));
+///     .r#where(FilterOperator::Single(Filter::eq("posts.published", true)));
 ///
 /// // Aggregate query
 /// let agg_query = QueryBuilder::new("orders")
+///     .select(vec!["user_id"])
 ///     .aggregate(Aggregate::Sum, "amount", Some("total_amount"))
 ///     .group_by(vec!["user_id"])
-///     .having(FilterOperator::Gt("total_amount".to_string(), Value::Real(1000.0)));
+///     .having(FilterOperator::Single(Filter::gt("total_amount", 1000.0)));
+///
+/// for builder in [query, joined_query, agg_query] {
+///     assert!(builder.build().is_ok());
+/// }
 /// ```
 pub struct QueryBuilder {
     table: String,
     select_columns: Vec<String>,
+    /// Scalar subqueries selected after the columns, with their alias
+    select_subqueries: Vec<(QueryBuilder, String)>,
+    /// Computed columns selected after the subqueries, in the order added
+    select_exprs: Vec<SelectExpr>,
     joins: Vec<JoinClause>,
     where_clauses: Vec<FilterOperator>,
+    where_combinator: crate::Combinator,
+    /// Values of the `:name` placeholders in raw and custom conditions
+    named_params: Vec<(String, Value)>,
     group_by: Vec<String>,
     having: Vec<FilterOperator>,
+    /// Raw HAVING expressions with their own parameters, ANDed after `having`
+    having_raw: Vec<(String, Vec<libsql::Value>)>,
     order_by: Vec<Sort>,
+    order_random: bool,
     limit: Option<u32>,
+    max_limit: Option<u32>,
     offset: Option<u32>,
+    /// 1-based page from `paginate`, its offset is taken from the clamped limit
+    page: Option<u32>,
     distinct: bool,
-    aggregate: Option<AggregateClause>,
+    aggregates: Vec<AggregateClause>,
+    index_hint: Option<IndexHint>,
+    source_params: Vec<Value>,
+    debug_explain: bool,
+    unions: Vec<(String, QueryBuilder)>,
+    /// Common table expressions in declaration order, with their name
+    ctes: Vec<(String, QueryBuilder)>,
+    recursive_ctes: bool,
+    label: Option<String>,
+    /// Failure of a subquery that had to be built when it was added
+    nested_error: Option<crate::Error>,
+    encrypted: crate::EncryptedColumns,
+    /// Columns and rows of `build_insert`
+    insert_columns: Vec<String>,
+    insert_rows: Vec<Vec<Value>>,
+    /// Assignments of `build_update`
+    set_values: Vec<(String, Value)>,
+    /// Whether `build_delete` may delete without a WHERE clause
+    allow_full_delete: bool,
+    /// Whether `build_update` may update without a WHERE clause
+    allow_full_update: bool,
+    quote_identifiers: bool,
+    /// Columns of the RETURNING clause of insert, update and delete
+    returning: Vec<String>,
+}
+
+/// `CASE WHEN ... THEN ... ELSE ... END` column for [`QueryBuilder::select_case`]
+///
+/// The THEN and ELSE values are bound as parameters. Without an ELSE value,
+/// rows matching no branch get `NULL`.
+#[derive(Debug, Clone, Default)]
+pub struct CaseExpr {
+    branches: Vec<(FilterOperator, Value)>,
+    else_value: Option<Value>,
+    alias: Option<String>,
+}
+
+impl CaseExpr {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a branch, checked in the order they were added
+    pub fn when(mut self, condition: FilterOperator, then: Value) -> Self {
+        self.branches.push((condition, then));
+        self
+    }
+
+    /// Value when no branch matches
+    pub fn else_(mut self, value: Value) -> Self {
+        self.else_value = Some(value);
+        self
+    }
+
+    pub fn as_alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+}
+
+/// Computed column of the select list
+#[derive(Clone)]
+enum SelectExpr {
+    Case(CaseExpr),
+    Window {
+        function: crate::WindowFunc,
+        partition_by: Vec<String>,
+        order_by: Vec<Sort>,
+        alias: String,
+    },
+    Coalesce {
+        exprs: Vec<String>,
+        alias: Option<String>,
+    },
+    Raw {
+        expr: String,
+        params: Vec<Value>,
+    },
+}
+
+impl SelectExpr {
+    fn conditions(&self) -> Vec<&FilterOperator> {
+        match self {
+            SelectExpr::Case(case) => case
+                .branches
+                .iter()
+                .map(|(condition, _)| condition)
+                .collect(),
+            SelectExpr::Window { .. } | SelectExpr::Coalesce { .. } | SelectExpr::Raw { .. } => {
+                Vec::new()
+            }
+        }
+    }
 }
 
 /// Join clause for complex queries
@@ -125,7 +253,23 @@ struct JoinClause {
     join_type: crate::JoinType,
     table: String,
     alias: Option<String>,
-    condition: String,
+    constraint: JoinConstraint,
+}
+
+/// How the rows of a joined table are matched
+#[derive(Clone)]
+enum JoinConstraint {
+    On(String),
+    Using(Vec<String>),
+    /// Cross joins pair all rows
+    None,
+}
+
+/// SQLite index hint for the FROM table
+#[derive(Clone)]
+enum IndexHint {
+    IndexedBy(String),
+    NotIndexed,
 }
 
 /// Aggregate clause for aggregation queries
@@ -141,16 +285,311 @@ impl QueryBuilder {
         Self {
             table: table.into(),
             select_columns: vec!["*".to_string()],
+            select_subqueries: Vec::new(),
+            select_exprs: Vec::new(),
             joins: Vec::new(),
             where_clauses: Vec::new(),
+            where_combinator: crate::Combinator::And,
+            named_params: Vec::new(),
             group_by: Vec::new(),
             having: Vec::new(),
+            having_raw: Vec::new(),
             order_by: Vec::new(),
+            order_random: false,
             limit: None,
+            max_limit: None,
             offset: None,
+            page: None,
             distinct: false,
-            aggregate: None,
+            aggregates: Vec::new(),
+            index_hint: None,
+            source_params: Vec::new(),
+            debug_explain: false,
+            unions: Vec::new(),
+            ctes: Vec::new(),
+            recursive_ctes: false,
+            label: None,
+            nested_error: None,
+            encrypted: crate::EncryptedColumns::default(),
+            insert_columns: Vec::new(),
+            insert_rows: Vec::new(),
+            set_values: Vec::new(),
+            allow_full_delete: false,
+            allow_full_update: false,
+            quote_identifiers: false,
+            returning: Vec::new(),
+        }
+    }
+
+    /// Create a query over an inline `VALUES` lookup table
+    ///
+    /// SQLite has no `AS alias(col1, col2)` column list for `VALUES`, so the rows are
+    /// wrapped in a sub-select that names the columns:
+    /// `FROM (SELECT column1 AS col1, column2 AS col2 FROM (VALUES (?, ?), (?, ?))) AS alias`.
+    /// All values are bound as parameters, ahead of any WHERE parameters.
+    pub fn from_values(rows: Vec<Vec<Value>>, columns: &[&str], alias: &str) -> Result<Self> {
+        if rows.is_empty() || columns.is_empty() {
+            return Err(crate::Error::Query(
+                "VALUES source needs at least one row and one column".to_string(),
+            ));
+        }
+        if let Some(row) = rows.iter().find(|row| row.len() != columns.len()) {
+            return Err(crate::Error::Query(format!(
+                "VALUES row has {} values but {} columns were named",
+                row.len(),
+                columns.len()
+            )));
+        }
+
+        let placeholders = vec!["?"; columns.len()].join(", ");
+        let values = vec![format!("({placeholders})"); rows.len()].join(", ");
+        let named: Vec<String> = columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| format!("column{} AS {column}", i + 1))
+            .collect();
+
+        let mut builder = Self::new(format!(
+            "(SELECT {} FROM (VALUES {values})) AS {alias}",
+            named.join(", ")
+        ));
+        builder.source_params = rows.into_iter().flatten().collect();
+        Ok(builder)
+    }
+
+    /// Create a query over the rows of `subquery`, a derived table named `alias`
+    ///
+    /// Renders `FROM (<subquery>) AS alias`, e.g. to aggregate over groups. The
+    /// subquery's parameters are bound ahead of any WHERE parameters. A failing
+    /// subquery fails the building of this query.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Aggregate, QueryBuilder};
+    ///
+    /// let counts = QueryBuilder::new("items")
+    ///     .select(vec!["COUNT(*) AS cnt"])
+    ///     .group_by(vec!["owner_id"]);
+    /// let (sql, _) = QueryBuilder::from_subquery(counts, "counts")
+    ///     .aggregate(Aggregate::Avg, "cnt", Some("avg_items"))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT AVG(cnt) AS avg_items FROM (SELECT COUNT(*) AS cnt FROM items GROUP BY owner_id) AS counts"
+    /// );
+    /// ```
+    pub fn from_subquery(subquery: QueryBuilder, alias: &str) -> Self {
+        match subquery.build_nested(|| format!("from_subquery({alias})")) {
+            Ok((sql, params)) => {
+                let mut builder = Self::new(format!("({sql}) AS {alias}"));
+                builder.source_params = params.into_iter().map(Value::from).collect();
+                builder
+            }
+            Err(err) => {
+                let mut builder = Self::new(alias);
+                builder.nested_error = Some(err);
+                builder
+            }
+        }
+    }
+
+    /// Combine any number of builders with `UNION ALL`
+    ///
+    /// Parameters are concatenated in builder order. All builders must select the
+    /// same number of columns and must not carry ORDER BY, LIMIT or OFFSET; add
+    /// those to the returned builder, where they apply to the combined result.
+    pub fn union_all_many(builders: Vec<QueryBuilder>) -> Result<Self> {
+        let mut builders = builders.into_iter();
+        let mut base = builders.next().ok_or_else(|| {
+            crate::Error::Query("union_all_many needs at least one builder".to_string())
+        })?;
+        base.check_compound_member()?;
+        for member in builders {
+            member.check_compound_member()?;
+            base.check_union_arity("UNION ALL", &member)?;
+            base.unions.push(("UNION ALL".to_string(), member));
+        }
+        Ok(base)
+    }
+
+    /// Append the rows of `other`, without duplicates, as `... UNION <other>`
+    ///
+    /// Parameters of `other` follow those of this builder. ORDER BY, LIMIT and
+    /// OFFSET of this builder apply to the combined result, `other` must not
+    /// have any and must select as many columns; otherwise the building fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let pending = QueryBuilder::new("items")
+    ///     .select(vec!["task"])
+    ///     .r#where(FilterOperator::Single(Filter::eq("completed", false)));
+    /// let recent = QueryBuilder::new("items")
+    ///     .select(vec!["task"])
+    ///     .r#where(FilterOperator::Single(Filter::gt("completed_at", "2024-06-01")));
+    /// let (sql, _) = pending.union(recent).build().unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT task FROM items WHERE completed = ? UNION SELECT task FROM items WHERE completed_at > ?"
+    /// );
+    /// ```
+    pub fn union(self, other: QueryBuilder) -> Self {
+        self.push_compound("UNION", other)
+    }
+
+    /// Append all rows of `other` as `... UNION ALL <other>`, see [`QueryBuilder::union`]
+    pub fn union_all(self, other: QueryBuilder) -> Self {
+        self.push_compound("UNION ALL", other)
+    }
+
+    fn push_compound(mut self, operator: &str, member: QueryBuilder) -> Self {
+        match member
+            .check_compound_member()
+            .and_then(|()| self.check_union_arity(operator, &member))
+        {
+            Ok(()) => self.unions.push((operator.to_string(), member)),
+            Err(err) => {
+                self.nested_error.get_or_insert(err);
+            }
+        }
+        self
+    }
+
+    fn check_union_arity(&self, operator: &str, member: &QueryBuilder) -> Result<()> {
+        if let (Some(expected), Some(found)) = (self.select_arity(), member.select_arity()) {
+            if expected != found {
+                return Err(crate::Error::Query(format!(
+                    "{operator} member selects {found} columns, expected {expected}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of selected columns, `None` when it depends on a `*`
+    fn select_arity(&self) -> Option<usize> {
+        let columns = self.plain_select_columns();
+        if columns.iter().any(|c| c.contains('*')) {
+            return None;
+        }
+        Some(
+            columns.len()
+                + self.select_subqueries.len()
+                + self.select_exprs.len()
+                + self.aggregates.len(),
+        )
+    }
+
+    /// Selected columns, without the default `*` once there are aggregates
+    fn plain_select_columns(&self) -> &[String] {
+        if !self.aggregates.is_empty() && self.select_columns == ["*"] {
+            &[]
+        } else {
+            &self.select_columns
+        }
+    }
+
+    fn check_compound_member(&self) -> Result<()> {
+        if !self.ctes.is_empty() {
+            return Err(crate::Error::Query(format!(
+                "compound member on {} must not use WITH, add it to the first builder",
+                self.table
+            )));
         }
+        if !self.order_by.is_empty()
+            || self.order_random
+            || self.limit.is_some()
+            || self.offset.is_some()
+            || self.page.is_some()
+        {
+            return Err(crate::Error::Query(format!(
+                "compound member on {} must not use ORDER BY, LIMIT or OFFSET",
+                self.table
+            )));
+        }
+        Ok(())
+    }
+
+    /// Define `query` as the common table expression `name`, for this query
+    ///
+    /// Prepends `WITH name AS (...)`, several calls are comma-separated in
+    /// declaration order. `name` may list the columns, e.g. `totals(task, sum)`.
+    /// The parameters of the expressions are bound before those of the query.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let open = QueryBuilder::new("items")
+    ///     .r#where(FilterOperator::Single(Filter::eq("completed", false)));
+    /// let (sql, _) = QueryBuilder::new("open_items")
+    ///     .with("open_items", open)
+    ///     .select(vec!["task"])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "WITH open_items AS (SELECT * FROM items WHERE completed = ?) SELECT task FROM open_items"
+    /// );
+    /// ```
+    pub fn with(mut self, name: &str, query: QueryBuilder) -> Self {
+        self.ctes.push((name.to_string(), query));
+        self
+    }
+
+    /// Like [`QueryBuilder::with`], but the expression may refer to itself
+    ///
+    /// Usually `query` is an anchor select combined by `union_all` with a
+    /// select joining `name`, e.g. to walk a category tree. Any recursive
+    /// expression makes the whole clause `WITH RECURSIVE`.
+    pub fn with_recursive(mut self, name: &str, query: QueryBuilder) -> Self {
+        self.recursive_ctes = true;
+        self.with(name, query)
+    }
+
+    /// Build the WITH clause, empty without common table expressions
+    fn build_with(&self) -> Result<(String, Vec<libsql::Value>)> {
+        if self.ctes.is_empty() {
+            return Ok((String::new(), Vec::new()));
+        }
+        let mut expressions = Vec::new();
+        let mut params = Vec::new();
+        for (name, query) in &self.ctes {
+            let (cte_sql, cte_params) = query.build_nested(|| format!("with({name})"))?;
+            expressions.push(format!("{name} AS ({cte_sql})"));
+            params.extend(cte_params);
+        }
+        let keyword = if self.recursive_ctes {
+            "WITH RECURSIVE"
+        } else {
+            "WITH"
+        };
+        Ok((format!("{keyword} {} ", expressions.join(", ")), params))
+    }
+
+    /// Name this builder in errors when it is nested in another query
+    ///
+    /// A failing union member or subquery is otherwise described by its
+    /// position, e.g. `UNION ALL member 2`.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Build as part of another query, prefixing errors with the label or `context`
+    pub(crate) fn build_nested(
+        &self,
+        context: impl FnOnce() -> String,
+    ) -> Result<(String, Vec<libsql::Value>)> {
+        self.build().map_err(|err| match &self.label {
+            Some(label) => err.with_context(label),
+            None => err.with_context(&context()),
+        })
     }
 
     /// Select specific columns
@@ -159,6 +598,246 @@ impl QueryBuilder {
         self
     }
 
+    /// Select the single value of `subquery` as `alias`, next to the columns
+    ///
+    /// The subquery may refer to the outer table's columns, which makes it
+    /// correlated and evaluated per row:
+    /// `(SELECT COUNT(*) FROM item_tags WHERE item_id = items.id) AS tag_count`.
+    /// Such column comparisons need a `FilterOperator::Custom` condition, a
+    /// `Filter` would bind `items.id` as a text value. Its parameters are bound
+    /// ahead of those of the other clauses, matching their place in the SQL.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{FilterOperator, QueryBuilder};
+    ///
+    /// let tag_count = QueryBuilder::new("item_tags")
+    ///     .select_count()
+    ///     .r#where(FilterOperator::Custom("item_tags.item_id = items.id".to_string()));
+    /// let (sql, _) = QueryBuilder::new("items")
+    ///     .select(vec!["items.id", "items.task"])
+    ///     .select_subquery(tag_count, "tag_count")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT items.id, items.task, (SELECT COUNT(*) FROM item_tags \
+    ///      WHERE item_tags.item_id = items.id) AS tag_count FROM items"
+    /// );
+    /// ```
+    pub fn select_subquery(mut self, subquery: QueryBuilder, alias: &str) -> Self {
+        self.select_subqueries.push((subquery, alias.to_string()));
+        self
+    }
+
+    /// Select a computed `CASE` column, after the columns and subqueries
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{CaseExpr, Filter, FilterOperator, QueryBuilder, Value};
+    ///
+    /// let status = CaseExpr::new()
+    ///     .when(FilterOperator::Single(Filter::eq("completed", true)), Value::from("done"))
+    ///     .else_(Value::from("open"))
+    ///     .as_alias("status");
+    /// let (sql, params) = QueryBuilder::new("items")
+    ///     .select(vec!["task"])
+    ///     .select_case(status)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT task, CASE WHEN completed = ? THEN ? ELSE ? END AS status FROM items"
+    /// );
+    /// assert_eq!(params.len(), 3);
+    /// ```
+    pub fn select_case(mut self, case: CaseExpr) -> Self {
+        self.select_exprs.push(SelectExpr::Case(case));
+        self
+    }
+
+    /// Select a window function over the rows of each partition as `alias`
+    ///
+    /// Unlike a GROUP BY, every row is kept and gets its own value, e.g. its
+    /// rank among the rows of the same owner. With an empty `partition_by` all
+    /// rows form one partition.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{QueryBuilder, Sort, WindowFunc};
+    ///
+    /// let (sql, _) = QueryBuilder::new("items")
+    ///     .select(vec!["id"])
+    ///     .select_window(WindowFunc::RowNumber, vec!["owner_id"], vec![Sort::desc("created_at")], "rn")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT id, ROW_NUMBER() OVER (PARTITION BY owner_id ORDER BY created_at DESC) AS rn FROM items"
+    /// );
+    /// ```
+    pub fn select_window(
+        mut self,
+        function: crate::WindowFunc,
+        partition_by: Vec<&str>,
+        order_by: Vec<Sort>,
+        alias: &str,
+    ) -> Self {
+        self.select_exprs.push(SelectExpr::Window {
+            function,
+            partition_by: partition_by.into_iter().map(str::to_string).collect(),
+            order_by,
+            alias: alias.to_string(),
+        });
+        self
+    }
+
+    /// Select the first non-`NULL` of `exprs`, optionally as `alias`
+    ///
+    /// Handy for aggregates over a left join, which are `NULL` rather than 0
+    /// when nothing matched. The expressions are column names or literals and
+    /// are not bound.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::QueryBuilder;
+    ///
+    /// let (sql, _) = QueryBuilder::new("totals")
+    ///     .select(vec!["owner_id"])
+    ///     .select_coalesce(vec!["sum_amount", "0"], Some("amount"))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT owner_id, COALESCE(sum_amount, 0) AS amount FROM totals");
+    /// ```
+    pub fn select_coalesce(mut self, exprs: Vec<&str>, alias: Option<&str>) -> Self {
+        self.select_exprs.push(SelectExpr::Coalesce {
+            exprs: exprs.into_iter().map(str::to_string).collect(),
+            alias: alias.map(str::to_string),
+        });
+        self
+    }
+
+    /// Select a raw SQL expression, binding `params` to its `?` placeholders
+    ///
+    /// The expression is pasted into the query as is: keeping it safe is up to
+    /// the caller, never build it from user input. Its parameters are bound
+    /// ahead of the WHERE parameters. The building fails with
+    /// `Error::Validation` unless there is exactly one parameter per
+    /// placeholder.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{QueryBuilder, Value};
+    ///
+    /// let (sql, params) = QueryBuilder::new("items")
+    ///     .select(vec!["id"])
+    ///     .select_raw("strftime(?, created_at) AS yr", vec![Value::from("%Y")])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT id, strftime(?, created_at) AS yr FROM items");
+    /// assert_eq!(params, vec![libsql::Value::Text("%Y".to_string())]);
+    /// ```
+    pub fn select_raw(mut self, expr: &str, params: Vec<Value>) -> Self {
+        let (_, placeholders) = name_placeholders(expr);
+        if placeholders != params.len() {
+            self.nested_error
+                .get_or_insert(crate::Error::Validation(format!(
+                    "select expression `{expr}` has {placeholders} placeholders but {} parameters",
+                    params.len()
+                )));
+        }
+        self.select_exprs.push(SelectExpr::Raw {
+            expr: expr.to_string(),
+            params,
+        });
+        self
+    }
+
+    fn build_select_expr(&self, expr: &SelectExpr) -> Result<(String, Vec<libsql::Value>)> {
+        match expr {
+            SelectExpr::Case(case) => self.build_case(case),
+            SelectExpr::Window {
+                function,
+                partition_by,
+                order_by,
+                alias,
+            } => {
+                let mut over = Vec::new();
+                if !partition_by.is_empty() {
+                    over.push(format!("PARTITION BY {}", self.quote_all(partition_by)));
+                }
+                if !order_by.is_empty() {
+                    let sorts: Vec<String> =
+                        order_by.iter().map(|sort| self.render_sort(sort)).collect();
+                    over.push(format!("ORDER BY {}", sorts.join(", ")));
+                }
+                Ok((
+                    format!("{function} OVER ({}) AS {alias}", over.join(" ")),
+                    Vec::new(),
+                ))
+            }
+            SelectExpr::Coalesce { exprs, alias } => {
+                if exprs.is_empty() {
+                    return Err(crate::Error::Query(
+                        "COALESCE needs at least one expression".to_string(),
+                    ));
+                }
+                let coalesce = format!("COALESCE({})", self.quote_all(exprs));
+                match alias {
+                    Some(alias) => Ok((format!("{coalesce} AS {alias}"), Vec::new())),
+                    None => Ok((coalesce, Vec::new())),
+                }
+            }
+            SelectExpr::Raw { expr, params } => self.resolve_named(
+                expr,
+                params
+                    .iter()
+                    .map(|value| self.value_to_libsql_value(value))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn render_sort(&self, sort: &Sort) -> String {
+        let column = self.quote_identifier(&sort.column);
+        match sort.nulls {
+            Some(nulls) => format!("{column} {} {nulls}", sort.order),
+            None => format!("{column} {}", sort.order),
+        }
+    }
+
+    /// Build a CASE expression, condition and THEN params branch by branch
+    fn build_case(&self, case: &CaseExpr) -> Result<(String, Vec<libsql::Value>)> {
+        if case.branches.is_empty() {
+            return Err(crate::Error::Query(format!(
+                "CASE expression on {} needs at least one WHEN branch",
+                self.table
+            )));
+        }
+        let mut sql = "CASE".to_string();
+        let mut params = Vec::new();
+        for (condition, then) in &case.branches {
+            let (condition_sql, condition_params) = self.build_filter_operator(condition)?;
+            sql.push_str(&format!(" WHEN {condition_sql} THEN ?"));
+            params.extend(condition_params);
+            params.push(self.value_to_libsql_value(then));
+        }
+        if let Some(otherwise) = &case.else_value {
+            sql.push_str(" ELSE ?");
+            params.push(self.value_to_libsql_value(otherwise));
+        }
+        sql.push_str(" END");
+        if let Some(alias) = &case.alias {
+            sql.push_str(&format!(" AS {alias}"));
+        }
+        Ok((sql, params))
+    }
+
     /// Add a join clause
     pub fn join(
         mut self,
@@ -170,7 +849,7 @@ impl QueryBuilder {
             join_type,
             table: table.into(),
             alias: None,
-            condition: condition.into(),
+            constraint: JoinConstraint::On(condition.into()),
         });
         self
     }
@@ -187,17 +866,266 @@ impl QueryBuilder {
             join_type,
             table: table.into(),
             alias: Some(alias.into()),
-            condition: condition.into(),
+            constraint: JoinConstraint::On(condition.into()),
+        });
+        self
+    }
+
+    /// Pair every row with every row of `table`
+    ///
+    /// Renders `CROSS JOIN table` without an `ON` clause. Filter the
+    /// combinations with WHERE conditions; SQLite also keeps the join order of
+    /// a cross join as written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::QueryBuilder;
+    ///
+    /// let (sql, _) = QueryBuilder::new("items")
+    ///     .select(vec!["items.task", "categories.name"])
+    ///     .cross_join("categories")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT items.task, categories.name FROM items CROSS JOIN categories"
+    /// );
+    /// ```
+    pub fn cross_join(mut self, table: impl Into<String>) -> Self {
+        self.joins.push(JoinClause {
+            join_type: crate::JoinType::Cross,
+            table: table.into(),
+            alias: None,
+            constraint: JoinConstraint::None,
         });
         self
     }
 
+    /// Add a join matching rows on equal `columns` of both tables
+    ///
+    /// Renders `USING (c1, c2)` instead of an `ON` condition. Building fails
+    /// with `Error::Query` without any column.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{JoinType, QueryBuilder};
+    ///
+    /// let (sql, _) = QueryBuilder::new("items")
+    ///     .join_using(JoinType::Inner, "item_stock", vec!["owner_id", "task"])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT * FROM items INNER JOIN item_stock USING (owner_id, task)");
+    /// ```
+    pub fn join_using(
+        mut self,
+        join_type: crate::JoinType,
+        table: impl Into<String>,
+        columns: Vec<&str>,
+    ) -> Self {
+        let table = table.into();
+        if columns.is_empty() {
+            self.nested_error.get_or_insert(crate::Error::Query(format!(
+                "JOIN {table} USING needs at least one column"
+            )));
+        }
+        self.joins.push(JoinClause {
+            join_type,
+            table,
+            alias: None,
+            constraint: JoinConstraint::Using(columns.into_iter().map(str::to_string).collect()),
+        });
+        self
+    }
+
+    /// Force the planner to use `index_name` for the FROM table (`INDEXED BY`)
+    ///
+    /// This is an optimization escape hatch for the rare case where SQLite picks a
+    /// bad index; the query fails to prepare if the index does not exist.
+    pub fn indexed_by(mut self, index_name: &str) -> Self {
+        self.index_hint = Some(IndexHint::IndexedBy(index_name.to_string()));
+        self
+    }
+
+    /// Forbid index usage for the FROM table (`NOT INDEXED`)
+    pub fn not_indexed(mut self) -> Self {
+        self.index_hint = Some(IndexHint::NotIndexed);
+        self
+    }
+
+    /// Set the rows [`QueryBuilder::build_insert`] inserts, one value per column
+    pub fn insert(mut self, columns: Vec<&str>, rows: Vec<Vec<Value>>) -> Self {
+        self.insert_columns = columns.into_iter().map(str::to_string).collect();
+        self.insert_rows = rows;
+        self
+    }
+
+    /// Assign `value` to `column` in [`QueryBuilder::build_update`]
+    pub fn set(mut self, column: &str, value: Value) -> Self {
+        self.set_values.push((column.to_string(), value));
+        self
+    }
+
+    /// Let [`QueryBuilder::build_delete`] delete every row when there is no
+    /// WHERE clause
+    pub fn allow_full_delete(mut self) -> Self {
+        self.allow_full_delete = true;
+        self
+    }
+
+    /// Let [`QueryBuilder::build_update`] update every row when there is no
+    /// WHERE clause
+    pub fn allow_full_update(mut self) -> Self {
+        self.allow_full_update = true;
+        self
+    }
+
+    /// Return `columns` of the inserted, updated or deleted rows
+    ///
+    /// Appends `RETURNING ...` to `build_insert`, `build_update` and
+    /// `build_delete`, SELECT builds ignore it.
+    pub fn returning(mut self, columns: Vec<&str>) -> Self {
+        self.returning = columns.into_iter().map(str::to_string).collect();
+        self
+    }
+
+    /// Wrap the table, column, join and group-by identifiers in double quotes
+    ///
+    /// Lets tables and columns use reserved words such as `order` or `group`.
+    /// Qualified names are quoted per part, `orders.id` becomes
+    /// `"orders"."id"`. `*`, expressions such as aggregates, aliased columns
+    /// and raw SQL conditions are left as written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let (sql, _) = QueryBuilder::new("order")
+    ///     .quote_identifiers(true)
+    ///     .select(vec!["order.id", "COUNT(*)"])
+    ///     .r#where(FilterOperator::Single(Filter::eq("group", "a")))
+    ///     .group_by(vec!["order.id"])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     r#"SELECT "order"."id", COUNT(*) FROM "order" WHERE "group" = ? GROUP BY "order"."id""#
+    /// );
+    /// ```
+    pub fn quote_identifiers(mut self, quote: bool) -> Self {
+        self.quote_identifiers = quote;
+        self
+    }
+
+    /// Remove all where clauses and [`QueryBuilder::bind`] values, keeping the
+    /// table, joins and everything else
+    pub fn clear_where(mut self) -> Self {
+        self.where_clauses.clear();
+        self.named_params.clear();
+        self
+    }
+
+    /// Remove all order by clauses
+    pub fn clear_order_by(mut self) -> Self {
+        self.order_by.clear();
+        self.order_random = false;
+        self
+    }
+
+    /// Remove the limit and offset
+    pub fn clear_limit_offset(mut self) -> Self {
+        self.limit = None;
+        self.offset = None;
+        self.page = None;
+        self
+    }
+
+    /// Copy of this query for counting its rows
+    ///
+    /// Only ORDER BY, LIMIT and OFFSET are removed, so filters, joins and
+    /// grouping stay identical to the data query by construction. Count it with
+    /// [`QueryBuilder::execute_count`].
+    pub fn clone_for_count(&self) -> QueryBuilder {
+        self.clone().clear_order_by().clear_limit_offset()
+    }
+
     /// Add a where clause
     pub fn r#where(mut self, filter: FilterOperator) -> Self {
         self.where_clauses.push(filter);
         self
     }
 
+    /// Add a `column BETWEEN ? AND ?` clause, inclusive of both bounds
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{QueryBuilder, Value};
+    ///
+    /// let (sql, params) = QueryBuilder::new("items")
+    ///     .where_between("quantity", Value::Integer(2), Value::Integer(5))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT * FROM items WHERE quantity BETWEEN ? AND ?");
+    /// assert_eq!(params, vec![libsql::Value::Integer(2), libsql::Value::Integer(5)]);
+    /// ```
+    pub fn where_between(self, column: &str, min: Value, max: Value) -> Self {
+        self.with_filter(crate::Filter::between(column, min, max))
+    }
+
+    /// Add a `column NOT BETWEEN ? AND ?` clause
+    pub fn where_not_between(self, column: &str, min: Value, max: Value) -> Self {
+        self.with_filter(crate::Filter::not_between(column, min, max))
+    }
+
+    /// Combine the top-level WHERE clauses with `combinator` instead of `AND`
+    ///
+    /// Applies to every clause of this builder, including those added by
+    /// helpers such as `search` or `where_in`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Combinator, Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let (sql, _) = QueryBuilder::new("tasks")
+    ///     .r#where(FilterOperator::Single(Filter::eq("status", "open")))
+    ///     .r#where(FilterOperator::Single(Filter::gt("priority", 5i64)))
+    ///     .where_combinator(Combinator::Or)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT * FROM tasks WHERE status = ? OR priority > ?");
+    /// ```
+    pub fn where_combinator(mut self, combinator: crate::Combinator) -> Self {
+        self.where_combinator = combinator;
+        self
+    }
+
+    /// Apply `f` only when `value` is present, e.g. for optional query parameters
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let category: Option<&str> = None;
+    /// let (sql, _) = QueryBuilder::new("items")
+    ///     .filter_if_some(category, |query, category| {
+    ///         query.r#where(FilterOperator::Single(Filter::eq("category", category)))
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT * FROM items");
+    /// ```
+    pub fn filter_if_some<T>(self, value: Option<T>, f: impl FnOnce(Self, T) -> Self) -> Self {
+        match value {
+            Some(value) => f(self, value),
+            None => self,
+        }
+    }
+
     /// Add a group by clause
     pub fn group_by(mut self, columns: Vec<impl Into<String>>) -> Self {
         self.group_by = columns.into_iter().map(|c| c.into()).collect();
@@ -216,6 +1144,30 @@ impl QueryBuilder {
         self
     }
 
+    /// Count rows per value of `column`, most common first
+    ///
+    /// Replaces the selected columns, grouping and ordering with
+    /// `SELECT column, COUNT(*) AS count ... GROUP BY column ORDER BY count DESC`,
+    /// ties ordered by `column`. Rows where `column` is `NULL` are counted as one
+    /// group. Run it with [`QueryBuilder::execute_count_by`].
+    pub fn count_by(self, column: &str) -> Self {
+        self.select(vec![column, "COUNT(*) AS count"])
+            .group_by(vec![column])
+            .clear_order_by()
+            .order_by(Sort::desc("count"))
+            .order_by(Sort::asc(column))
+    }
+
+    /// Order by `RANDOM()`, after any other order by clauses
+    ///
+    /// Combine with `limit(1)` for a random pick. `RANDOM()` can't use an index,
+    /// so SQLite evaluates and sorts every matching row; filter first on large
+    /// tables.
+    pub fn order_by_random(mut self) -> Self {
+        self.order_random = true;
+        self
+    }
+
     /// Add multiple order by clauses
     pub fn order_by_multiple(mut self, sorts: Vec<Sort>) -> Self {
         self.order_by.extend(sorts);
@@ -228,9 +1180,27 @@ impl QueryBuilder {
         self
     }
 
+    /// Cap the limit, e.g. to bound page sizes requested by clients
+    pub fn max_limit(mut self, max_limit: u32) -> Self {
+        self.max_limit = Some(max_limit);
+        self
+    }
+
+    /// Apply limit and offset from a `Pagination`, clamped by `max_limit`
+    ///
+    /// The offset is the page times the clamped limit, so pages stay
+    /// contiguous when `per_page` is above `max_limit`.
+    pub fn paginate(mut self, pagination: &Pagination) -> Self {
+        self.limit = Some(pagination.limit());
+        self.offset = None;
+        self.page = Some(pagination.page.max(1));
+        self
+    }
+
     /// Set offset
     pub fn offset(mut self, offset: u32) -> Self {
         self.offset = Some(offset);
+        self.page = None;
         self
     }
 
@@ -240,14 +1210,35 @@ impl QueryBuilder {
         self
     }
 
-    /// Set aggregate function
+    /// Add an aggregate to the selected columns
+    ///
+    /// Aggregates are selected after the columns and subqueries, in the order
+    /// they were added; the default `*` is dropped once there is one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Aggregate, QueryBuilder};
+    ///
+    /// let (sql, _) = QueryBuilder::new("orders")
+    ///     .select(vec!["user_id"])
+    ///     .aggregate(Aggregate::Count, "*", Some("orders"))
+    ///     .aggregate(Aggregate::Sum, "amount", None::<String>)
+    ///     .group_by(vec!["user_id"])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT user_id, COUNT(*) AS orders, SUM(amount) FROM orders GROUP BY user_id"
+    /// );
+    /// ```
     pub fn aggregate(
         mut self,
         function: Aggregate,
         column: impl Into<String>,
         alias: Option<impl Into<String>>,
     ) -> Self {
-        self.aggregate = Some(AggregateClause {
+        self.aggregates.push(AggregateClause {
             function,
             column: column.into(),
             alias: alias.map(|a| a.into()),
@@ -292,22 +1283,112 @@ impl QueryBuilder {
         self
     }
 
-    /// Add where condition
+    /// Add a raw WHERE condition, binding `params` to its `?` placeholders
+    ///
+    /// The building fails with `Error::Validation` unless there is exactly one
+    /// parameter per placeholder.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::QueryBuilder;
+    ///
+    /// let (sql, params) = QueryBuilder::new("items")
+    ///     .where_condition("quantity > ?", vec![libsql::Value::Integer(2)])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT * FROM items WHERE quantity > ?");
+    /// assert_eq!(params, vec![libsql::Value::Integer(2)]);
+    /// ```
     pub fn where_condition(
         mut self,
         condition: &str,
-        _params: impl Into<Vec<libsql::Value>>,
+        params: impl Into<Vec<libsql::Value>>,
     ) -> Self {
-        // This is a simplified implementation - in a real implementation you'd parse the condition
+        let params: Vec<Value> = params.into().into_iter().map(Value::from).collect();
+        let (_, placeholders) = name_placeholders(condition);
+        if placeholders != params.len() {
+            self.nested_error
+                .get_or_insert(crate::Error::Validation(format!(
+                "WHERE condition `{condition}` has {placeholders} placeholders but {} parameters",
+                params.len()
+            )));
+        }
         self.where_clauses
-            .push(FilterOperator::Custom(condition.to_string()));
+            .push(FilterOperator::Raw(condition.to_string(), params));
         self
     }
 
-    /// Add search
+    /// Bind `value` to the `:name` placeholders of raw and custom conditions
+    ///
+    /// Placeholders are resolved in WHERE and HAVING conditions, join and CASE
+    /// conditions and the expressions of [`QueryBuilder::select_raw`] and
+    /// [`QueryBuilder::having_raw`]. Each `:name` is replaced with a positional
+    /// `?` in the built SQL and its value put at the matching position, between
+    /// those of any `?` of the same condition. `name` may be given with or
+    /// without the colon, binding it again replaces the value. The building
+    /// fails with `Error::Validation` if a placeholder has no binding or a
+    /// binding is used by none of them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{FilterOperator, QueryBuilder, Value};
+    ///
+    /// let (sql, params) = QueryBuilder::new("items")
+    ///     .r#where(FilterOperator::Custom(
+    ///         "owner_id = :owner AND (quantity < :min OR quantity > :max)".to_string(),
+    ///     ))
+    ///     .bind("max", Value::from(10i64))
+    ///     .bind("owner", Value::from("1"))
+    ///     .bind("min", Value::from(2i64))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT * FROM items WHERE owner_id = ? AND (quantity < ? OR quantity > ?)"
+    /// );
+    /// assert_eq!(
+    ///     params,
+    ///     vec![
+    ///         libsql::Value::Text("1".to_string()),
+    ///         libsql::Value::Integer(2),
+    ///         libsql::Value::Integer(10),
+    ///     ]
+    /// );
+    /// ```
+    pub fn bind(mut self, name: &str, value: Value) -> Self {
+        let name = name.strip_prefix(':').unwrap_or(name);
+        match self
+            .named_params
+            .iter_mut()
+            .find(|(bound, _)| bound == name)
+        {
+            Some((_, bound)) => *bound = value,
+            None => self.named_params.push((name.to_string(), value)),
+        }
+        self
+    }
+
+    /// Match rows whose `field` contains `query`
+    ///
+    /// The query is bound as a parameter, with `%`, `_` and `\` escaped so they
+    /// match literally.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::QueryBuilder;
+    ///
+    /// let (sql, params) = QueryBuilder::new("items").search("task", "50%").build().unwrap();
+    /// assert_eq!(sql, r"SELECT * FROM items WHERE task LIKE ? ESCAPE '\'");
+    /// assert_eq!(params, vec![libsql::Value::Text(r"%50\%%".to_string())]);
+    /// ```
     pub fn search(mut self, field: &str, query: &str) -> Self {
-        let condition = format!("{field} LIKE '%{query}%'");
-        self.where_clauses.push(FilterOperator::Custom(condition));
+        self.where_clauses.push(FilterOperator::Contains(
+            field.to_string(),
+            query.to_string(),
+        ));
         self
     }
 
@@ -335,41 +1416,277 @@ impl QueryBuilder {
     }
 
     /// Add having condition
-    pub fn having_condition(
-        mut self,
-        condition: &str,
-        _params: impl Into<Vec<libsql::Value>>,
-    ) -> Self {
-        // This is a simplified implementation
-        self.having
-            .push(FilterOperator::Custom(condition.to_string()));
+    ///
+    /// Same as [`QueryBuilder::having_raw`].
+    pub fn having_condition(self, condition: &str, params: impl Into<Vec<libsql::Value>>) -> Self {
+        self.having_raw(condition, params.into())
+    }
+
+    /// Add a raw HAVING expression, binding `params` to its `?` placeholders
+    ///
+    /// Meant for aggregate conditions `having` can't express, several calls
+    /// are ANDed. The building fails with `Error::Validation` unless there is
+    /// exactly one parameter per placeholder.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::QueryBuilder;
+    ///
+    /// let (sql, params) = QueryBuilder::new("items")
+    ///     .select(vec!["task", "SUM(quantity) AS total"])
+    ///     .group_by(vec!["task"])
+    ///     .having_raw("SUM(quantity) > ?", vec![libsql::Value::Integer(10)])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     sql,
+    ///     "SELECT task, SUM(quantity) AS total FROM items GROUP BY task HAVING (SUM(quantity) > ?)"
+    /// );
+    /// assert_eq!(params, vec![libsql::Value::Integer(10)]);
+    /// ```
+    pub fn having_raw(mut self, expr: &str, params: Vec<libsql::Value>) -> Self {
+        let (_, placeholders) = name_placeholders(expr);
+        if placeholders != params.len() {
+            self.nested_error
+                .get_or_insert(crate::Error::Validation(format!(
+                    "HAVING expression `{expr}` has {placeholders} placeholders but {} parameters",
+                    params.len()
+                )));
+        }
+        self.having_raw.push((expr.to_string(), params));
         self
     }
 
-    /// Add where in clause
+    /// Add a `field IN (subquery)` clause
+    ///
+    /// The subquery's parameters are bound in place, it must select a single
+    /// column.
     pub fn where_in(mut self, field: &str, subquery: QueryBuilder) -> Self {
-        let (subquery_sql, _) = subquery.build().unwrap_or_default();
-        let condition = format!("{field} IN ({subquery_sql})");
-        self.where_clauses.push(FilterOperator::Custom(condition));
+        self.where_clauses.push(FilterOperator::InSubquery(
+            field.to_string(),
+            Box::new(subquery),
+        ));
         self
     }
 
+    /// Decrypt the `encrypted` columns while `execute` maps the rows
+    ///
+    /// Columns are matched by their result name, so alias an encrypted column
+    /// only to its own name. Conditions on these columns compare ciphertext.
+    pub fn encrypted(mut self, encrypted: crate::EncryptedColumns) -> Self {
+        self.encrypted = encrypted;
+        self
+    }
+
+    /// [`QueryBuilder::encrypted`] unless columns were set already
+    pub(crate) fn encrypted_or(mut self, encrypted: crate::EncryptedColumns) -> Self {
+        if self.encrypted.is_empty() {
+            self.encrypted = encrypted;
+        }
+        self
+    }
+
+    /// Log the `EXPLAIN QUERY PLAN` output at debug level before each `execute`
+    ///
+    /// Only active with the `tracing` feature; without it this is a no-op and no
+    /// extra query is sent.
+    pub fn debug_explain_to_tracing(mut self) -> Self {
+        self.debug_explain = true;
+        self
+    }
+
+    /// Run `EXPLAIN QUERY PLAN` for the built query and return the plan details
+    pub async fn explain_query_plan(&self, db: &Database) -> Result<Vec<String>> {
+        let (sql, params) = self.build()?;
+        let mut rows = db
+            .query(&format!("EXPLAIN QUERY PLAN {sql}"), params)
+            .await?;
+        let mut plan = Vec::new();
+        while let Some(row) = rows.next().await? {
+            plan.push(row.get::<String>(3)?);
+        }
+        Ok(plan)
+    }
+
     /// Execute count query
     pub async fn execute_count(&self, db: &Database) -> Result<u64> {
         let (sql, params) = self.build_count()?;
+        db.query_scalar::<u64>(&sql, params)
+            .await?
+            .ok_or_else(|| crate::Error::Query("No count result".to_string()))
+    }
+
+    /// Whether the query matches at least one row
+    ///
+    /// Runs `SELECT EXISTS(...)` without ORDER BY, LIMIT and OFFSET, so SQLite
+    /// stops at the first match instead of counting or fetching all rows.
+    pub async fn exists(&self, db: &Database) -> Result<bool> {
+        let (sql, params) = self.clone().clear_order_by().clear_limit_offset().build()?;
+        let exists = db
+            .query_scalar::<bool>(&format!("SELECT EXISTS({sql})"), params)
+            .await?;
+        Ok(exists.unwrap_or(false))
+    }
+
+    /// Execute the query and collect a single column of every row
+    ///
+    /// Handy for simple projections, e.g. building an IN-list from a prior query.
+    /// Use `Option<T>` for columns that may be `NULL`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, QueryBuilder};
+    ///
+    /// async fn tasks(db: &Database) -> libsql_orm::Result<Vec<String>> {
+    ///     QueryBuilder::new("items")
+    ///         .select(vec!["task"])
+    ///         .execute_column(db, 0)
+    ///         .await
+    /// }
+    /// ```
+    pub async fn execute_column<T: crate::FromSql>(
+        &self,
+        db: &Database,
+        column_index: usize,
+    ) -> Result<Vec<T>> {
+        let index = i32::try_from(column_index)
+            .map_err(|_| crate::Error::Query(format!("Invalid column index {column_index}")))?;
+        let (sql, params) = self.build()?;
         let mut rows = db.query(&sql, params).await?;
+        let mut values = Vec::new();
+        while let Some(row) = rows.next().await? {
+            values.push(T::from_sql(row.get_value(index)?)?);
+        }
+        Ok(values)
+    }
 
-        if let Some(row) = rows.next().await? {
-            row.get_value(0)
-                .ok()
-                .and_then(|v| match v {
-                    libsql::Value::Integer(i) => Some(i as u64),
-                    _ => None,
-                })
-                .ok_or_else(|| crate::Error::Query("Failed to get count".to_string()))
-        } else {
-            Err(crate::Error::Query("No count result".to_string()))
+    /// Execute the query and map every row with a closure
+    ///
+    /// Gives full control over column extraction, e.g. to compute a derived
+    /// field while reading, where [`QueryBuilder::execute`] would need a second
+    /// pass over the results.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, QueryBuilder};
+    ///
+    /// async fn labels(db: &Database) -> libsql_orm::Result<Vec<String>> {
+    ///     QueryBuilder::new("items")
+    ///         .select(vec!["id", "task"])
+    ///         .execute_map(db, |row| {
+    ///             let id: i64 = row.get(0)?;
+    ///             let task: String = row.get(1)?;
+    ///             Ok(format!("#{id} {task}"))
+    ///         })
+    ///         .await
+    /// }
+    /// ```
+    pub async fn execute_map<T, F>(&self, db: &Database, f: F) -> Result<Vec<T>>
+    where
+        F: Fn(&libsql::Row) -> Result<T>,
+    {
+        let (sql, params) = self.build()?;
+        let mut rows = db.query(&sql, params).await?;
+        let mut values = Vec::new();
+        while let Some(row) = rows.next().await? {
+            values.push(f(&row)?);
+        }
+        Ok(values)
+    }
+
+    /// Execute a [`QueryBuilder::count_by`] query and collect `(key, count)` pairs
+    ///
+    /// Use `Option<K>` when the column may be `NULL`, the `NULL` group then has
+    /// the key `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use libsql_orm::{Database, QueryBuilder};
+    ///
+    /// async fn per_category(db: &Database) -> libsql_orm::Result<Vec<(Option<String>, i64)>> {
+    ///     QueryBuilder::new("items")
+    ///         .count_by("category")
+    ///         .execute_count_by(db)
+    ///         .await
+    /// }
+    /// ```
+    pub async fn execute_count_by<K: crate::FromSql>(
+        &self,
+        db: &Database,
+    ) -> Result<Vec<(K, i64)>> {
+        self.execute_map(db, |row| {
+            let key = K::from_sql(row.get_value(0)?)?;
+            let count: i64 = row.get(1)?;
+            Ok((key, count))
+        })
+        .await
+    }
+
+    /// Approximate `COUNT(DISTINCT column)` over the rows of this query
+    ///
+    /// Counts the distinct values of a random sample of about 10,000 rows in
+    /// SQL and scales the result up, so SQLite only builds a temporary index
+    /// of the sampled values and just three numbers are returned. Queries with
+    /// at most 10,000 non-`NULL` values get the exact count. The estimate is
+    /// the GEE estimator, `sqrt(rows / sampled) * once + more_than_once` over
+    /// the sampled values seen once and more than once; expect an error of a
+    /// few percent when values repeat and more when almost all are unique.
+    /// `NULL`s are not counted.
+    pub async fn count_distinct_estimate(&self, db: &Database, column: &str) -> Result<u64> {
+        let (inner, params) = self
+            .clone()
+            .clear_order_by()
+            .select(vec![format!("{column} AS distinct_value")])
+            .build()?;
+
+        let rows: i64 = db
+            .query_scalar(
+                &format!("SELECT COUNT(distinct_value) FROM ({inner})"),
+                params.clone(),
+            )
+            .await?
+            .unwrap_or(0);
+        let rows = rows as u64;
+        if rows <= DISTINCT_SAMPLE_ROWS {
+            let distinct: i64 = db
+                .query_scalar(
+                    &format!("SELECT COUNT(DISTINCT distinct_value) FROM ({inner})"),
+                    params,
+                )
+                .await?
+                .unwrap_or(0);
+            return Ok(distinct as u64);
+        }
+
+        let step = rows.div_ceil(DISTINCT_SAMPLE_ROWS);
+        let mut sample_params = params;
+        sample_params.push(libsql::Value::Integer(step as i64));
+        let mut sample = db
+            .query(
+                &format!(
+                    "SELECT SUM(n = 1), SUM(n > 1), SUM(n) FROM \
+                     (SELECT COUNT(*) AS n FROM ({inner}) \
+                     WHERE distinct_value IS NOT NULL AND ABS(RANDOM()) % ? = 0 \
+                     GROUP BY distinct_value)"
+                ),
+                sample_params,
+            )
+            .await?;
+        let Some(row) = sample.next().await? else {
+            return Ok(0);
+        };
+        let once = row.get::<Option<i64>>(0)?.unwrap_or(0) as f64;
+        let repeated = row.get::<Option<i64>>(1)?.unwrap_or(0) as f64;
+        let sampled = row.get::<Option<i64>>(2)?.unwrap_or(0) as f64;
+        if sampled == 0.0 {
+            return Ok(0);
         }
+        let estimate = (rows as f64 / sampled).sqrt() * once + repeated;
+        Ok((estimate.round() as u64).clamp((once + repeated) as u64, rows))
     }
 
     /// Execute aggregate query
@@ -385,8 +1702,11 @@ impl QueryBuilder {
 
     /// Build the SQL query
     pub fn build(&self) -> Result<(String, Vec<libsql::Value>)> {
-        let mut sql = String::new();
-        let mut params = Vec::new();
+        if let Some(err) = &self.nested_error {
+            return Err(err.clone());
+        }
+        self.check_bindings()?;
+        let (mut sql, mut params) = self.build_with()?;
 
         // SELECT clause
         sql.push_str("SELECT ");
@@ -394,124 +1714,580 @@ impl QueryBuilder {
             sql.push_str("DISTINCT ");
         }
 
-        if let Some(agg) = &self.aggregate {
-            sql.push_str(&format!("{}({})", agg.function, agg.column));
-            if let Some(alias) = &agg.alias {
-                sql.push_str(&format!(" AS {alias}"));
+        let mut select_list: Vec<String> = self
+            .plain_select_columns()
+            .iter()
+            .map(|column| self.quote_identifier(column))
+            .collect();
+        for (subquery, alias) in &self.select_subqueries {
+            let (subquery_sql, subquery_params) =
+                subquery.build_nested(|| format!("select_subquery({alias})"))?;
+            select_list.push(format!("({subquery_sql}) AS {alias}"));
+            params.extend(subquery_params);
+        }
+        for expr in &self.select_exprs {
+            let (expr_sql, expr_params) = self.build_select_expr(expr)?;
+            select_list.push(expr_sql);
+            params.extend(expr_params);
+        }
+        for agg in &self.aggregates {
+            match &agg.alias {
+                Some(alias) => {
+                    select_list.push(format!("{}({}) AS {alias}", agg.function, agg.column))
+                }
+                None => select_list.push(format!("{}({})", agg.function, agg.column)),
             }
-        } else {
-            sql.push_str(&self.select_columns.join(", "));
         }
+        sql.push_str(&select_list.join(", "));
 
         // FROM clause
-        sql.push_str(&format!(" FROM {}", self.table));
+        sql.push_str(&self.build_from()?);
+        params.extend(
+            self.source_params
+                .iter()
+                .map(|value| self.value_to_libsql_value(value)),
+        );
 
         // JOIN clauses
-        for join in &self.joins {
-            sql.push_str(&format!(" {} {}", join.join_type, join.table));
-            if let Some(alias) = &join.alias {
-                sql.push_str(&format!(" AS {alias}"));
-            }
-            sql.push_str(&format!(" ON {}", join.condition));
-        }
+        let (joins_sql, joins_params) = self.build_joins()?;
+        sql.push_str(&joins_sql);
+        params.extend(joins_params);
 
         // WHERE clause
         if !self.where_clauses.is_empty() {
             sql.push_str(" WHERE ");
-            let (where_sql, where_params) = self.build_where_clause(&self.where_clauses)?;
+            let (where_sql, where_params) =
+                self.build_where_clause(&self.where_clauses, self.where_combinator)?;
             sql.push_str(&where_sql);
             params.extend(where_params);
         }
 
         // GROUP BY clause
         if !self.group_by.is_empty() {
-            sql.push_str(&format!(" GROUP BY {}", self.group_by.join(", ")));
+            sql.push_str(&format!(" GROUP BY {}", self.quote_all(&self.group_by)));
         }
 
         // HAVING clause
-        if !self.having.is_empty() {
-            sql.push_str(" HAVING ");
-            let (having_sql, having_params) = self.build_where_clause(&self.having)?;
-            sql.push_str(&having_sql);
+        if let Some((having_sql, having_params)) = self.build_having()? {
+            sql.push_str(&format!(" HAVING {having_sql}"));
             params.extend(having_params);
         }
 
+        // Compound members
+        for (i, (operator, member)) in self.unions.iter().enumerate() {
+            let (member_sql, member_params) =
+                member.build_nested(|| format!("{operator} member {}", i + 2))?;
+            sql.push_str(&format!(" {operator} {member_sql}"));
+            params.extend(member_params);
+        }
+
         // ORDER BY clause
-        if !self.order_by.is_empty() {
+        if !self.order_by.is_empty() || self.order_random {
             sql.push_str(" ORDER BY ");
-            let order_clauses: Vec<String> = self
+            let mut order_clauses: Vec<String> = self
                 .order_by
                 .iter()
-                .map(|sort| format!("{} {}", sort.column, sort.order))
+                .map(|sort| self.render_sort(sort))
                 .collect();
+            if self.order_random {
+                order_clauses.push("RANDOM()".to_string());
+            }
             sql.push_str(&order_clauses.join(", "));
         }
 
         // LIMIT and OFFSET
-        if let Some(limit) = self.limit {
+        let limit = match (self.limit, self.max_limit) {
+            (Some(limit), Some(max)) => Some(limit.min(max)),
+            (limit, _) => limit,
+        };
+        if let Some(limit) = limit {
             sql.push_str(&format!(" LIMIT {limit}"));
         }
-        if let Some(offset) = self.offset {
+        let offset = match self.page {
+            Some(page) => Some(page.saturating_sub(1).saturating_mul(limit.unwrap_or(0))),
+            None => self.offset,
+        };
+        if let Some(offset) = offset {
             sql.push_str(&format!(" OFFSET {offset}"));
         }
 
+        Ok((normalize_sql(&sql), params))
+    }
+
+    /// Build the query with named placeholders `:p1`, `:p2`, ... instead of `?`
+    ///
+    /// For [`Database::query_named`], e.g. when the SQL is combined with
+    /// hand-written parts that bind their own named parameters.
+    pub fn build_named(&self) -> Result<(String, HashMap<String, libsql::Value>)> {
+        let (sql, params) = self.build()?;
+        let (sql, count) = name_placeholders(&sql);
+        if count != params.len() {
+            return Err(crate::Error::Query(format!(
+                "Query has {count} placeholders but {} parameters",
+                params.len()
+            )));
+        }
+        let params = params
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (format!(":p{}", i + 1), value))
+            .collect();
         Ok((sql, params))
     }
 
+    /// Build the query without exposing parameter values
+    ///
+    /// Each bound value is replaced by its type (`<text>`, `<integer>`, ...), so the
+    /// result can go into logs or support tickets without leaking user data.
+    pub fn build_redacted(&self) -> Result<(String, Vec<String>)> {
+        let (sql, params) = self.build()?;
+        let redacted = params
+            .iter()
+            .map(|value| {
+                match value {
+                    libsql::Value::Null => "NULL",
+                    libsql::Value::Integer(_) => "<integer>",
+                    libsql::Value::Real(_) => "<real>",
+                    libsql::Value::Text(_) => "<text>",
+                    libsql::Value::Blob(_) => "<blob>",
+                }
+                .to_string()
+            })
+            .collect();
+        Ok((sql, redacted))
+    }
+
+    /// Ensure the query is fully parameterized
+    ///
+    /// Fails with `Error::Validation` if any WHERE or HAVING clause, including
+    /// those of compound members, selected subqueries and common table
+    /// expressions, is caller-written SQL:
+    ///
+    /// - a `FilterOperator::Custom` or `FilterOperator::Raw` fragment, which
+    ///   includes [`QueryBuilder::where_condition`]
+    /// - a [`QueryBuilder::having_raw`] or [`QueryBuilder::select_raw`] expression
+    ///
+    /// Typed filters pass, and so do the clauses of [`QueryBuilder::search`],
+    /// [`QueryBuilder::where_in`], `Exists`, `NotExists` and `InSubquery` as
+    /// long as their subqueries pass too.
+    /// Call it before executing queries on security-sensitive endpoints.
+    pub fn validate_no_raw(&self) -> Result<()> {
+        if self
+            .where_clauses
+            .iter()
+            .chain(&self.having)
+            .chain(self.select_exprs.iter().flat_map(SelectExpr::conditions))
+            .any(contains_raw)
+            || !self.having_raw.is_empty()
+            || self
+                .select_exprs
+                .iter()
+                .any(|expr| matches!(expr, SelectExpr::Raw { .. }))
+        {
+            return Err(crate::Error::Validation(format!(
+                "Query on {} contains a raw SQL condition",
+                self.table
+            )));
+        }
+        self.unions
+            .iter()
+            .map(|(_, member)| member)
+            .chain(self.select_subqueries.iter().map(|(subquery, _)| subquery))
+            .chain(self.ctes.iter().map(|(_, query)| query))
+            .try_for_each(QueryBuilder::validate_no_raw)
+    }
+
+    /// Panic unless every `?` of the built query has a parameter
+    ///
+    /// Meant for tests of query-building code: a `FilterOperator::Custom`
+    /// fragment with a `?` silently binds nothing, which SQLite runs as `NULL`
+    /// and so matches no rows. A build error panics too.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,should_panic
+    /// use libsql_orm::{FilterOperator, QueryBuilder};
+    ///
+    /// QueryBuilder::new("items")
+    ///     .r#where(FilterOperator::Custom("owner_id = ?".to_string()))
+    ///     .assert_all_params_bound();
+    /// ```
+    #[track_caller]
+    pub fn assert_all_params_bound(&self) {
+        let (sql, params) = match self.build() {
+            Ok(built) => built,
+            Err(err) => panic!("query on {} does not build: {err}", self.table),
+        };
+        let (_, placeholders) = name_placeholders(&sql);
+        assert!(
+            placeholders == params.len(),
+            "query has {placeholders} `?` placeholders but {} bound parameters, \
+             a Custom clause probably contains a `?`: {sql}",
+            params.len()
+        );
+    }
+
     /// Build a count query
+    ///
+    /// Grouped queries count their groups rather than the underlying rows, and
+    /// compound queries the rows of all members, by wrapping the query as
+    /// `SELECT COUNT(*) FROM (...)`.
     pub fn build_count(&self) -> Result<(String, Vec<libsql::Value>)> {
-        let mut sql = String::new();
-        let mut params = Vec::new();
+        if let Some(err) = &self.nested_error {
+            return Err(err.clone());
+        }
+        self.check_bindings()?;
+        if !self.group_by.is_empty() || !self.unions.is_empty() {
+            let (grouped_sql, params) = self.clone_for_count().build()?;
+            return Ok((format!("SELECT COUNT(*) FROM ({grouped_sql})"), params));
+        }
+
+        let (mut sql, mut params) = self.build_with()?;
 
         sql.push_str("SELECT COUNT(*)");
 
         // FROM clause
-        sql.push_str(&format!(" FROM {}", self.table));
+        sql.push_str(&self.build_from()?);
+        params.extend(
+            self.source_params
+                .iter()
+                .map(|value| self.value_to_libsql_value(value)),
+        );
 
         // JOIN clauses
-        for join in &self.joins {
-            sql.push_str(&format!(" {} {}", join.join_type, join.table));
-            if let Some(alias) = &join.alias {
-                sql.push_str(&format!(" AS {alias}"));
-            }
-            sql.push_str(&format!(" ON {}", join.condition));
-        }
+        let (joins_sql, joins_params) = self.build_joins()?;
+        sql.push_str(&joins_sql);
+        params.extend(joins_params);
 
         // WHERE clause
         if !self.where_clauses.is_empty() {
             sql.push_str(" WHERE ");
-            let (where_sql, where_params) = self.build_where_clause(&self.where_clauses)?;
+            let (where_sql, where_params) =
+                self.build_where_clause(&self.where_clauses, self.where_combinator)?;
             sql.push_str(&where_sql);
             params.extend(where_params);
         }
 
         // GROUP BY clause
         if !self.group_by.is_empty() {
-            sql.push_str(&format!(" GROUP BY {}", self.group_by.join(", ")));
+            sql.push_str(&format!(" GROUP BY {}", self.quote_all(&self.group_by)));
         }
 
         // HAVING clause
+        if let Some((having_sql, having_params)) = self.build_having()? {
+            sql.push_str(&format!(" HAVING {having_sql}"));
+            params.extend(having_params);
+        }
+
+        Ok((normalize_sql(&sql), params))
+    }
+
+    /// Build an INSERT of the rows given to [`QueryBuilder::insert`]
+    ///
+    /// Parameters are flattened row by row. Fails with `Error::Query` without
+    /// rows or columns, or when a row has more or fewer values than columns.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{QueryBuilder, Value};
+    ///
+    /// let (sql, params) = QueryBuilder::new("items")
+    ///     .insert(
+    ///         vec!["owner_id", "task"],
+    ///         vec![
+    ///             vec![Value::from("1"), Value::from("milk")],
+    ///             vec![Value::from("1"), Value::from("eggs")],
+    ///         ],
+    ///     )
+    ///     .build_insert()
+    ///     .unwrap();
+    /// assert_eq!(sql, "INSERT INTO items (owner_id, task) VALUES (?, ?), (?, ?)");
+    /// assert_eq!(params.len(), 4);
+    /// ```
+    pub fn build_insert(&self) -> Result<(String, Vec<libsql::Value>)> {
+        if self.insert_rows.is_empty() || self.insert_columns.is_empty() {
+            return Err(crate::Error::Query(format!(
+                "INSERT into {} needs at least one row and one column",
+                self.table
+            )));
+        }
+        if let Some(row) = self
+            .insert_rows
+            .iter()
+            .find(|row| row.len() != self.insert_columns.len())
+        {
+            return Err(crate::Error::Query(format!(
+                "INSERT row has {} values but {} columns were named",
+                row.len(),
+                self.insert_columns.len()
+            )));
+        }
+
+        let placeholders = vec!["?"; self.insert_columns.len()].join(", ");
+        let values = vec![format!("({placeholders})"); self.insert_rows.len()].join(", ");
+        let mut sql = format!(
+            "INSERT INTO {} ({}) VALUES {values}",
+            self.quote_identifier(&self.table),
+            self.quote_all(&self.insert_columns)
+        );
+        sql.push_str(&self.build_returning());
+        let params = self
+            .insert_rows
+            .iter()
+            .flatten()
+            .map(|value| self.value_to_libsql_value(value))
+            .collect();
+        Ok((normalize_sql(&sql), params))
+    }
+
+    /// Build an UPDATE of the rows matching the WHERE clauses
+    ///
+    /// Assigns the values given to [`QueryBuilder::set`], their parameters go
+    /// before those of the WHERE clauses. Fails with `Error::Query` without any
+    /// assignment or with joins, which UPDATE can't use. Without a WHERE clause
+    /// it fails too, unless [`QueryBuilder::allow_full_update`] was called.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder, Value};
+    ///
+    /// let (sql, _) = QueryBuilder::new("items")
+    ///     .set("completed", Value::from(true))
+    ///     .r#where(FilterOperator::Single(Filter::eq("id", 7i64)))
+    ///     .build_update()
+    ///     .unwrap();
+    /// assert_eq!(sql, "UPDATE items SET completed = ? WHERE id = ?");
+    /// assert!(QueryBuilder::new("items")
+    ///     .set("completed", Value::from(true))
+    ///     .build_update()
+    ///     .is_err());
+    /// ```
+    pub fn build_update(&self) -> Result<(String, Vec<libsql::Value>)> {
+        if let Some(err) = &self.nested_error {
+            return Err(err.clone());
+        }
+        self.check_bindings()?;
+        if self.set_values.is_empty() {
+            return Err(crate::Error::Query(format!(
+                "UPDATE of {} needs at least one set value",
+                self.table
+            )));
+        }
+        if self.where_clauses.is_empty() && !self.allow_full_update {
+            return Err(crate::Error::Query(
+                "refusing to build unconditional UPDATE".to_string(),
+            ));
+        }
+        if !self.joins.is_empty() {
+            return Err(crate::Error::Query(format!(
+                "UPDATE of {} can't use joins, filter with where_in instead",
+                self.table
+            )));
+        }
+
+        let assignments: Vec<String> = self
+            .set_values
+            .iter()
+            .map(|(column, _)| format!("{} = ?", self.quote_identifier(column)))
+            .collect();
+        let mut sql = format!(
+            "UPDATE {} SET {}",
+            self.quote_identifier(&self.table),
+            assignments.join(", ")
+        );
+        let mut params: Vec<libsql::Value> = self
+            .set_values
+            .iter()
+            .map(|(_, value)| self.value_to_libsql_value(value))
+            .collect();
+        if !self.where_clauses.is_empty() {
+            let (where_sql, where_params) =
+                self.build_where_clause(&self.where_clauses, self.where_combinator)?;
+            sql.push_str(&format!(" WHERE {where_sql}"));
+            params.extend(where_params);
+        }
+        sql.push_str(&self.build_returning());
+        Ok((normalize_sql(&sql), params))
+    }
+
+    /// Build a DELETE of the rows matching the WHERE clauses
+    ///
+    /// Without a WHERE clause it fails with `Error::Query`, unless
+    /// [`QueryBuilder::allow_full_delete`] was called, so a forgotten filter
+    /// can't empty the table. Joins are rejected like in `build_update`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use libsql_orm::{Filter, FilterOperator, QueryBuilder};
+    ///
+    /// let (sql, _) = QueryBuilder::new("items")
+    ///     .r#where(FilterOperator::Single(Filter::eq("completed", true)))
+    ///     .build_delete()
+    ///     .unwrap();
+    /// assert_eq!(sql, "DELETE FROM items WHERE completed = ?");
+    /// assert!(QueryBuilder::new("items").build_delete().is_err());
+    /// ```
+    pub fn build_delete(&self) -> Result<(String, Vec<libsql::Value>)> {
+        if let Some(err) = &self.nested_error {
+            return Err(err.clone());
+        }
+        self.check_bindings()?;
+        if self.where_clauses.is_empty() && !self.allow_full_delete {
+            return Err(crate::Error::Query(
+                "refusing to build unconditional DELETE".to_string(),
+            ));
+        }
+        if !self.joins.is_empty() {
+            return Err(crate::Error::Query(format!(
+                "DELETE from {} can't use joins, filter with where_in instead",
+                self.table
+            )));
+        }
+
+        let mut sql = format!("DELETE FROM {}", self.quote_identifier(&self.table));
+        let mut params = Vec::new();
+        if !self.where_clauses.is_empty() {
+            let (where_sql, where_params) =
+                self.build_where_clause(&self.where_clauses, self.where_combinator)?;
+            sql.push_str(&format!(" WHERE {where_sql}"));
+            params.extend(where_params);
+        }
+        sql.push_str(&self.build_returning());
+        Ok((normalize_sql(&sql), params))
+    }
+
+    fn build_returning(&self) -> String {
+        if self.returning.is_empty() {
+            String::new()
+        } else {
+            format!(" RETURNING {}", self.quote_all(&self.returning))
+        }
+    }
+
+    /// Build the HAVING conditions, `None` without any
+    fn build_having(&self) -> Result<Option<(String, Vec<libsql::Value>)>> {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
         if !self.having.is_empty() {
-            sql.push_str(" HAVING ");
-            let (having_sql, having_params) = self.build_where_clause(&self.having)?;
-            sql.push_str(&having_sql);
+            let (having_sql, having_params) =
+                self.build_where_clause(&self.having, crate::Combinator::And)?;
+            conditions.push(having_sql);
             params.extend(having_params);
         }
+        for (expr, expr_params) in &self.having_raw {
+            let (expr_sql, expr_params) = self.resolve_named(expr, expr_params.clone())?;
+            conditions.push(format!("({expr_sql})"));
+            params.extend(expr_params);
+        }
+        if conditions.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some((conditions.join(" AND "), params)))
+    }
 
+    /// Build the FROM clause including any index hint
+    fn build_from(&self) -> Result<String> {
+        let table = self.quote_identifier(&self.table);
+        match &self.index_hint {
+            None => Ok(format!(" FROM {table}")),
+            Some(IndexHint::NotIndexed) => Ok(format!(" FROM {table} NOT INDEXED")),
+            Some(IndexHint::IndexedBy(index)) => {
+                if !is_plain_identifier(index) {
+                    return Err(crate::Error::Query(format!(
+                        "Invalid index name for INDEXED BY: {index:?}"
+                    )));
+                }
+                Ok(format!(" FROM {table} INDEXED BY {index}"))
+            }
+        }
+    }
+
+    /// Build the JOIN clauses, resolving `:name` bindings of ON conditions
+    fn build_joins(&self) -> Result<(String, Vec<libsql::Value>)> {
+        let mut sql = String::new();
+        let mut params = Vec::new();
+        for join in &self.joins {
+            sql.push_str(&format!(
+                " {} {}",
+                join.join_type,
+                self.quote_identifier(&join.table)
+            ));
+            if let Some(alias) = &join.alias {
+                sql.push_str(&format!(" AS {}", self.quote_identifier(alias)));
+            }
+            match &join.constraint {
+                // `join(JoinType::Cross, table, "")` is a cross join as well
+                JoinConstraint::On(condition)
+                    if matches!(join.join_type, crate::JoinType::Cross)
+                        && condition.trim().is_empty() => {}
+                JoinConstraint::On(condition) => {
+                    let (condition, condition_params) =
+                        self.resolve_named(condition, Vec::new())?;
+                    sql.push_str(&format!(" ON {condition}"));
+                    params.extend(condition_params);
+                }
+                JoinConstraint::Using(columns) => {
+                    sql.push_str(&format!(" USING ({})", self.quote_all(columns)))
+                }
+                JoinConstraint::None => {}
+            }
+        }
         Ok((sql, params))
     }
 
+    /// `name` in double quotes per `.`-separated part when quoting is enabled
+    ///
+    /// Only plain identifiers are quoted, `*`, expressions and anything with an
+    /// alias or a space pass through unchanged.
+    fn quote_identifier(&self, name: &str) -> String {
+        if !self.quote_identifiers {
+            return name.to_string();
+        }
+        let parts: Vec<&str> = name.split('.').collect();
+        let last = parts.len() - 1;
+        let quotable = parts
+            .iter()
+            .enumerate()
+            .all(|(i, part)| is_plain_identifier(part) || (i == last && i > 0 && *part == "*"));
+        if !quotable {
+            return name.to_string();
+        }
+        parts
+            .iter()
+            .map(|part| {
+                if *part == "*" {
+                    part.to_string()
+                } else {
+                    format!("\"{part}\"")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    fn quote_all(&self, names: &[String]) -> String {
+        names
+            .iter()
+            .map(|name| self.quote_identifier(name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
     /// Build where clause from filter operators
     fn build_where_clause(
         &self,
         filters: &[FilterOperator],
+        combinator: crate::Combinator,
     ) -> Result<(String, Vec<libsql::Value>)> {
         let mut sql = String::new();
         let mut params = Vec::new();
 
         for (i, filter) in filters.iter().enumerate() {
             if i > 0 {
-                sql.push_str(" AND ");
+                sql.push_str(&format!(" {combinator} "));
             }
             let (filter_sql, filter_params) = self.build_filter_operator(filter)?;
             sql.push_str(&filter_sql);
@@ -562,7 +2338,137 @@ impl QueryBuilder {
                 let (filter_sql, filter_params) = self.build_filter_operator(filter)?;
                 Ok((format!("NOT ({filter_sql})"), filter_params))
             }
-            FilterOperator::Custom(condition) => Ok((condition.clone(), vec![])),
+            FilterOperator::Between(column, min, max) => {
+                warn_if_reversed(column, min, max);
+                Ok((
+                    format!("{} BETWEEN ? AND ?", self.quote_identifier(column)),
+                    vec![
+                        self.value_to_libsql_value(min),
+                        self.value_to_libsql_value(max),
+                    ],
+                ))
+            }
+            FilterOperator::Contains(field, query) => Ok((
+                format!("{field} LIKE ? ESCAPE '\\'"),
+                vec![libsql::Value::Text(format!("%{}%", escape_like(query)))],
+            )),
+            FilterOperator::Custom(condition) => self.resolve_named(condition, Vec::new()),
+            FilterOperator::Exists(subquery) => {
+                let (subquery_sql, params) =
+                    subquery.build_nested(|| "EXISTS subquery".to_string())?;
+                Ok((format!("EXISTS ({subquery_sql})"), params))
+            }
+            FilterOperator::NotExists(subquery) => {
+                let (subquery_sql, params) =
+                    subquery.build_nested(|| "NOT EXISTS subquery".to_string())?;
+                Ok((format!("NOT EXISTS ({subquery_sql})"), params))
+            }
+            FilterOperator::InSubquery(column, subquery) => {
+                let (subquery_sql, params) =
+                    subquery.build_nested(|| format!("subquery of where_in({column})"))?;
+                Ok((
+                    format!("{} IN ({subquery_sql})", self.quote_identifier(column)),
+                    params,
+                ))
+            }
+            FilterOperator::Raw(condition, params) => self.resolve_named(
+                condition,
+                params
+                    .iter()
+                    .map(|value| self.value_to_libsql_value(value))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Replace the `:name` placeholders of `condition` with `?`, interleaving
+    /// their bound values with the positional `params`
+    fn resolve_named(
+        &self,
+        condition: &str,
+        params: Vec<libsql::Value>,
+    ) -> Result<(String, Vec<libsql::Value>)> {
+        let (sql, named) = positional_placeholders(condition);
+        let mut values = Vec::with_capacity(params.len() + named.len());
+        let total = params.len() + named.len();
+        let mut positional = params.into_iter();
+        let mut named = named.into_iter().peekable();
+        for index in 0..total {
+            match named.next_if(|(position, _)| *position == index) {
+                Some((_, name)) => {
+                    let value = self
+                        .named_params
+                        .iter()
+                        .find(|(bound, _)| *bound == name)
+                        .map(|(_, value)| value)
+                        .ok_or_else(|| {
+                            crate::Error::Validation(format!(
+                                "Placeholder :{name} in `{condition}` has no binding"
+                            ))
+                        })?;
+                    values.push(self.value_to_libsql_value(value));
+                }
+                None => values.extend(positional.next()),
+            }
+        }
+        Ok((sql, values))
+    }
+
+    /// Fail on a [`QueryBuilder::bind`] value no condition or expression has a
+    /// placeholder for
+    fn check_bindings(&self) -> Result<()> {
+        fn names_in(sql: &str, names: &mut Vec<String>) {
+            names.extend(
+                positional_placeholders(sql)
+                    .1
+                    .into_iter()
+                    .map(|(_, name)| name),
+            );
+        }
+        fn collect(filter: &FilterOperator, names: &mut Vec<String>) {
+            match filter {
+                FilterOperator::Custom(condition) | FilterOperator::Raw(condition, _) => {
+                    names_in(condition, names)
+                }
+                FilterOperator::And(filters) | FilterOperator::Or(filters) => {
+                    filters.iter().for_each(|filter| collect(filter, names))
+                }
+                FilterOperator::Not(filter) => collect(filter, names),
+                _ => {}
+            }
+        }
+        let mut used = Vec::new();
+        for filter in self
+            .where_clauses
+            .iter()
+            .chain(&self.having)
+            .chain(self.select_exprs.iter().flat_map(SelectExpr::conditions))
+        {
+            collect(filter, &mut used);
+        }
+        for expr in &self.select_exprs {
+            if let SelectExpr::Raw { expr, .. } = expr {
+                names_in(expr, &mut used);
+            }
+        }
+        for (expr, _) in &self.having_raw {
+            names_in(expr, &mut used);
+        }
+        for join in &self.joins {
+            if let JoinConstraint::On(condition) = &join.constraint {
+                names_in(condition, &mut used);
+            }
+        }
+        match self
+            .named_params
+            .iter()
+            .find(|(name, _)| !used.contains(name))
+        {
+            Some((name, _)) => Err(crate::Error::Validation(format!(
+                "Binding :{name} is not used by any condition of the query on {}",
+                self.table
+            ))),
+            None => Ok(()),
         }
     }
 
@@ -573,13 +2479,36 @@ impl QueryBuilder {
 
         match &filter.operator {
             Operator::IsNull => {
-                sql.push_str(&format!("{} IS NULL", filter.column));
+                sql.push_str(&format!(
+                    "{} IS NULL",
+                    self.quote_identifier(&filter.column)
+                ));
             }
             Operator::IsNotNull => {
-                sql.push_str(&format!("{} IS NOT NULL", filter.column));
+                sql.push_str(&format!(
+                    "{} IS NOT NULL",
+                    self.quote_identifier(&filter.column)
+                ));
+            }
+            // `IN ()` is a syntax error in SQLite, spell out what it would mean
+            _ if matches!(&filter.value, FilterValue::Multiple(values) if values.is_empty()) => {
+                match filter.operator {
+                    Operator::In => sql.push_str("1 = 0"),
+                    Operator::NotIn => sql.push_str("1 = 1"),
+                    _ => {
+                        return Err(crate::Error::Validation(format!(
+                            "Empty value list for {} {}",
+                            filter.column, filter.operator
+                        )))
+                    }
+                }
             }
             _ => {
-                sql.push_str(&format!("{} {} ", filter.column, filter.operator));
+                sql.push_str(&format!(
+                    "{} {} ",
+                    self.quote_identifier(&filter.column),
+                    filter.operator
+                ));
                 match &filter.value {
                     FilterValue::Single(value) => {
                         sql.push('?');
@@ -597,6 +2526,7 @@ impl QueryBuilder {
                         sql.push(')');
                     }
                     FilterValue::Range(min, max) => {
+                        warn_if_reversed(&filter.column, min, max);
                         sql.push_str("? AND ?");
                         params.push(self.value_to_libsql_value(min));
                         params.push(self.value_to_libsql_value(max));
@@ -626,22 +2556,111 @@ impl QueryBuilder {
         T: serde::de::DeserializeOwned,
     {
         let (sql, params) = self.build()?;
-        let mut rows = db.query(&sql, params).await?;
+
+        #[cfg(feature = "tracing")]
+        if self.debug_explain {
+            let plan = self.explain_query_plan(db).await?;
+            log::debug!(target: "libsql_orm::explain", "{sql}\n{}", plan.join("\n"));
+        }
+
+        self.query_rows(db, &sql, params).await
+    }
+
+    /// Run the [`QueryBuilder::build_insert`] INSERT and map the `returning`
+    /// rows like [`QueryBuilder::execute`]
+    ///
+    /// Fails with `Error::Query` without `returning` columns.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # use std::collections::HashMap;
+    /// # use libsql_orm::{Database, QueryBuilder};
+    /// # async fn example(db: &Database) -> libsql_orm::Result<()> {
+    /// let ids: Vec<HashMap<String, i64>> = QueryBuilder::new("items")
+    ///     .insert(vec!["owner_id", "task"], vec![vec!["1".into(), "milk".into()]])
+    ///     .returning(vec!["id"])
+    ///     .insert_returning(db)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn insert_returning<T>(&self, db: &Database) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.returning_rows(db, "insert_returning", || self.build_insert())
+            .await
+    }
+
+    /// Run the [`QueryBuilder::build_update`] UPDATE and map the `returning`
+    /// rows, like [`QueryBuilder::insert_returning`]
+    pub async fn update_returning<T>(&self, db: &Database) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.returning_rows(db, "update_returning", || self.build_update())
+            .await
+    }
+
+    /// Run the [`QueryBuilder::build_delete`] DELETE and map the `returning`
+    /// rows, like [`QueryBuilder::insert_returning`]
+    pub async fn delete_returning<T>(&self, db: &Database) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.returning_rows(db, "delete_returning", || self.build_delete())
+            .await
+    }
+
+    async fn returning_rows<T>(
+        &self,
+        db: &Database,
+        method: &str,
+        build: impl FnOnce() -> Result<(String, Vec<libsql::Value>)>,
+    ) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        if self.returning.is_empty() {
+            return Err(crate::Error::Query(format!(
+                "{method} on {} needs returning columns",
+                self.table
+            )));
+        }
+        let (sql, params) = build()?;
+        self.query_rows(db, &sql, params).await
+    }
+
+    /// Run `sql` and deserialize each row from a map of column name to value
+    async fn query_rows<T>(
+        &self,
+        db: &Database,
+        sql: &str,
+        params: Vec<libsql::Value>,
+    ) -> Result<Vec<T>>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut rows = db.query(sql, params).await?;
 
         let mut results = Vec::new();
         while let Some(row) = rows.next().await? {
             let mut map = HashMap::new();
             for i in 0..row.column_count() {
-                if let Some(column_name) = row.column_name(i) {
-                    let value = row.get_value(i).unwrap_or(libsql::Value::Null);
-                    map.insert(
-                        column_name.to_string(),
-                        self.libsql_value_to_json_value(&value),
-                    );
-                }
+                let value = row.get_value(i).unwrap_or(libsql::Value::Null);
+                let key = column_key(row.column_name(i), i);
+                let value = self.encrypted.decrypt(&key, value)?;
+                map.insert(key, self.libsql_value_to_json_value(&value));
             }
+            let mut columns: Vec<String> = map.keys().cloned().collect();
+            columns.sort();
             let json_value = serde_json::to_value(map)?;
-            let result: T = serde_json::from_value(json_value)?;
+            let result: T =
+                serde_json::from_value(json_value).map_err(|e| crate::Error::Deserialize {
+                    message: e.to_string(),
+                    columns,
+                })?;
             results.push(result);
         }
 
@@ -658,31 +2677,18 @@ impl QueryBuilder {
         T: serde::de::DeserializeOwned,
     {
         // Get total count
-        let count_builder = QueryBuilder::new(&self.table).select(vec!["COUNT(*) as count"]);
-
-        let (count_sql, count_params) = count_builder.build_count()?;
-        let mut count_rows = db.query(&count_sql, count_params).await?;
-        let total: u64 = if let Some(row) = count_rows.next().await? {
-            row.get_value(0)
-                .ok()
-                .and_then(|v| match v {
-                    libsql::Value::Integer(i) => Some(i as u64),
-                    _ => None,
-                })
-                .unwrap_or(0)
-        } else {
-            0
-        };
+        let total = self.clone_for_count().execute_count(db).await?;
 
         // Get paginated data
-        let data_builder = self
-            .clone()
-            .limit(pagination.limit())
-            .offset(pagination.offset());
+        let mut pagination = pagination.clone();
+        if let Some(max_limit) = self.max_limit {
+            pagination.per_page = pagination.per_page.min(max_limit);
+        }
+        let data_builder = self.clone().paginate(&pagination);
 
         let data = data_builder.execute::<T>(db).await?;
 
-        Ok(PaginatedResult::with_total(data, pagination.clone(), total))
+        Ok(PaginatedResult::with_total(data, pagination, total))
     }
 
     /// Convert libsql::Value to serde_json::Value
@@ -707,31 +2713,238 @@ impl QueryBuilder {
     }
 }
 
+impl std::fmt::Debug for QueryBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryBuilder")
+            .field("table", &self.table)
+            .field("sql", &self.build().map(|(sql, _)| sql))
+            .finish_non_exhaustive()
+    }
+}
+
 impl Clone for QueryBuilder {
     fn clone(&self) -> Self {
         Self {
             table: self.table.clone(),
             select_columns: self.select_columns.clone(),
+            select_subqueries: self.select_subqueries.clone(),
+            select_exprs: self.select_exprs.clone(),
             joins: self.joins.clone(),
             where_clauses: self.where_clauses.clone(),
+            where_combinator: self.where_combinator,
+            named_params: self.named_params.clone(),
             group_by: self.group_by.clone(),
             having: self.having.clone(),
+            having_raw: self.having_raw.clone(),
             order_by: self.order_by.clone(),
+            order_random: self.order_random,
             limit: self.limit,
+            max_limit: self.max_limit,
             offset: self.offset,
+            page: self.page,
             distinct: self.distinct,
-            aggregate: self.aggregate.clone(),
+            aggregates: self.aggregates.clone(),
+            index_hint: self.index_hint.clone(),
+            source_params: self.source_params.clone(),
+            debug_explain: self.debug_explain,
+            unions: self.unions.clone(),
+            ctes: self.ctes.clone(),
+            recursive_ctes: self.recursive_ctes,
+            label: self.label.clone(),
+            nested_error: self.nested_error.clone(),
+            encrypted: self.encrypted.clone(),
+            insert_columns: self.insert_columns.clone(),
+            insert_rows: self.insert_rows.clone(),
+            set_values: self.set_values.clone(),
+            allow_full_delete: self.allow_full_delete,
+            allow_full_update: self.allow_full_update,
+            quote_identifiers: self.quote_identifiers,
+            returning: self.returning.clone(),
         }
     }
 }
 
+/// Stable hash of a column value for distinct counting, `None` for `NULL`
+/// Whether `min > max`, i.e. a `BETWEEN` that can never match
+///
+/// Only values of the same kind are compared, numbers also across integer and
+/// real; anything else isn't treated as reversed.
+pub(crate) fn is_reversed_range(min: &Value, max: &Value) -> bool {
+    match (min, max) {
+        (Value::Integer(min), Value::Integer(max)) => min > max,
+        (Value::Integer(min), Value::Real(max)) => (*min as f64) > *max,
+        (Value::Real(min), Value::Integer(max)) => *min > (*max as f64),
+        (Value::Real(min), Value::Real(max)) => min > max,
+        (Value::Text(min), Value::Text(max)) => min > max,
+        _ => false,
+    }
+}
+
+/// Reversed bounds are valid SQL, so they are only logged, not rejected
+fn warn_if_reversed(column: &str, min: &Value, max: &Value) {
+    if is_reversed_range(min, max) {
+        log::warn!("BETWEEN on {column} has min {min:?} > max {max:?} and never matches");
+    }
+}
+
+/// Letters, digits and underscores, not starting with a digit
+fn is_plain_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Escape the LIKE wildcards in `text` for a pattern with `ESCAPE '\'`
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Number the `?` placeholders outside quoted literals as `:p1`, `:p2`, ...
+fn name_placeholders(sql: &str) -> (String, usize) {
+    let mut named = String::with_capacity(sql.len());
+    let mut quote: Option<char> = None;
+    let mut count = 0;
+    for c in sql.chars() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                named.push(c);
+            }
+            None if c == '?' => {
+                count += 1;
+                named.push_str(&format!(":p{count}"));
+            }
+            None => {
+                if matches!(c, '\'' | '"' | '`') {
+                    quote = Some(c);
+                }
+                named.push(c);
+            }
+        }
+    }
+    (named, count)
+}
+
+/// Replace the `:name` placeholders outside quoted literals with `?`
+///
+/// Also returns the names in order, with the position of each among all the
+/// placeholders, `?` included.
+fn positional_placeholders(sql: &str) -> (String, Vec<(usize, String)>) {
+    let mut positional = String::with_capacity(sql.len());
+    let mut names = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut placeholders = 0;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                positional.push(c);
+            }
+            None if c == ':'
+                && chars
+                    .peek()
+                    .is_some_and(|&n| n.is_ascii_alphabetic() || n == '_') =>
+            {
+                let mut name = String::new();
+                while let Some(n) = chars.next_if(|&n| n.is_ascii_alphanumeric() || n == '_') {
+                    name.push(n);
+                }
+                names.push((placeholders, name));
+                placeholders += 1;
+                positional.push('?');
+            }
+            None => {
+                if c == '?' {
+                    placeholders += 1;
+                } else if matches!(c, '\'' | '"' | '`') {
+                    quote = Some(c);
+                }
+                positional.push(c);
+            }
+        }
+    }
+    (positional, names)
+}
+
+/// Collapse whitespace runs outside of quoted literals into single spaces and
+/// trim the ends, so equivalent builders produce byte-identical SQL
+fn normalize_sql(sql: &str) -> String {
+    let mut normalized = String::with_capacity(sql.len());
+    let mut quote: Option<char> = None;
+    let mut pending_space = false;
+    for c in sql.chars() {
+        match quote {
+            Some(q) => {
+                normalized.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None if c.is_whitespace() => pending_space = true,
+            None => {
+                if pending_space && !normalized.is_empty() {
+                    normalized.push(' ');
+                }
+                pending_space = false;
+                if matches!(c, '\'' | '"' | '`') {
+                    quote = Some(c);
+                }
+                normalized.push(c);
+            }
+        }
+    }
+    normalized
+}
+
+fn contains_raw(filter: &FilterOperator) -> bool {
+    match filter {
+        FilterOperator::Custom(_) | FilterOperator::Raw(..) => true,
+        FilterOperator::And(filters) | FilterOperator::Or(filters) => {
+            filters.iter().any(contains_raw)
+        }
+        FilterOperator::Not(filter) => contains_raw(filter),
+        FilterOperator::Exists(subquery)
+        | FilterOperator::NotExists(subquery)
+        | FilterOperator::InSubquery(_, subquery) => subquery.validate_no_raw().is_err(),
+        FilterOperator::Single(_) | FilterOperator::Between(..) | FilterOperator::Contains(..) => {
+            false
+        }
+    }
+}
+
+/// Map key for a result column
+///
+/// Unnamed expressions (e.g. a computed column the driver reports without a name)
+/// get a stable positional key `col_{index}` instead of being dropped. Alias the
+/// expression with `AS` to deserialize it into a named field.
+pub(crate) fn column_key(name: Option<&str>, index: i32) -> String {
+    match name {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => format!("col_{index}"),
+    }
+}
+
 impl Clone for JoinClause {
     fn clone(&self) -> Self {
         Self {
             join_type: self.join_type,
             table: self.table.clone(),
             alias: self.alias.clone(),
-            condition: self.condition.clone(),
+            constraint: self.constraint.clone(),
         }
     }
 }
@@ -102,3 +102,2933 @@ mod value_tests {
         assert_eq!(value, Value::Null);
     }
 }
+
+#[cfg(test)]
+mod database_tests {
+    use crate::Database;
+
+    async fn seeded_db() -> Database {
+        let db = Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, task TEXT NOT NULL);
+                 INSERT INTO items (task) VALUES ('milk'), ('eggs');",
+            )
+            .await
+            .unwrap();
+        db
+    }
+
+    #[tokio::test]
+    async fn test_query_scalar_integer() {
+        let db = seeded_db().await;
+        let count: Option<i64> = db
+            .query_scalar("SELECT COUNT(*) FROM items", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_query_scalar_text() {
+        let db = seeded_db().await;
+        let task: Option<String> = db
+            .query_scalar(
+                "SELECT task FROM items WHERE id = ?",
+                vec![libsql::Value::Integer(2)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(task.as_deref(), Some("eggs"));
+    }
+
+    #[test]
+    fn test_from_sql_rejects_integers_out_of_range() {
+        use crate::FromSql;
+
+        assert_eq!(u32::from_sql(libsql::Value::Integer(7)).unwrap(), 7);
+        assert!(matches!(
+            u64::from_sql(libsql::Value::Integer(-1)),
+            Err(crate::Error::Serialization(_))
+        ));
+        assert!(i32::from_sql(libsql::Value::Integer(i64::from(i32::MAX) + 1)).is_err());
+        assert!(u32::from_sql(libsql::Value::Integer(-5)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_execute_keeps_unaliased_aggregate() {
+        let db = seeded_db().await;
+        let rows: Vec<std::collections::HashMap<String, serde_json::Value>> =
+            crate::QueryBuilder::new("items")
+                .select(vec!["task", "LENGTH(task)"])
+                .execute(&db)
+                .await
+                .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].len(), 2);
+        assert_eq!(rows[0]["LENGTH(task)"], serde_json::json!(4));
+    }
+
+    #[tokio::test]
+    async fn test_execute_reports_columns_on_missing_field() {
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct WithQuantity {
+            task: String,
+            quantity: i64,
+        }
+
+        let db = seeded_db().await;
+        let err = crate::QueryBuilder::new("items")
+            .execute::<WithQuantity>(&db)
+            .await
+            .unwrap_err();
+
+        match &err {
+            crate::Error::Deserialize { message, columns } => {
+                assert!(message.contains("missing field `quantity`"), "{message}");
+                assert_eq!(columns, &vec!["id".to_string(), "task".to_string()]);
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+        assert!(err.to_string().contains("row columns: id, task"));
+    }
+
+    #[test]
+    fn test_unnamed_column_gets_positional_key() {
+        assert_eq!(crate::query::column_key(None, 2), "col_2");
+        assert_eq!(crate::query::column_key(Some(""), 0), "col_0");
+        assert_eq!(crate::query::column_key(Some("count"), 0), "count");
+    }
+
+    #[tokio::test]
+    async fn test_attach_joins_against_reference_db() {
+        let db = seeded_db().await;
+        db.attach(":memory:", "catalog").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE catalog.products (name TEXT NOT NULL, aisle INTEGER NOT NULL);
+                 INSERT INTO catalog.products (name, aisle) VALUES ('milk', 4), ('eggs', 2);",
+            )
+            .await
+            .unwrap();
+
+        let rows: Vec<std::collections::HashMap<String, serde_json::Value>> =
+            crate::QueryBuilder::new("items")
+                .select(vec!["items.task", "catalog.products.aisle"])
+                .join(
+                    crate::JoinType::Inner,
+                    "catalog.products",
+                    "catalog.products.name = items.task",
+                )
+                .order_by(crate::Sort::new(
+                    "catalog.products.aisle",
+                    crate::SortOrder::Asc,
+                ))
+                .execute(&db)
+                .await
+                .unwrap();
+        let tasks: Vec<&str> = rows.iter().map(|r| r["task"].as_str().unwrap()).collect();
+        assert_eq!(tasks, vec!["eggs", "milk"]);
+
+        db.detach("catalog").await.unwrap();
+        assert!(db
+            .query("SELECT * FROM catalog.products", vec![])
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_order_by_random_picks_varying_rows() {
+        let db = seeded_db().await;
+        let pick = crate::QueryBuilder::new("items")
+            .select(vec!["task"])
+            .order_by_random()
+            .limit(1);
+        let (sql, _) = pick.build().unwrap();
+        assert_eq!(sql, "SELECT task FROM items ORDER BY RANDOM() LIMIT 1");
+
+        let mut picked = std::collections::HashSet::new();
+        for _ in 0..64 {
+            let tasks: Vec<String> = pick.execute_column(&db, 0).await.unwrap();
+            assert_eq!(tasks.len(), 1);
+            picked.extend(tasks);
+        }
+        assert_eq!(picked.len(), 2);
+
+        let (sql, _) = crate::QueryBuilder::new("items")
+            .order_by(crate::Sort::new("task", crate::SortOrder::Asc))
+            .order_by_random()
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM items ORDER BY task ASC, RANDOM()");
+    }
+
+    #[tokio::test]
+    async fn test_exists_reports_matching_rows() {
+        let db = seeded_db().await;
+        let with_task = |task: &str| {
+            crate::QueryBuilder::new("items")
+                .r#where(crate::FilterOperator::Single(crate::Filter::eq(
+                    "task", task,
+                )))
+                .order_by(crate::Sort::new("id", crate::SortOrder::Desc))
+                .limit(1)
+                .offset(5)
+        };
+        assert!(with_task("milk").exists(&db).await.unwrap());
+        assert!(!with_task("butter").exists(&db).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_execute_column_collects_one_column() {
+        let db = seeded_db().await;
+        let tasks: Vec<String> = crate::QueryBuilder::new("items")
+            .select(vec!["id", "task"])
+            .order_by(crate::Sort::new("task", crate::SortOrder::Asc))
+            .execute_column(&db, 1)
+            .await
+            .unwrap();
+        assert_eq!(tasks, vec!["eggs", "milk"]);
+
+        let ids: Vec<i64> = crate::QueryBuilder::new("items")
+            .select(vec!["id"])
+            .execute_column(&db, 0)
+            .await
+            .unwrap();
+        assert_eq!(ids, vec![1, 2]);
+        assert!(crate::QueryBuilder::new("items")
+            .select(vec!["id"])
+            .execute_column::<i64>(&db, 3)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_count_by_orders_by_count() {
+        let db = seeded_db().await;
+        db.inner
+            .execute_batch(
+                "ALTER TABLE items ADD COLUMN category TEXT;
+                 UPDATE items SET category = 'dairy';
+                 INSERT INTO items (task, category) VALUES
+                    ('cheese', 'dairy'), ('apples', 'fruit'), ('bread', NULL),
+                    ('pears', 'fruit'), ('salt', NULL), ('ham', 'meat');",
+            )
+            .await
+            .unwrap();
+
+        let counts: Vec<(Option<String>, i64)> = crate::QueryBuilder::new("items")
+            .order_by(crate::Sort::asc("task"))
+            .count_by("category")
+            .execute_count_by(&db)
+            .await
+            .unwrap();
+        assert_eq!(
+            counts,
+            vec![
+                (Some("dairy".to_string()), 3),
+                (None, 2),
+                (Some("fruit".to_string()), 2),
+                (Some("meat".to_string()), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_named_binds_by_name() {
+        use std::collections::HashMap;
+
+        let db = seeded_db().await;
+        let params = HashMap::from([
+            ("task".to_string(), libsql::Value::Text("eggs".to_string())),
+            (":min_id".to_string(), libsql::Value::Integer(1)),
+        ]);
+        let mut rows = db
+            .query_named(
+                "SELECT id, task FROM items WHERE id >= :min_id AND task = :task",
+                params,
+            )
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<i64>(0).unwrap(), 2);
+        assert_eq!(row.get::<String>(1).unwrap(), "eggs");
+
+        let missing = HashMap::from([("task".to_string(), libsql::Value::Text("eggs".into()))]);
+        assert!(matches!(
+            db.query_named(
+                "SELECT id FROM items WHERE id = :id AND task = :task",
+                missing.clone()
+            )
+            .await,
+            Err(crate::Error::Validation(_))
+        ));
+        assert!(matches!(
+            db.query_named("SELECT id FROM items", missing).await,
+            Err(crate::Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_build_named_runs_with_query_named() {
+        let db = seeded_db().await;
+        let (sql, params) = crate::QueryBuilder::new("items")
+            .select(vec!["task"])
+            .r#where(crate::FilterOperator::Single(crate::Filter::ne(
+                "task", "milk",
+            )))
+            .r#where(crate::FilterOperator::Custom("task != '?'".to_string()))
+            .r#where(crate::FilterOperator::Single(crate::Filter::gt("id", 0i64)))
+            .build_named()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT task FROM items WHERE task != :p1 AND task != '?' AND id > :p2"
+        );
+
+        let mut rows = db.query_named(&sql, params).await.unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<String>(0).unwrap(), "eggs");
+        assert!(rows.next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_execute_map_derives_fields() {
+        #[derive(Debug, PartialEq)]
+        struct Scored {
+            task: String,
+            total: i64,
+        }
+
+        let db = seeded_db().await;
+        let rows = crate::QueryBuilder::new("items")
+            .select(vec!["task", "id", "id * 10"])
+            .order_by(crate::Sort::asc("id"))
+            .execute_map(&db, |row| {
+                let id: i64 = row.get(1)?;
+                let bonus: i64 = row.get(2)?;
+                Ok(Scored {
+                    task: row.get(0)?,
+                    total: id + bonus,
+                })
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                Scored {
+                    task: "milk".to_string(),
+                    total: 11
+                },
+                Scored {
+                    task: "eggs".to_string(),
+                    total: 22
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_distinct_estimate_is_close_to_exact() {
+        let db = seeded_db().await;
+        db.inner
+            .execute_batch(
+                "CREATE TABLE events (owner_id INTEGER);
+                 WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 20000)
+                 INSERT INTO events (owner_id) SELECT i % 5000 FROM n;
+                 INSERT INTO events (owner_id) VALUES (NULL);",
+            )
+            .await
+            .unwrap();
+        let exact: Option<i64> = db
+            .query_scalar("SELECT COUNT(DISTINCT owner_id) FROM events", vec![])
+            .await
+            .unwrap();
+        let exact = exact.unwrap() as f64;
+
+        let estimate = crate::QueryBuilder::new("events")
+            .count_distinct_estimate(&db, "owner_id")
+            .await
+            .unwrap() as f64;
+        assert!(
+            (estimate - exact).abs() / exact < 0.1,
+            "estimate {estimate} too far from {exact}"
+        );
+
+        let small = crate::QueryBuilder::new("items")
+            .count_distinct_estimate(&db, "task")
+            .await
+            .unwrap();
+        assert_eq!(small, 2);
+    }
+
+    #[tokio::test]
+    async fn test_backup_reopens_with_same_rows() {
+        let db = seeded_db().await;
+        let path = std::env::temp_dir().join(format!(
+            "libsql-orm-backup-{}-{:?}.db",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        db.backup_to(&path).await.unwrap();
+        let err = db.backup_to(&path).await.unwrap_err();
+        assert!(err.to_string().contains("already exists"), "{err}");
+
+        let restored = Database::new_local(path.to_str().unwrap()).await.unwrap();
+        let tasks: Vec<String> = crate::QueryBuilder::new("items")
+            .select(vec!["task"])
+            .order_by(crate::Sort::asc("id"))
+            .execute_column(&restored, 0)
+            .await
+            .unwrap();
+        assert_eq!(tasks, vec!["milk", "eggs"]);
+        drop(restored);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_fts_indexes_bulk_loaded_rows() {
+        let db = seeded_db().await;
+        db.inner
+            .execute_batch(
+                "CREATE VIRTUAL TABLE items_fts USING fts5 (task, content = 'items', content_rowid = 'id');
+                 INSERT INTO items (task) VALUES ('oat milk'), ('whole milk'), ('bread');",
+            )
+            .await
+            .unwrap();
+        let count_matches = "SELECT COUNT(*) FROM items_fts WHERE items_fts MATCH 'milk'";
+        let before: Option<i64> = db.query_scalar(count_matches, vec![]).await.unwrap();
+        assert_eq!(before, Some(0));
+
+        db.rebuild_fts("items_fts").await.unwrap();
+        let after: Option<i64> = db.query_scalar(count_matches, vec![]).await.unwrap();
+        assert_eq!(after, Some(3));
+
+        assert!(matches!(
+            db.rebuild_fts("items").await,
+            Err(crate::Error::NotFound(_))
+        ));
+        assert!(matches!(
+            db.rebuild_fts("missing_fts").await,
+            Err(crate::Error::NotFound(_))
+        ));
+        assert!(matches!(
+            db.rebuild_fts("items_fts; DROP TABLE items").await,
+            Err(crate::Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_analyze_fills_stats_used_by_planner() {
+        let db = seeded_db().await;
+        db.inner
+            .execute_batch(
+                "CREATE TABLE events (owner_id INTEGER, kind TEXT);
+                 CREATE INDEX idx_events_owner ON events (owner_id);
+                 WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 1000)
+                 INSERT INTO events (owner_id, kind) SELECT i % 100, 'added' FROM n;",
+            )
+            .await
+            .unwrap();
+
+        db.analyze().await.unwrap();
+        let stats: Option<String> = db
+            .query_scalar(
+                "SELECT stat FROM sqlite_stat1 WHERE idx = 'idx_events_owner'",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert_eq!(stats.as_deref(), Some("1000 10"));
+
+        let plan = crate::QueryBuilder::new("events")
+            .r#where(crate::FilterOperator::Single(crate::Filter::eq(
+                "owner_id", 7i64,
+            )))
+            .explain_query_plan(&db)
+            .await
+            .unwrap();
+        assert!(plan.iter().any(|step| step.contains("idx_events_owner")));
+
+        db.execute("DELETE FROM events WHERE owner_id > 10", vec![])
+            .await
+            .unwrap();
+        db.vacuum().await.unwrap();
+        db.savepoint("open").await.unwrap();
+        assert!(db.vacuum().await.is_err());
+        db.release_savepoint("open").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_cancels_slow_query() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let db = Arc::new(seeded_db().await);
+        let finished = Arc::new(AtomicBool::new(false));
+        let interrupter = {
+            let db = db.clone();
+            let finished = finished.clone();
+            // Keep interrupting until the query is gone, the first call may come
+            // before the statement started
+            std::thread::spawn(move || {
+                while !finished.load(Ordering::SeqCst) {
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                    db.interrupt().unwrap();
+                }
+            })
+        };
+
+        let result: crate::Result<Option<i64>> = db
+            .query_scalar(
+                "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 10000000000)
+                 SELECT COUNT(*) FROM n",
+                vec![],
+            )
+            .await;
+        finished.store(true, Ordering::SeqCst);
+        interrupter.join().unwrap();
+
+        assert!(
+            matches!(result, Err(crate::Error::Interrupted)),
+            "unexpected result: {result:?}"
+        );
+        let count: Option<i64> = db
+            .query_scalar("SELECT COUNT(*) FROM items", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_grouped_count_counts_groups() {
+        let db = seeded_db().await;
+        db.execute("INSERT INTO items (task) VALUES ('milk'), ('milk')", vec![])
+            .await
+            .unwrap();
+        let grouped = crate::QueryBuilder::new("items")
+            .select(vec!["task", "COUNT(*) AS copies"])
+            .group_by(vec!["task"])
+            .order_by(crate::Sort::new("task", crate::SortOrder::Asc))
+            .limit(1);
+
+        let (sql, _) = grouped.build_count().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT COUNT(*) FROM (SELECT task, COUNT(*) AS copies FROM items GROUP BY task)"
+        );
+        assert_eq!(grouped.execute_count(&db).await.unwrap(), 2);
+        assert_eq!(
+            grouped
+                .clone()
+                .having(crate::FilterOperator::Custom("copies > 1".to_string()))
+                .execute_count(&db)
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            crate::QueryBuilder::new("items")
+                .execute_count(&db)
+                .await
+                .unwrap(),
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rollback_to_savepoint_keeps_outer_transaction() {
+        let db = seeded_db().await;
+        db.execute("BEGIN", vec![]).await.unwrap();
+        db.execute("INSERT INTO items (task) VALUES ('bread')", vec![])
+            .await
+            .unwrap();
+
+        db.savepoint("merge").await.unwrap();
+        db.execute("DELETE FROM items WHERE task = 'milk'", vec![])
+            .await
+            .unwrap();
+        db.execute("INSERT INTO items (task) VALUES ('butter')", vec![])
+            .await
+            .unwrap();
+        db.rollback_to_savepoint("merge").await.unwrap();
+
+        db.execute("COMMIT", vec![]).await.unwrap();
+        let mut rows = db
+            .query("SELECT task FROM items ORDER BY id", vec![])
+            .await
+            .unwrap();
+        let mut tasks = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            tasks.push(row.get::<String>(0).unwrap());
+        }
+        assert_eq!(tasks, vec!["milk", "eggs", "bread"]);
+    }
+
+    #[tokio::test]
+    async fn test_release_savepoint_keeps_inner_changes() {
+        let db = seeded_db().await;
+        db.execute("BEGIN", vec![]).await.unwrap();
+        db.savepoint("merge").await.unwrap();
+        db.execute("DELETE FROM items WHERE task = 'milk'", vec![])
+            .await
+            .unwrap();
+        db.release_savepoint("merge").await.unwrap();
+        db.execute("COMMIT", vec![]).await.unwrap();
+
+        let count: Option<i64> = db
+            .query_scalar("SELECT COUNT(*) FROM items", vec![])
+            .await
+            .unwrap();
+        assert_eq!(count, Some(1));
+        assert!(matches!(
+            db.savepoint("a b").await,
+            Err(crate::Error::Validation(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_attach_rejects_invalid_schema_name() {
+        let db = seeded_db().await;
+        assert!(matches!(
+            db.attach(":memory:", "x; DROP TABLE items").await,
+            Err(crate::Error::Validation(_))
+        ));
+        assert!(db.attach(":memory:", "main").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_scalar_empty_result() {
+        let db = seeded_db().await;
+        let task: Option<String> = db
+            .query_scalar(
+                "SELECT task FROM items WHERE id = ?",
+                vec![libsql::Value::Integer(42)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(task, None);
+    }
+}
+
+#[cfg(test)]
+mod pagination_tests {
+    use crate::Pagination;
+
+    #[test]
+    fn test_page_computes_offset() {
+        let pagination = Pagination::page(3, 20);
+        assert_eq!(pagination.offset(), 40);
+        assert_eq!(pagination.limit(), 20);
+    }
+
+    #[test]
+    fn test_current_page_round_trips() {
+        for page in 1..=5 {
+            assert_eq!(Pagination::page(page, 20).current_page(), page);
+        }
+    }
+
+    #[test]
+    fn test_page_zero_is_first_page() {
+        let pagination = Pagination::page(0, 20);
+        assert_eq!(pagination.offset(), 0);
+        assert_eq!(pagination.current_page(), 1);
+    }
+
+    #[test]
+    fn test_huge_page_saturates_offset() {
+        let pagination = Pagination::page(u32::MAX, 20);
+        assert_eq!(pagination.offset(), u32::MAX);
+
+        let (sql, _) = crate::QueryBuilder::new("items")
+            .paginate(&pagination)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            format!("SELECT * FROM items LIMIT 20 OFFSET {}", u32::MAX)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginated_total_counts_filtered_rows() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, task TEXT NOT NULL, completed BOOLEAN NOT NULL);
+                 INSERT INTO items (task, completed) VALUES
+                     ('milk', 0), ('eggs', 1), ('bread', 0), ('flour', 0), ('rice', 1);",
+            )
+            .await
+            .unwrap();
+        let page: crate::PaginatedResult<std::collections::HashMap<String, serde_json::Value>> =
+            crate::QueryBuilder::new("items")
+                .r#where(crate::FilterOperator::Single(crate::Filter::eq(
+                    "completed",
+                    false,
+                )))
+                .order_by(crate::Sort::asc("id"))
+                .execute_paginated(&db, &Pagination::new(1, 2))
+                .await
+                .unwrap();
+        assert_eq!(page.pagination.total, Some(3));
+        assert_eq!(page.data.len(), 2);
+        assert_eq!(page.data[0]["task"], "milk");
+    }
+}
+
+#[cfg(test)]
+mod query_tests {
+    use crate::{FilterOperator, QueryBuilder, Value};
+
+    #[test]
+    fn test_between_renders_sql() {
+        let (sql, params) = QueryBuilder::new("items")
+            .r#where(FilterOperator::Between(
+                "created_at".to_string(),
+                Value::Text("2024-01-01".to_string()),
+                Value::Text("2024-12-31".to_string()),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM items WHERE created_at BETWEEN ? AND ?");
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_select_subquery_binds_its_params_first() {
+        let tag_count = QueryBuilder::new("item_tags")
+            .select_count()
+            .r#where(FilterOperator::Custom(
+                "item_tags.item_id = items.id".to_string(),
+            ))
+            .r#where(FilterOperator::Single(crate::Filter::ne(
+                "item_tags.tag_id",
+                9i64,
+            )));
+        let (sql, params) = QueryBuilder::new("items")
+            .select(vec!["items.id"])
+            .select_subquery(tag_count, "tag_count")
+            .r#where(FilterOperator::Single(crate::Filter::eq(
+                "items.owner_id",
+                "1",
+            )))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT items.id, (SELECT COUNT(*) FROM item_tags WHERE item_tags.item_id = items.id \
+             AND item_tags.tag_id != ?) AS tag_count FROM items WHERE items.owner_id = ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Integer(9),
+                libsql::Value::Text("1".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_assert_all_params_bound_accepts_typed_query() {
+        QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")))
+            .r#where(FilterOperator::Custom("task != 'why?'".to_string()))
+            .assert_all_params_bound();
+    }
+
+    #[test]
+    #[should_panic(expected = "query has 2 `?` placeholders but 1 bound parameters")]
+    fn test_assert_all_params_bound_rejects_unbound_custom() {
+        QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")))
+            .r#where(FilterOperator::Custom("category = ?".to_string()))
+            .assert_all_params_bound();
+    }
+
+    #[test]
+    fn test_filter_if_some_applies_only_present_values() {
+        let by_category = |category: Option<&str>| {
+            QueryBuilder::new("items")
+                .filter_if_some(category, |query, category| {
+                    query.r#where(FilterOperator::Single(crate::Filter::eq(
+                        "category", category,
+                    )))
+                })
+                .build()
+                .unwrap()
+        };
+        assert_eq!(
+            by_category(None),
+            QueryBuilder::new("items").build().unwrap()
+        );
+
+        let (sql, params) = by_category(Some("dairy"));
+        assert_eq!(sql, "SELECT * FROM items WHERE category = ?");
+        assert_eq!(params, vec![libsql::Value::Text("dairy".to_string())]);
+    }
+
+    #[test]
+    fn test_empty_in_list_renders_constant() {
+        let (sql, params) = QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(crate::Filter::in_values(
+                "id",
+                Vec::<i64>::new(),
+            )))
+            .r#where(FilterOperator::Single(crate::Filter::not_in_values(
+                "owner_id",
+                Vec::<i64>::new(),
+            )))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM items WHERE 1 = 0 AND 1 = 1");
+        assert!(params.is_empty());
+
+        let empty_eq = crate::Filter::new(
+            "id",
+            crate::Operator::Eq,
+            crate::filters::FilterValue::Multiple(vec![]),
+        );
+        assert!(matches!(
+            QueryBuilder::new("items")
+                .r#where(FilterOperator::Single(empty_eq))
+                .build(),
+            Err(crate::Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_reversed_range_is_detected_but_still_builds() {
+        use crate::query::is_reversed_range;
+
+        assert!(is_reversed_range(&Value::Integer(10), &Value::Integer(1)));
+        assert!(is_reversed_range(&Value::Real(2.5), &Value::Integer(2)));
+        assert!(is_reversed_range(
+            &Value::Text("2024-12-31".to_string()),
+            &Value::Text("2024-01-01".to_string())
+        ));
+        assert!(!is_reversed_range(&Value::Integer(1), &Value::Integer(1)));
+        assert!(!is_reversed_range(
+            &Value::Text("b".to_string()),
+            &Value::Integer(1)
+        ));
+
+        let (sql, params) = QueryBuilder::new("items")
+            .r#where(FilterOperator::Between(
+                "id".to_string(),
+                Value::Integer(10),
+                Value::Integer(1),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM items WHERE id BETWEEN ? AND ?");
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_indexed_by_hint() {
+        let (sql, _) = QueryBuilder::new("items")
+            .indexed_by("idx_owner_completed")
+            .r#where(FilterOperator::Custom("completed = 0".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items INDEXED BY idx_owner_completed WHERE completed = 0"
+        );
+
+        let (count_sql, _) = QueryBuilder::new("items")
+            .not_indexed()
+            .build_count()
+            .unwrap();
+        assert_eq!(count_sql, "SELECT COUNT(*) FROM items NOT INDEXED");
+    }
+
+    #[test]
+    fn test_indexed_by_rejects_invalid_identifier() {
+        let result = QueryBuilder::new("items")
+            .indexed_by("idx; DROP TABLE items")
+            .build();
+        assert!(matches!(result, Err(crate::Error::Query(_))));
+    }
+
+    #[test]
+    fn test_clear_where_keeps_joins() {
+        let base = QueryBuilder::new("items")
+            .join(crate::JoinType::Left, "users", "users.id = items.owner_id")
+            .r#where(FilterOperator::Custom("items.completed = 0".to_string()))
+            .order_by(crate::Sort::new("items.id", crate::SortOrder::Desc))
+            .limit(10)
+            .offset(20);
+
+        let (sql, _) = base.clone().clear_where().build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items LEFT JOIN users ON users.id = items.owner_id ORDER BY items.id DESC LIMIT 10 OFFSET 20"
+        );
+
+        let (sql, _) = base.clear_order_by().clear_limit_offset().build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items LEFT JOIN users ON users.id = items.owner_id WHERE items.completed = 0"
+        );
+    }
+
+    #[test]
+    fn test_build_redacted_hides_values() {
+        let (sql, params) = QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(crate::Filter::eq(
+                "owner_id", "secret",
+            )))
+            .r#where(FilterOperator::Single(crate::Filter::eq(
+                "completed",
+                false,
+            )))
+            .build_redacted()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items WHERE owner_id = ? AND completed = ?"
+        );
+        assert_eq!(params, vec!["<text>", "<integer>"]);
+    }
+
+    #[test]
+    fn test_paginate_matches_limit_offset() {
+        let pagination = crate::Pagination::page(3, 25);
+        let explicit = QueryBuilder::new("items")
+            .limit(pagination.limit())
+            .offset(pagination.offset())
+            .build()
+            .unwrap();
+        let paginated = QueryBuilder::new("items")
+            .paginate(&pagination)
+            .build()
+            .unwrap();
+        assert_eq!(paginated.0, explicit.0);
+        assert_eq!(paginated.0, "SELECT * FROM items LIMIT 25 OFFSET 50");
+    }
+
+    #[test]
+    fn test_paginate_is_clamped_by_max_limit() {
+        let (sql, _) = QueryBuilder::new("items")
+            .max_limit(100)
+            .paginate(&crate::Pagination::page(1, 10_000))
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM items LIMIT 100 OFFSET 0");
+    }
+
+    #[test]
+    fn test_clamped_pages_stay_contiguous() {
+        for (page, offset) in [(2, 20), (3, 40)] {
+            let (sql, _) = QueryBuilder::new("items")
+                .max_limit(20)
+                .paginate(&crate::Pagination::page(page, 100))
+                .build()
+                .unwrap();
+            assert_eq!(sql, format!("SELECT * FROM items LIMIT 20 OFFSET {offset}"));
+        }
+
+        // `max_limit` set after `paginate` clamps the offset as well
+        let (sql, _) = QueryBuilder::new("items")
+            .paginate(&crate::Pagination::page(2, 100))
+            .max_limit(20)
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM items LIMIT 20 OFFSET 20");
+    }
+
+    #[test]
+    fn test_validate_no_raw_rejects_custom_clause() {
+        let typed = QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")))
+            .having(FilterOperator::Between(
+                "id".to_string(),
+                Value::Integer(1),
+                Value::Integer(9),
+            ));
+        assert!(typed.validate_no_raw().is_ok());
+
+        let nested_raw = typed.r#where(FilterOperator::Not(Box::new(FilterOperator::Or(vec![
+            FilterOperator::Single(crate::Filter::eq("completed", false)),
+            FilterOperator::Custom("1 = 1".to_string()),
+        ]))));
+        assert!(matches!(
+            nested_raw.validate_no_raw(),
+            Err(crate::Error::Validation(_))
+        ));
+    }
+
+    #[test]
+    fn test_build_normalizes_whitespace() {
+        let spaced = QueryBuilder::new("items")
+            .select(vec!["id", " task "])
+            .r#where(FilterOperator::Custom(
+                "\n  task  =  'two  spaces'\t".to_string(),
+            ))
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")))
+            .build()
+            .unwrap();
+        let tidy = QueryBuilder::new("items")
+            .select(vec!["id", "task"])
+            .r#where(FilterOperator::Custom("task = 'two  spaces'".to_string()))
+            .with_filter(crate::Filter::eq("owner_id", "1"))
+            .build()
+            .unwrap();
+        assert_eq!(spaced.0, tidy.0);
+        assert_eq!(
+            tidy.0,
+            "SELECT id, task FROM items WHERE task = 'two  spaces' AND owner_id = ?"
+        );
+
+        let (count_sql, _) = QueryBuilder::new("items")
+            .r#where(FilterOperator::Custom(" completed = 0 ".to_string()))
+            .build_count()
+            .unwrap();
+        assert_eq!(count_sql, "SELECT COUNT(*) FROM items WHERE completed = 0");
+    }
+
+    #[test]
+    fn test_where_between_and_not_between_bind_min_then_max() {
+        for (builder, keyword) in [
+            (
+                QueryBuilder::new("items").where_between(
+                    "price",
+                    Value::Real(1.5),
+                    Value::Real(4.0),
+                ),
+                "BETWEEN",
+            ),
+            (
+                QueryBuilder::new("items").where_not_between(
+                    "price",
+                    Value::Real(1.5),
+                    Value::Real(4.0),
+                ),
+                "NOT BETWEEN",
+            ),
+        ] {
+            let (sql, params) = builder.build().unwrap();
+            assert_eq!(
+                sql,
+                format!("SELECT * FROM items WHERE price {keyword} ? AND ?")
+            );
+            assert_eq!(
+                params,
+                vec![libsql::Value::Real(1.5), libsql::Value::Real(4.0)]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sort_places_nulls_as_requested() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, due TEXT);
+                 INSERT INTO items (id, due) VALUES (1, '2024-02-01'), (2, NULL), (3, '2024-01-01');",
+            )
+            .await
+            .unwrap();
+        for (sort, order_by, ids) in [
+            (crate::Sort::asc("due"), "due ASC", vec![2, 3, 1]),
+            (
+                crate::Sort::asc("due").nulls_last(),
+                "due ASC NULLS LAST",
+                vec![3, 1, 2],
+            ),
+            (
+                crate::Sort::desc("due").nulls_first(),
+                "due DESC NULLS FIRST",
+                vec![2, 1, 3],
+            ),
+        ] {
+            let builder = QueryBuilder::new("items").select(vec!["id"]).order_by(sort);
+            let (sql, _) = builder.build().unwrap();
+            assert_eq!(sql, format!("SELECT id FROM items ORDER BY {order_by}"));
+            let found: Vec<i64> = builder.execute_column(&db, 0).await.unwrap();
+            assert_eq!(found, ids, "{order_by}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_identifiers_allows_reserved_words() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                r#"CREATE TABLE "order" (id INTEGER PRIMARY KEY, "group" TEXT, "select" INTEGER);
+                   CREATE TABLE users (id INTEGER PRIMARY KEY, "order" INTEGER);
+                   INSERT INTO "order" (id, "group", "select") VALUES (1, 'a', 3), (2, 'a', NULL), (3, 'b', 1);
+                   INSERT INTO users (id, "order") VALUES (1, 1), (2, 3);"#,
+            )
+            .await
+            .unwrap();
+        let builder = || {
+            QueryBuilder::new("order")
+                .select(vec!["order.group", "COUNT(*) AS count", "users.*"])
+                .join(
+                    crate::JoinType::Left,
+                    "users",
+                    r#"users."order" = "order".id"#,
+                )
+                .r#where(FilterOperator::Single(crate::Filter::is_not_null("select")))
+                .group_by(vec!["order.group"])
+                .order_by(crate::Sort::asc("order.group"))
+        };
+
+        let (bare, _) = builder().build().unwrap();
+        assert_eq!(
+            bare,
+            r#"SELECT order.group, COUNT(*) AS count, users.* FROM order LEFT JOIN users ON users."order" = "order".id WHERE select IS NOT NULL GROUP BY order.group ORDER BY order.group ASC"#
+        );
+        assert!(builder().execute_count(&db).await.is_err());
+
+        let quoted = builder().quote_identifiers(true);
+        let (sql, _) = quoted.build().unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT "order"."group", COUNT(*) AS count, "users".* FROM "order" LEFT JOIN "users" ON users."order" = "order".id WHERE "select" IS NOT NULL GROUP BY "order"."group" ORDER BY "order"."group" ASC"#
+        );
+        let groups: Vec<String> = quoted.execute_column(&db, 0).await.unwrap();
+        assert_eq!(groups, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_multiple_aggregates_follow_group_column() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER, amount REAL);
+                 INSERT INTO orders (user_id, amount) VALUES (1, 2.5), (1, 4.0), (2, 10.0);",
+            )
+            .await
+            .unwrap();
+        let builder = QueryBuilder::new("orders")
+            .select(vec!["user_id"])
+            .aggregate(crate::Aggregate::Count, "*", Some("order_count"))
+            .aggregate(crate::Aggregate::Sum, "amount", Some("total"))
+            .group_by(vec!["user_id"])
+            .order_by(crate::Sort::asc("user_id"));
+        let (sql, _) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT user_id, COUNT(*) AS order_count, SUM(amount) AS total FROM orders \
+             GROUP BY user_id ORDER BY user_id ASC"
+        );
+
+        let rows: Vec<std::collections::HashMap<String, serde_json::Value>> =
+            builder.execute(&db).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["order_count"], 2);
+        assert_eq!(rows[0]["total"], 6.5);
+
+        let (single, _) = QueryBuilder::new("orders")
+            .aggregate(crate::Aggregate::Max, "amount", None::<String>)
+            .build()
+            .unwrap();
+        assert_eq!(single, "SELECT MAX(amount) FROM orders");
+    }
+
+    #[tokio::test]
+    async fn test_select_case_binds_values_in_branch_order() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, completed BOOLEAN, quantity INTEGER);
+                 INSERT INTO items (completed, quantity) VALUES (1, 1), (0, 12), (0, 2);",
+            )
+            .await
+            .unwrap();
+        let status = crate::CaseExpr::new()
+            .when(
+                FilterOperator::Single(crate::Filter::eq("completed", true)),
+                Value::from("done"),
+            )
+            .when(
+                FilterOperator::Single(crate::Filter::gt("quantity", 10i64)),
+                Value::from("bulk"),
+            )
+            .else_(Value::from("open"))
+            .as_alias("status");
+        let builder = QueryBuilder::new("items")
+            .select(vec!["id"])
+            .select_case(status)
+            .r#where(FilterOperator::Single(crate::Filter::ne("id", 99i64)))
+            .order_by(crate::Sort::asc("id"));
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, CASE WHEN completed = ? THEN ? WHEN quantity > ? THEN ? ELSE ? END AS status \
+             FROM items WHERE id != ? ORDER BY id ASC"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Integer(1),
+                libsql::Value::Text("done".to_string()),
+                libsql::Value::Integer(10),
+                libsql::Value::Text("bulk".to_string()),
+                libsql::Value::Text("open".to_string()),
+                libsql::Value::Integer(99),
+            ]
+        );
+        let statuses: Vec<String> = builder.execute_column(&db, 1).await.unwrap();
+        assert_eq!(statuses, vec!["done", "bulk", "open"]);
+
+        let empty = QueryBuilder::new("items").select_case(crate::CaseExpr::new());
+        assert!(matches!(empty.build(), Err(crate::Error::Query(_))));
+    }
+
+    #[tokio::test]
+    async fn test_select_window_renders_partition_and_order() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id TEXT, quantity INTEGER);
+                 INSERT INTO items (owner_id, quantity) VALUES
+                     ('1', 5), ('1', 5), ('1', 2), ('2', 7);",
+            )
+            .await
+            .unwrap();
+        let builder = QueryBuilder::new("items")
+            .select(vec!["id"])
+            .select_window(
+                crate::WindowFunc::RowNumber,
+                vec!["owner_id"],
+                vec![crate::Sort::desc("quantity"), crate::Sort::asc("id")],
+                "rn",
+            )
+            .select_window(
+                crate::WindowFunc::Rank,
+                vec!["owner_id"],
+                vec![crate::Sort::desc("quantity")],
+                "rank",
+            )
+            .select_window(
+                crate::WindowFunc::DenseRank,
+                vec![],
+                vec![crate::Sort::desc("quantity")],
+                "dense",
+            )
+            .order_by(crate::Sort::asc("id"));
+        let (sql, _) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id, \
+             ROW_NUMBER() OVER (PARTITION BY owner_id ORDER BY quantity DESC, id ASC) AS rn, \
+             RANK() OVER (PARTITION BY owner_id ORDER BY quantity DESC) AS rank, \
+             DENSE_RANK() OVER (ORDER BY quantity DESC) AS dense \
+             FROM items ORDER BY id ASC"
+        );
+
+        let rows: Vec<std::collections::HashMap<String, i64>> = builder.execute(&db).await.unwrap();
+        let ranks: Vec<(i64, i64, i64)> = rows
+            .iter()
+            .map(|row| (row["rn"], row["rank"], row["dense"]))
+            .collect();
+        assert_eq!(ranks, vec![(1, 1, 2), (2, 1, 2), (3, 3, 3), (1, 1, 1)]);
+    }
+
+    #[test]
+    fn test_select_coalesce_renders_fragment() {
+        let (sql, params) = QueryBuilder::new("totals")
+            .select(vec!["owner_id"])
+            .select_coalesce(vec!["sum_amount", "0"], Some("amount"))
+            .select_coalesce(vec!["a", "b"], None)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT owner_id, COALESCE(sum_amount, 0) AS amount, COALESCE(a, b) FROM totals"
+        );
+        assert!(params.is_empty());
+
+        let err = QueryBuilder::new("totals")
+            .select_coalesce(vec![], Some("amount"))
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Query(_)));
+    }
+
+    #[tokio::test]
+    async fn test_select_raw_binds_ahead_of_where() {
+        let builder = QueryBuilder::new("items")
+            .select(vec!["owner_id"])
+            .select_raw("strftime(?, created_at) AS yr", vec![Value::from("%Y")])
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")));
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT owner_id, strftime(?, created_at) AS yr FROM items WHERE owner_id = ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("%Y".to_string()),
+                libsql::Value::Text("1".to_string()),
+            ]
+        );
+        assert!(builder.validate_no_raw().is_err());
+
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id TEXT, created_at TEXT);
+                 INSERT INTO items (owner_id, created_at) VALUES
+                     ('1', '2024-03-01T10:00:00Z'), ('2', '2025-03-01T10:00:00Z');",
+            )
+            .await
+            .unwrap();
+        let rows: Vec<std::collections::HashMap<String, String>> =
+            builder.execute(&db).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["yr"], "2024");
+
+        let err = QueryBuilder::new("items")
+            .select_raw("strftime(?, created_at)", vec![])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Validation(_)));
+    }
+
+    #[test]
+    fn test_bind_resolves_names_in_placeholder_order() {
+        let (sql, params) = QueryBuilder::new("items")
+            .where_condition(
+                "owner_id = :owner AND quantity BETWEEN ? AND :max AND task != ':owner'",
+                vec![libsql::Value::Integer(2)],
+            )
+            .r#where(FilterOperator::Or(vec![
+                FilterOperator::Custom("tag = :tag".to_string()),
+                FilterOperator::Custom("owner_id = :owner".to_string()),
+            ]))
+            .bind("tag", Value::from("dairy"))
+            .bind(":max", Value::from(10i64))
+            .bind("owner", Value::from("2"))
+            .bind("owner", Value::from("1"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items WHERE owner_id = ? AND quantity BETWEEN ? AND ? \
+             AND task != ':owner' AND (tag = ? OR owner_id = ?)"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("1".to_string()),
+                libsql::Value::Integer(2),
+                libsql::Value::Integer(10),
+                libsql::Value::Text("dairy".to_string()),
+                libsql::Value::Text("1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bind_rejects_missing_and_unused_bindings() {
+        let missing = QueryBuilder::new("items")
+            .r#where(FilterOperator::Custom(
+                "owner_id = :owner AND quantity > :min".to_string(),
+            ))
+            .bind("owner", Value::from("1"))
+            .build()
+            .unwrap_err();
+        assert!(
+            matches!(&missing, crate::Error::Validation(msg) if msg.contains(":min")),
+            "{missing:?}"
+        );
+
+        let unused = QueryBuilder::new("items")
+            .r#where(FilterOperator::Custom("owner_id = :owner".to_string()))
+            .bind("owner", Value::from("1"))
+            .bind("ownr", Value::from("1"))
+            .build_count()
+            .unwrap_err();
+        assert!(
+            matches!(&unused, crate::Error::Validation(msg) if msg.contains(":ownr")),
+            "{unused:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_from_subquery_averages_group_counts() {
+        let counts = QueryBuilder::new("items")
+            .select(vec!["owner_id", "COUNT(*) AS cnt"])
+            .r#where(FilterOperator::Single(crate::Filter::ne("task", "skip")))
+            .group_by(vec!["owner_id"]);
+        let builder = QueryBuilder::from_subquery(counts, "counts")
+            .aggregate(crate::Aggregate::Avg, "cnt", Some("avg_items"))
+            .r#where(FilterOperator::Single(crate::Filter::ne("owner_id", "3")));
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT AVG(cnt) AS avg_items FROM (SELECT owner_id, COUNT(*) AS cnt FROM items \
+             WHERE task != ? GROUP BY owner_id) AS counts WHERE owner_id != ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("skip".to_string()),
+                libsql::Value::Text("3".to_string()),
+            ]
+        );
+
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id TEXT, task TEXT);
+                 INSERT INTO items (owner_id, task) VALUES
+                     ('1', 'milk'), ('1', 'eggs'), ('1', 'skip'), ('2', 'rice'),
+                     ('3', 'tea'), ('3', 'jam'), ('3', 'oil');",
+            )
+            .await
+            .unwrap();
+        let rows: Vec<std::collections::HashMap<String, f64>> = builder.execute(&db).await.unwrap();
+        assert_eq!(rows[0]["avg_items"], 1.5);
+        assert_eq!(builder.execute_count(&db).await.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_clear_where_drops_bindings() {
+        let (sql, params) = QueryBuilder::new("items")
+            .r#where(FilterOperator::Custom("owner_id = :owner".to_string()))
+            .bind("owner", Value::from("1"))
+            .clear_where()
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM items");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_bind_resolves_names_in_select_and_having_expressions() {
+        let (sql, params) = QueryBuilder::new("items")
+            .select(vec!["owner_id"])
+            .select_raw("strftime(:format, created_at) AS yr", vec![])
+            .select_case(
+                crate::CaseExpr::new()
+                    .when(
+                        FilterOperator::Custom("quantity > :many".to_string()),
+                        Value::from("many"),
+                    )
+                    .as_alias("amount"),
+            )
+            .group_by(vec!["owner_id"])
+            .having_raw("COUNT(*) >= :min", vec![])
+            .bind("min", Value::from(2i64))
+            .bind("many", Value::from(5i64))
+            .bind("format", Value::from("%Y"))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT owner_id, strftime(?, created_at) AS yr, \
+             CASE WHEN quantity > ? THEN ? END AS amount \
+             FROM items GROUP BY owner_id HAVING (COUNT(*) >= ?)"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("%Y".to_string()),
+                libsql::Value::Integer(5),
+                libsql::Value::Text("many".to_string()),
+                libsql::Value::Integer(2),
+            ]
+        );
+
+        let missing = QueryBuilder::new("items")
+            .group_by(vec!["owner_id"])
+            .having_raw("COUNT(*) >= :min", vec![])
+            .build()
+            .unwrap_err();
+        assert!(matches!(missing, crate::Error::Validation(_)));
+    }
+
+    #[test]
+    fn test_bind_resolves_names_in_join_conditions() {
+        let builder = QueryBuilder::new("items")
+            .select(vec!["items.task"])
+            .join(
+                crate::JoinType::Left,
+                "store_layouts",
+                "store_layouts.category = items.category AND store_layouts.store_id = :store",
+            )
+            .r#where(FilterOperator::Custom(
+                "items.owner_id = :owner".to_string(),
+            ))
+            .bind("owner", Value::from("1"))
+            .bind("store", Value::from("market"));
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT items.task FROM items LEFT JOIN store_layouts \
+             ON store_layouts.category = items.category AND store_layouts.store_id = ? \
+             WHERE items.owner_id = ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("market".to_string()),
+                libsql::Value::Text("1".to_string()),
+            ]
+        );
+        let (_, count_params) = builder.build_count().unwrap();
+        assert_eq!(count_params, params);
+    }
+
+    #[test]
+    fn test_between_binds_bounds_in_order() {
+        let (_, params) = QueryBuilder::new("items")
+            .r#where(FilterOperator::Between(
+                "id".to_string(),
+                Value::Integer(3),
+                Value::Integer(7),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(
+            params,
+            vec![libsql::Value::Integer(3), libsql::Value::Integer(7)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_having_raw_binds_threshold_after_where() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id TEXT, task TEXT, quantity INTEGER);
+                 INSERT INTO items (owner_id, task, quantity) VALUES
+                     ('1', 'milk', 2), ('1', 'milk', 3), ('1', 'eggs', 1), ('2', 'eggs', 9);",
+            )
+            .await
+            .unwrap();
+        let builder = QueryBuilder::new("items")
+            .select(vec!["task", "SUM(quantity) AS total"])
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")))
+            .group_by(vec!["task"])
+            .having(FilterOperator::Single(crate::Filter::gt("COUNT(*)", 0i64)))
+            .having_raw("SUM(quantity) > ?", vec![libsql::Value::Integer(4)]);
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT task, SUM(quantity) AS total FROM items WHERE owner_id = ? \
+             GROUP BY task HAVING COUNT(*) > ? AND (SUM(quantity) > ?)"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("1".to_string()),
+                libsql::Value::Integer(0),
+                libsql::Value::Integer(4),
+            ]
+        );
+        builder.assert_all_params_bound();
+
+        let rows: Vec<std::collections::HashMap<String, serde_json::Value>> =
+            builder.execute(&db).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["task"], "milk");
+        assert_eq!(rows[0]["total"], 5);
+
+        let (count_sql, count_params) = builder.build_count().unwrap();
+        assert!(count_sql.ends_with("HAVING COUNT(*) > ? AND (SUM(quantity) > ?))"));
+        assert_eq!(count_params.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_where_combinator_or_joins_top_level_clauses() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE tasks (id INTEGER PRIMARY KEY, status TEXT, priority INTEGER);
+                 INSERT INTO tasks (status, priority) VALUES
+                     ('open', 1), ('done', 9), ('done', 2), ('open', 7);",
+            )
+            .await
+            .unwrap();
+        let builder = QueryBuilder::new("tasks")
+            .select(vec!["id"])
+            .r#where(FilterOperator::Single(crate::Filter::eq("status", "open")))
+            .r#where(FilterOperator::And(vec![
+                FilterOperator::Single(crate::Filter::gt("priority", 5i64)),
+                FilterOperator::Single(crate::Filter::ne("status", "archived")),
+            ]))
+            .where_combinator(crate::Combinator::Or)
+            .order_by(crate::Sort::asc("id"));
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT id FROM tasks WHERE status = ? OR (priority > ? AND status != ?) ORDER BY id ASC"
+        );
+        assert_eq!(params.len(), 3);
+
+        let ids: Vec<i64> = builder.execute_column(&db, 0).await.unwrap();
+        assert_eq!(ids, vec![1, 2, 4]);
+        assert_eq!(builder.execute_count(&db).await.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_with_prefixes_cte_and_binds_its_params_first() {
+        let open = QueryBuilder::new("items")
+            .select(vec!["task", "quantity"])
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")));
+        let (sql, params) = QueryBuilder::new("open_items")
+            .with("open_items", open)
+            .select(vec!["task"])
+            .r#where(FilterOperator::Single(crate::Filter::gt("quantity", 2i64)))
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "WITH open_items AS (SELECT task, quantity FROM items WHERE owner_id = ?) \
+             SELECT task FROM open_items WHERE quantity > ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("1".to_string()),
+                libsql::Value::Integer(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_cte_fails_outer_build_with_its_name() {
+        let broken = QueryBuilder::new("items").indexed_by("no such index");
+        let err = QueryBuilder::new("open_items")
+            .with("open_items", broken)
+            .build()
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("with(open_items): Invalid index name"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_compound_member_with_cte_is_rejected() {
+        let open = QueryBuilder::new("items").r#where(FilterOperator::Single(crate::Filter::eq(
+            "completed",
+            false,
+        )));
+        let member = QueryBuilder::new("open_items")
+            .with("open_items", open)
+            .select(vec!["task"]);
+        let err = QueryBuilder::new("items")
+            .select(vec!["task"])
+            .union_all(member)
+            .build()
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("compound member on open_items must not use WITH"),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_recursive_walks_category_tree() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE categories (id INTEGER PRIMARY KEY, parent_id INTEGER, name TEXT);
+                 INSERT INTO categories (id, parent_id, name) VALUES
+                     (1, NULL, 'food'), (2, 1, 'dairy'), (3, 2, 'cheese'), (4, NULL, 'household');",
+            )
+            .await
+            .unwrap();
+        let tree = QueryBuilder::new("categories")
+            .select(vec!["id"])
+            .r#where(FilterOperator::Single(crate::Filter::eq("name", "food")))
+            .union_all(
+                QueryBuilder::new("categories")
+                    .select(vec!["categories.id"])
+                    .join(
+                        crate::JoinType::Inner,
+                        "subtree",
+                        "categories.parent_id = subtree.id",
+                    ),
+            );
+        let builder = QueryBuilder::new("categories")
+            .with_recursive("subtree(id)", tree)
+            .with(
+                "excluded",
+                QueryBuilder::new("categories")
+                    .select(vec!["id"])
+                    .r#where(FilterOperator::Single(crate::Filter::eq("name", "cheese"))),
+            )
+            .select(vec!["name"])
+            .r#where(FilterOperator::Custom(
+                "id IN (SELECT id FROM subtree) AND id NOT IN (SELECT id FROM excluded)"
+                    .to_string(),
+            ))
+            .order_by(crate::Sort::asc("id"));
+        let (sql, params) = builder.build().unwrap();
+        assert!(sql.starts_with("WITH RECURSIVE subtree(id) AS (SELECT id FROM categories"));
+        assert!(
+            sql.contains("), excluded AS (SELECT id FROM categories WHERE name = ?) SELECT name")
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("food".to_string()),
+                libsql::Value::Text("cheese".to_string()),
+            ]
+        );
+
+        let names: Vec<String> = builder.execute_column(&db, 0).await.unwrap();
+        assert_eq!(names, vec!["food".to_string(), "dairy".to_string()]);
+        assert_eq!(builder.execute_count(&db).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_exists_subquery_params_sit_between_scalar_filters() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, active BOOLEAN);
+                 CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id INTEGER, completed BOOLEAN);
+                 INSERT INTO users (id, name, active) VALUES (1, 'ada', 1), (2, 'bob', 1), (3, 'cy', 0);
+                 INSERT INTO items (owner_id, completed) VALUES (1, 1), (2, 0), (3, 1);",
+            )
+            .await
+            .unwrap();
+        let completed_items = || {
+            QueryBuilder::new("items")
+                .select(vec!["1"])
+                .r#where(FilterOperator::Custom(
+                    "items.owner_id = users.id".to_string(),
+                ))
+                .r#where(FilterOperator::Single(crate::Filter::eq("completed", true)))
+        };
+        let builder = QueryBuilder::new("users")
+            .select(vec!["name"])
+            .r#where(FilterOperator::Single(crate::Filter::eq("active", true)))
+            .r#where(FilterOperator::Exists(Box::new(completed_items())))
+            .r#where(FilterOperator::Single(crate::Filter::ne("name", "zed")));
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT name FROM users WHERE active = ? AND EXISTS (SELECT 1 FROM items \
+             WHERE items.owner_id = users.id AND completed = ?) AND name != ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Integer(1),
+                libsql::Value::Integer(1),
+                libsql::Value::Text("zed".to_string()),
+            ]
+        );
+        let names: Vec<String> = builder.execute_column(&db, 0).await.unwrap();
+        assert_eq!(names, vec!["ada"]);
+
+        let without: Vec<String> = QueryBuilder::new("users")
+            .select(vec!["name"])
+            .r#where(FilterOperator::NotExists(Box::new(completed_items())))
+            .execute_column(&db, 0)
+            .await
+            .unwrap();
+        assert_eq!(without, vec!["bob"]);
+    }
+
+    #[test]
+    fn test_search_binds_escaped_pattern() {
+        let builder = QueryBuilder::new("items").search("task", r"a_b' OR 1=1 --\");
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(sql, r"SELECT * FROM items WHERE task LIKE ? ESCAPE '\'");
+        assert_eq!(
+            params,
+            vec![libsql::Value::Text(r"%a\_b' OR 1=1 --\\%".to_string())]
+        );
+        builder.assert_all_params_bound();
+    }
+
+    #[test]
+    fn test_search_passes_validate_no_raw() {
+        let builder = QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")))
+            .search("task", "milk");
+        assert!(builder.validate_no_raw().is_ok());
+
+        let builder = builder.where_condition("quantity > ?", vec![libsql::Value::Integer(2)]);
+        assert!(builder.validate_no_raw().is_err());
+        assert!(builder.clone().clear_where().validate_no_raw().is_ok());
+
+        // Only the search condition itself passes, wherever it ends up
+        let raw_first = builder
+            .clear_where()
+            .where_condition("quantity > ?", vec![libsql::Value::Integer(2)])
+            .search("task", "milk");
+        assert!(raw_first.validate_no_raw().is_err());
+        let filter = FilterOperator::Or(vec![
+            FilterOperator::Contains("task".to_string(), "milk".to_string()),
+            FilterOperator::Custom("1 = 1".to_string()),
+        ]);
+        assert!(QueryBuilder::new("items")
+            .r#where(filter)
+            .validate_no_raw()
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_wildcards_literally() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, task TEXT NOT NULL);
+                 INSERT INTO items (task) VALUES ('a_b'), ('axb'), ('50% off'), ('500 g');",
+            )
+            .await
+            .unwrap();
+        for (query, expected) in [("a_b", "a_b"), ("50%", "50% off")] {
+            let tasks: Vec<String> = QueryBuilder::new("items")
+                .select(vec!["task"])
+                .search("task", query)
+                .execute_column(&db, 0)
+                .await
+                .unwrap();
+            assert_eq!(tasks, vec![expected.to_string()]);
+        }
+    }
+
+    #[test]
+    fn test_where_condition_binds_params_in_order() {
+        let builder = QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(crate::Filter::eq("owner_id", "1")))
+            .where_condition(
+                "quantity > ? AND task != ?",
+                vec![
+                    libsql::Value::Integer(18),
+                    libsql::Value::Text("milk".to_string()),
+                ],
+            );
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items WHERE owner_id = ? AND quantity > ? AND task != ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("1".to_string()),
+                libsql::Value::Integer(18),
+                libsql::Value::Text("milk".to_string()),
+            ]
+        );
+        builder.assert_all_params_bound();
+        assert!(builder.validate_no_raw().is_err());
+
+        let err = QueryBuilder::new("items")
+            .where_condition("quantity > ?", vec![])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Validation(_)));
+    }
+
+    #[test]
+    fn test_having_raw_rejects_param_mismatch() {
+        let err = QueryBuilder::new("items")
+            .group_by(vec!["task"])
+            .having_raw(
+                "SUM(quantity) BETWEEN ? AND ?",
+                vec![libsql::Value::Integer(1)],
+            )
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Validation(_)));
+    }
+}
+
+#[cfg(test)]
+mod join_tests {
+    use crate::{Database, JoinType, QueryBuilder};
+    use std::collections::HashMap;
+
+    async fn seeded_db() -> Database {
+        let db = Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+                 CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id INTEGER, task TEXT NOT NULL);
+                 INSERT INTO users (id, name) VALUES (1, 'ada'), (2, 'bob');
+                 INSERT INTO items (owner_id, task) VALUES (1, 'milk'), (1, 'eggs'), (3, 'flour');",
+            )
+            .await
+            .unwrap();
+        db
+    }
+
+    async fn joined_rows(join_type: JoinType) -> (String, usize) {
+        let db = seeded_db().await;
+        let builder = QueryBuilder::new("items")
+            .select(vec!["items.task", "users.name"])
+            .join(join_type, "users", "users.id = items.owner_id");
+        let (sql, _) = builder.build().unwrap();
+        let rows: Vec<HashMap<String, serde_json::Value>> = builder.execute(&db).await.unwrap();
+        (sql, rows.len())
+    }
+
+    #[tokio::test]
+    async fn test_join_using_renders_shared_columns() {
+        let db = seeded_db().await;
+        db.inner
+            .execute_batch(
+                "CREATE TABLE stock (owner_id INTEGER, task TEXT, quantity INTEGER);
+                 INSERT INTO stock VALUES (1, 'milk', 2), (2, 'milk', 5), (1, 'flour', 1);",
+            )
+            .await
+            .unwrap();
+        let builder = QueryBuilder::new("items")
+            .select(vec!["task", "quantity", "users.name"])
+            .join(JoinType::Inner, "users", "users.id = items.owner_id")
+            .join_using(JoinType::Inner, "stock", vec!["owner_id", "task"]);
+        let (sql, _) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT task, quantity, users.name FROM items \
+             INNER JOIN users ON users.id = items.owner_id \
+             INNER JOIN stock USING (owner_id, task)"
+        );
+
+        let rows: Vec<HashMap<String, serde_json::Value>> = builder.execute(&db).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["quantity"], 2);
+        assert_eq!(builder.execute_count(&db).await.unwrap(), 1);
+
+        let err = QueryBuilder::new("items")
+            .join_using(JoinType::Inner, "stock", vec![])
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Query(_)));
+    }
+
+    #[tokio::test]
+    async fn test_cross_join_has_no_on_clause() {
+        let db = seeded_db().await;
+        let builder = QueryBuilder::new("items")
+            .select(vec!["items.task", "users.name"])
+            .cross_join("users");
+        let (sql, _) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT items.task, users.name FROM items CROSS JOIN users"
+        );
+        assert!(!sql.contains(" ON "));
+        let (count_sql, _) = builder.build_count().unwrap();
+        assert_eq!(count_sql, "SELECT COUNT(*) FROM items CROSS JOIN users");
+        assert_eq!(builder.execute_count(&db).await.unwrap(), 6);
+
+        let (sql, _) = QueryBuilder::new("items")
+            .join(JoinType::Cross, "users", "")
+            .build()
+            .unwrap();
+        assert_eq!(sql, "SELECT * FROM items CROSS JOIN users");
+    }
+
+    #[test]
+    fn test_join_type_display_includes_join() {
+        assert_eq!(JoinType::Inner.to_string(), "INNER JOIN");
+        assert_eq!(JoinType::Left.to_string(), "LEFT JOIN");
+        assert_eq!(JoinType::Right.to_string(), "RIGHT JOIN");
+        assert_eq!(JoinType::FullOuter.to_string(), "FULL OUTER JOIN");
+        assert_eq!(JoinType::Cross.to_string(), "CROSS JOIN");
+    }
+
+    #[test]
+    fn test_cross_join_type_round_trips_through_serde() {
+        let json = serde_json::to_string(&JoinType::Cross).unwrap();
+        assert_eq!(json, "\"Cross\"");
+        let decoded: JoinType = serde_json::from_str(&json).unwrap();
+        assert!(matches!(decoded, JoinType::Cross));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_full_outer_join_type_decodes_both_spellings() {
+        let json = serde_json::to_string(&JoinType::FullOuter).unwrap();
+        assert_eq!(json, "\"FullOuter\"");
+        let decoded: JoinType = serde_json::from_str(&json).unwrap();
+        assert!(matches!(decoded, JoinType::FullOuter));
+
+        // Written before the rename, and by the deprecated variant still
+        let old = serde_json::to_string(&JoinType::Full).unwrap();
+        assert_eq!(old, "\"Full\"");
+        let decoded: JoinType = serde_json::from_str(&old).unwrap();
+        assert!(matches!(decoded, JoinType::FullOuter));
+
+        assert!(serde_json::from_str::<JoinType>("\"Outer\"").is_err());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_deprecated_full_join_renders_full_outer_join() {
+        assert_eq!(JoinType::Full.to_string(), "FULL OUTER JOIN");
+        let (sql, _) = QueryBuilder::new("items")
+            .join(JoinType::Full, "users", "users.id = items.owner_id")
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items FULL OUTER JOIN users ON users.id = items.owner_id"
+        );
+        let parsed: JoinType = serde_json::from_str("\"Full\"").unwrap();
+        assert_eq!(parsed.to_string(), "FULL OUTER JOIN");
+    }
+
+    #[test]
+    fn test_build_and_build_count_render_each_join_type() {
+        for (join_type, keyword) in [
+            (JoinType::Inner, "INNER JOIN"),
+            (JoinType::Left, "LEFT JOIN"),
+            (JoinType::Right, "RIGHT JOIN"),
+            (JoinType::FullOuter, "FULL OUTER JOIN"),
+        ] {
+            let builder = QueryBuilder::new("items")
+                .select(vec!["items.task", "users.name"])
+                .join(join_type, "users", "users.id = items.owner_id");
+            let (sql, _) = builder.build().unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    "SELECT items.task, users.name FROM items {keyword} users ON users.id = items.owner_id"
+                )
+            );
+            let (count_sql, _) = builder.build_count().unwrap();
+            assert_eq!(
+                count_sql,
+                format!("SELECT COUNT(*) FROM items {keyword} users ON users.id = items.owner_id")
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clone_for_count_keeps_filters_joins_and_grouping() {
+        let db = seeded_db().await;
+        let builder = QueryBuilder::new("items")
+            .select(vec!["users.name", "COUNT(*) AS count"])
+            .join(JoinType::Inner, "users", "users.id = items.owner_id")
+            .r#where(crate::FilterOperator::Single(crate::Filter::ne(
+                "task", "flour",
+            )))
+            .group_by(vec!["users.name"])
+            .order_by(crate::Sort::desc("count"))
+            .limit(1)
+            .offset(1);
+
+        let (sql, params) = builder.clone_for_count().build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT users.name, COUNT(*) AS count FROM items \
+             INNER JOIN users ON users.id = items.owner_id \
+             WHERE task != ? GROUP BY users.name"
+        );
+        assert_eq!(params.len(), 1);
+
+        let total = builder.clone_for_count().execute_count(&db).await.unwrap();
+        assert_eq!(total, 1);
+        let page: crate::PaginatedResult<HashMap<String, serde_json::Value>> = builder
+            .execute_paginated(&db, &crate::Pagination::new(1, 10))
+            .await
+            .unwrap();
+        assert_eq!(page.pagination.total, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_inner_join_executes() {
+        let (sql, rows) = joined_rows(JoinType::Inner).await;
+        assert_eq!(
+            sql,
+            "SELECT items.task, users.name FROM items INNER JOIN users ON users.id = items.owner_id"
+        );
+        assert_eq!(rows, 2);
+    }
+
+    #[tokio::test]
+    async fn test_left_join_executes() {
+        let (sql, rows) = joined_rows(JoinType::Left).await;
+        assert!(sql.contains(" LEFT JOIN users ON "));
+        assert_eq!(rows, 3);
+    }
+
+    #[tokio::test]
+    async fn test_right_join_executes() {
+        let (sql, rows) = joined_rows(JoinType::Right).await;
+        assert!(sql.contains(" RIGHT JOIN users ON "));
+        assert_eq!(rows, 3);
+    }
+
+    #[tokio::test]
+    async fn test_full_join_executes() {
+        let (sql, rows) = joined_rows(JoinType::FullOuter).await;
+        assert!(sql.contains(" FULL OUTER JOIN users ON "));
+        assert_eq!(rows, 4);
+    }
+}
+
+#[cfg(test)]
+mod values_tests {
+    use crate::{Database, JoinType, QueryBuilder, Value};
+    use std::collections::HashMap;
+
+    fn priorities() -> QueryBuilder {
+        QueryBuilder::from_values(
+            vec![
+                vec![Value::from("dairy"), Value::Integer(1)],
+                vec![Value::from("bakery"), Value::Integer(2)],
+            ],
+            &["category", "priority"],
+            "p",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_values_sql_and_param_order() {
+        let (sql, params) = priorities().build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM (SELECT column1 AS category, column2 AS priority FROM (VALUES (?, ?), (?, ?))) AS p"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("dairy".to_string()),
+                libsql::Value::Integer(1),
+                libsql::Value::Text("bakery".to_string()),
+                libsql::Value::Integer(2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_values_rejects_ragged_rows() {
+        let result = QueryBuilder::from_values(
+            vec![
+                vec![Value::Integer(1)],
+                vec![Value::Integer(2), Value::Integer(3)],
+            ],
+            &["id"],
+            "v",
+        );
+        assert!(matches!(result, Err(crate::Error::Query(_))));
+    }
+
+    #[tokio::test]
+    async fn test_from_values_joins_against_table() {
+        let db = Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, task TEXT NOT NULL, category TEXT);
+                 INSERT INTO items (task, category) VALUES ('bread', 'bakery'), ('milk', 'dairy');",
+            )
+            .await
+            .unwrap();
+
+        let rows: Vec<HashMap<String, serde_json::Value>> = priorities()
+            .select(vec!["items.task", "p.priority"])
+            .join(JoinType::Inner, "items", "items.category = p.category")
+            .r#where(crate::FilterOperator::Single(crate::Filter::gt(
+                "p.priority",
+                0i64,
+            )))
+            .order_by(crate::Sort::new("p.priority", crate::SortOrder::Asc))
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let tasks: Vec<&str> = rows.iter().map(|r| r["task"].as_str().unwrap()).collect();
+        assert_eq!(tasks, vec!["milk", "bread"]);
+    }
+}
+
+#[cfg(test)]
+mod explain_tests {
+    use crate::{Database, Filter, FilterOperator, QueryBuilder};
+
+    async fn indexed_db() -> Database {
+        let db = Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id TEXT, task TEXT);
+                 CREATE INDEX idx_items_owner ON items(owner_id);
+                 INSERT INTO items (owner_id, task) VALUES ('1', 'milk');",
+            )
+            .await
+            .unwrap();
+        db
+    }
+
+    fn owner_query() -> QueryBuilder {
+        QueryBuilder::new("items").r#where(FilterOperator::Single(Filter::eq("owner_id", "1")))
+    }
+
+    #[tokio::test]
+    async fn test_explain_query_plan_reports_index() {
+        let db = indexed_db().await;
+        let plan = owner_query().explain_query_plan(&db).await.unwrap();
+        assert!(
+            plan.iter().any(|d| d.contains("idx_items_owner")),
+            "unexpected plan: {plan:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_explain_query_plan_reports_scan_without_index() {
+        let db = indexed_db().await;
+        let plan = QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(Filter::eq("task", "milk")))
+            .explain_query_plan(&db)
+            .await
+            .unwrap();
+        assert!(!plan.is_empty());
+        assert!(
+            plan.iter().any(|d| d.contains("SCAN")),
+            "unexpected plan: {plan:?}"
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_debug_explain_logs_plan_once_per_execution() {
+        use std::sync::Mutex;
+
+        static PLANS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        struct PlanLogger;
+
+        impl log::Log for PlanLogger {
+            fn enabled(&self, metadata: &log::Metadata) -> bool {
+                metadata.target() == "libsql_orm::explain"
+            }
+
+            fn log(&self, record: &log::Record) {
+                if self.enabled(record.metadata()) {
+                    PLANS.lock().unwrap().push(record.args().to_string());
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        log::set_logger(&PlanLogger).unwrap();
+        log::set_max_level(log::LevelFilter::Debug);
+
+        let db = indexed_db().await;
+        let query = owner_query().debug_explain_to_tracing();
+        let _: Vec<serde_json::Value> = query.execute(&db).await.unwrap();
+        assert_eq!(PLANS.lock().unwrap().len(), 1);
+        let _: Vec<serde_json::Value> = query.execute(&db).await.unwrap();
+        assert_eq!(PLANS.lock().unwrap().len(), 2);
+        let first = PLANS.lock().unwrap()[0].clone();
+        assert!(first.contains("idx_items_owner"), "{first}");
+
+        let _: Vec<serde_json::Value> = owner_query().execute(&db).await.unwrap();
+        assert_eq!(PLANS.lock().unwrap().len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod union_tests {
+    use crate::{Filter, FilterOperator, QueryBuilder};
+
+    fn per_category(category: &str) -> QueryBuilder {
+        QueryBuilder::new("items")
+            .select(vec!["task", "category"])
+            .r#where(FilterOperator::Single(Filter::eq("category", category)))
+    }
+
+    #[test]
+    fn test_failing_member_is_named_in_error() {
+        let broken = || per_category("bakery").indexed_by("no such index");
+        let err = QueryBuilder::union_all_many(vec![per_category("dairy"), broken()])
+            .unwrap()
+            .build()
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("UNION ALL member 2: Invalid index name"),
+            "{err}"
+        );
+
+        let err = QueryBuilder::union_all_many(vec![
+            per_category("dairy"),
+            broken().label("bakery_items"),
+        ])
+        .unwrap()
+        .build()
+        .unwrap_err();
+        assert!(err.to_string().contains("bakery_items: "), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_where_in_binds_subquery_params() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id TEXT, task TEXT);
+                 CREATE TABLE tags (item_id INTEGER, name TEXT);
+                 INSERT INTO items (owner_id, task) VALUES ('1', 'milk'), ('1', 'tea'), ('2', 'eggs');
+                 INSERT INTO tags VALUES (1, 'dairy'), (2, 'drinks'), (3, 'dairy');",
+            )
+            .await
+            .unwrap();
+        let dairy = QueryBuilder::new("tags")
+            .select(vec!["item_id"])
+            .r#where(FilterOperator::Single(Filter::eq("name", "dairy")));
+        let builder = QueryBuilder::new("items")
+            .r#where(FilterOperator::Single(Filter::eq("owner_id", "1")))
+            .where_in("id", dairy);
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items WHERE owner_id = ? AND id IN (SELECT item_id FROM tags WHERE name = ?)"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("1".to_string()),
+                libsql::Value::Text("dairy".to_string()),
+            ]
+        );
+        assert!(builder.validate_no_raw().is_ok());
+        builder.assert_all_params_bound();
+
+        let rows: Vec<serde_json::Value> = builder.execute(&db).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["task"], "milk");
+        assert_eq!(builder.execute_count(&db).await.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_failing_where_in_subquery_is_named_in_error() {
+        let subquery = QueryBuilder::new("tags")
+            .select(vec!["item_id"])
+            .indexed_by("no such index");
+        let builder = QueryBuilder::new("items").where_in("id", subquery);
+        for result in [builder.build(), builder.build_count()] {
+            let err = result.unwrap_err();
+            assert!(matches!(err, crate::Error::Query(_)));
+            assert!(
+                err.to_string().contains("subquery of where_in(id): "),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_union_all_many_concatenates_params_in_order() {
+        let (sql, params) = QueryBuilder::union_all_many(vec![
+            per_category("dairy"),
+            per_category("bakery"),
+            per_category("produce"),
+        ])
+        .unwrap()
+        .order_by(crate::Sort::new("task", crate::SortOrder::Asc))
+        .build()
+        .unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT task, category FROM items WHERE category = ? \
+             UNION ALL SELECT task, category FROM items WHERE category = ? \
+             UNION ALL SELECT task, category FROM items WHERE category = ? \
+             ORDER BY task ASC"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("dairy".to_string()),
+                libsql::Value::Text("bakery".to_string()),
+                libsql::Value::Text("produce".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_union_all_many_rejects_empty_and_mismatched_input() {
+        assert!(matches!(
+            QueryBuilder::union_all_many(vec![]),
+            Err(crate::Error::Query(_))
+        ));
+        assert!(matches!(
+            QueryBuilder::union_all_many(vec![
+                per_category("dairy"),
+                QueryBuilder::new("items").select(vec!["task"]),
+            ]),
+            Err(crate::Error::Query(_))
+        ));
+        assert!(matches!(
+            QueryBuilder::union_all_many(vec![per_category("dairy"), per_category("x").limit(1)]),
+            Err(crate::Error::Query(_))
+        ));
+    }
+
+    #[test]
+    fn test_union_binds_left_params_then_right() {
+        for (builder, operator) in [
+            (per_category("dairy").union(per_category("bakery")), "UNION"),
+            (
+                per_category("dairy").union_all(per_category("bakery")),
+                "UNION ALL",
+            ),
+        ] {
+            let (sql, params) = builder.limit(5).build().unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    "SELECT task, category FROM items WHERE category = ? \
+                     {operator} SELECT task, category FROM items WHERE category = ? LIMIT 5"
+                )
+            );
+            assert_eq!(
+                params,
+                vec![
+                    libsql::Value::Text("dairy".to_string()),
+                    libsql::Value::Text("bakery".to_string()),
+                ]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_union_drops_duplicates_union_all_keeps_them() {
+        let db = crate::Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, task TEXT, category TEXT);
+                 INSERT INTO items (task, category) VALUES ('milk', 'dairy'), ('bread', 'bakery');",
+            )
+            .await
+            .unwrap();
+        let dairy = || per_category("dairy");
+        let union: Vec<String> = dairy().union(dairy()).execute_column(&db, 0).await.unwrap();
+        assert_eq!(union, vec!["milk".to_string()]);
+        let union_all = dairy().union_all(dairy()).execute_count(&db).await.unwrap();
+        assert_eq!(union_all, 2);
+
+        let err = dairy()
+            .union(QueryBuilder::new("items").select(vec!["task"]))
+            .build()
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("UNION member selects 1 columns"),
+            "{err}"
+        );
+    }
+}
+
+#[cfg(test)]
+mod dml_tests {
+    use crate::{Database, QueryBuilder, Value};
+
+    async fn items_db() -> Database {
+        let db = Database::new_local(":memory:").await.unwrap();
+        db.inner
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, owner_id TEXT, task TEXT NOT NULL, completed BOOLEAN NOT NULL DEFAULT 0);",
+            )
+            .await
+            .unwrap();
+        db
+    }
+
+    fn placeholders(sql: &str) -> usize {
+        sql.matches('?').count()
+    }
+
+    #[test]
+    fn test_build_insert_single_row() {
+        let (sql, params) = QueryBuilder::new("items")
+            .insert(
+                vec!["owner_id", "task", "completed"],
+                vec![vec![
+                    Value::from("1"),
+                    Value::from("milk"),
+                    Value::from(false),
+                ]],
+            )
+            .build_insert()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "INSERT INTO items (owner_id, task, completed) VALUES (?, ?, ?)"
+        );
+        assert_eq!(placeholders(&sql), 3);
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("1".to_string()),
+                libsql::Value::Text("milk".to_string()),
+                libsql::Value::Integer(0),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_build_insert_multi_row_flattens_row_major() {
+        let db = items_db().await;
+        let rows: Vec<Vec<Value>> = ["milk", "eggs", "bread"]
+            .into_iter()
+            .map(|task| vec![Value::from("1"), Value::from(task)])
+            .collect();
+        let (sql, params) = QueryBuilder::new("items")
+            .insert(vec!["owner_id", "task"], rows)
+            .build_insert()
+            .unwrap();
+        assert_eq!(placeholders(&sql), 2 * 3);
+        assert_eq!(params.len(), 6);
+        assert_eq!(params[3], libsql::Value::Text("eggs".to_string()));
+
+        assert_eq!(db.execute(&sql, params).await.unwrap(), 3);
+        let tasks: Vec<String> = QueryBuilder::new("items")
+            .select(vec!["task"])
+            .order_by(crate::Sort::asc("id"))
+            .execute_column(&db, 0)
+            .await
+            .unwrap();
+        assert_eq!(tasks, vec!["milk", "eggs", "bread"]);
+    }
+
+    #[tokio::test]
+    async fn test_build_update_binds_set_values_before_where() {
+        let db = items_db().await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO items (owner_id, task) VALUES ('1', 'milk'), ('2', 'milk'), ('1', 'eggs');",
+            )
+            .await
+            .unwrap();
+        let (sql, params) = QueryBuilder::new("items")
+            .set("task", Value::from("oat milk"))
+            .set("completed", Value::from(true))
+            .r#where(crate::FilterOperator::Single(crate::Filter::eq(
+                "task", "milk",
+            )))
+            .r#where(crate::FilterOperator::Single(crate::Filter::eq(
+                "owner_id", "1",
+            )))
+            .build_update()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "UPDATE items SET task = ?, completed = ? WHERE task = ? AND owner_id = ?"
+        );
+        assert_eq!(
+            params,
+            vec![
+                libsql::Value::Text("oat milk".to_string()),
+                libsql::Value::Integer(1),
+                libsql::Value::Text("milk".to_string()),
+                libsql::Value::Text("1".to_string()),
+            ]
+        );
+
+        assert_eq!(db.execute(&sql, params).await.unwrap(), 1);
+        let completed: Vec<String> = QueryBuilder::new("items")
+            .select(vec!["task"])
+            .r#where(crate::FilterOperator::Single(crate::Filter::eq(
+                "completed",
+                true,
+            )))
+            .execute_column(&db, 0)
+            .await
+            .unwrap();
+        assert_eq!(completed, vec!["oat milk"]);
+    }
+
+    #[test]
+    fn test_build_update_needs_set_values() {
+        let err = QueryBuilder::new("items")
+            .r#where(crate::FilterOperator::Single(crate::Filter::eq("id", 1i64)))
+            .build_update()
+            .unwrap_err();
+        assert!(matches!(err, crate::Error::Query(_)));
+    }
+
+    #[test]
+    fn test_build_update_refuses_unconditional_update() {
+        let err = QueryBuilder::new("items")
+            .set("completed", Value::from(true))
+            .build_update()
+            .unwrap_err();
+        assert!(matches!(&err, crate::Error::Query(_)));
+        assert!(err
+            .to_string()
+            .contains("refusing to build unconditional UPDATE"));
+
+        let (sql, params) = QueryBuilder::new("items")
+            .set("completed", Value::from(true))
+            .allow_full_update()
+            .build_update()
+            .unwrap();
+        assert_eq!(sql, "UPDATE items SET completed = ?");
+        assert_eq!(params, vec![libsql::Value::Integer(1)]);
+    }
+
+    #[tokio::test]
+    async fn test_build_delete_removes_filtered_rows() {
+        let db = items_db().await;
+        db.inner
+            .execute_batch(
+                "INSERT INTO items (owner_id, task, completed) VALUES ('1', 'milk', 1), ('1', 'eggs', 0), ('2', 'rice', 1);",
+            )
+            .await
+            .unwrap();
+        let (sql, params) = QueryBuilder::new("items")
+            .r#where(crate::FilterOperator::Single(crate::Filter::eq(
+                "owner_id", "1",
+            )))
+            .r#where(crate::FilterOperator::Single(crate::Filter::eq(
+                "completed",
+                true,
+            )))
+            .build_delete()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "DELETE FROM items WHERE owner_id = ? AND completed = ?"
+        );
+        assert_eq!(db.execute(&sql, params).await.unwrap(), 1);
+        assert_eq!(
+            QueryBuilder::new("items").execute_count(&db).await.unwrap(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_build_delete_refuses_unconditional_delete() {
+        let err = QueryBuilder::new("items").build_delete().unwrap_err();
+        assert!(matches!(&err, crate::Error::Query(_)));
+        assert!(err
+            .to_string()
+            .contains("refusing to build unconditional DELETE"));
+
+        let (sql, params) = QueryBuilder::new("items")
+            .allow_full_delete()
+            .build_delete()
+            .unwrap();
+        assert_eq!(sql, "DELETE FROM items");
+        assert!(params.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_returning_appends_to_dml_only() {
+        let builder = QueryBuilder::new("items")
+            .insert(vec!["task"], vec![vec![Value::from("milk")]])
+            .returning(vec!["id"]);
+        let (sql, _) = builder.build_insert().unwrap();
+        assert_eq!(sql, "INSERT INTO items (task) VALUES (?) RETURNING id");
+        let (select_sql, _) = builder.build().unwrap();
+        assert_eq!(select_sql, "SELECT * FROM items");
+
+        let db = items_db().await;
+        let inserted: Vec<std::collections::HashMap<String, i64>> =
+            builder.insert_returning(&db).await.unwrap();
+        assert_eq!(inserted.len(), 1);
+        assert_eq!(inserted[0]["id"], 1);
+
+        let updated: Vec<std::collections::HashMap<String, serde_json::Value>> =
+            QueryBuilder::new("items")
+                .set("completed", Value::from(true))
+                .r#where(crate::FilterOperator::Single(crate::Filter::eq("id", 1i64)))
+                .returning(vec!["task", "completed"])
+                .update_returning(&db)
+                .await
+                .unwrap();
+        assert_eq!(updated[0]["task"], "milk");
+        assert_eq!(updated[0]["completed"], 1);
+
+        let (delete_sql, _) = QueryBuilder::new("items")
+            .allow_full_delete()
+            .returning(vec!["id", "task"])
+            .build_delete()
+            .unwrap();
+        assert_eq!(delete_sql, "DELETE FROM items RETURNING id, task");
+        assert!(matches!(
+            QueryBuilder::new("items")
+                .allow_full_delete()
+                .delete_returning::<serde_json::Value>(&db)
+                .await,
+            Err(crate::Error::Query(_))
+        ));
+
+        // A builder without rows or values never turns into a DELETE
+        let read_only = QueryBuilder::new("items")
+            .allow_full_delete()
+            .returning(vec!["id"]);
+        assert!(read_only
+            .insert_returning::<serde_json::Value>(&db)
+            .await
+            .is_err());
+        assert!(read_only
+            .update_returning::<serde_json::Value>(&db)
+            .await
+            .is_err());
+        assert_eq!(
+            QueryBuilder::new("items").execute_count(&db).await.unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_build_insert_rejects_mismatched_or_empty_rows() {
+        let mismatched = QueryBuilder::new("items")
+            .insert(
+                vec!["owner_id", "task"],
+                vec![
+                    vec![Value::from("1"), Value::from("milk")],
+                    vec![Value::from("eggs")],
+                ],
+            )
+            .build_insert();
+        assert!(matches!(mismatched, Err(crate::Error::Query(_))));
+        let empty = QueryBuilder::new("items")
+            .insert(vec!["task"], vec![])
+            .build_insert();
+        assert!(matches!(empty, Err(crate::Error::Query(_))));
+    }
+}
+
+#[cfg(test)]
+mod cipher_tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::{Database, EncryptedColumns, FieldCipher, Model, QueryBuilder, Value};
+
+    /// XOR with a repeating key, enough to tell ciphertext from plaintext
+    struct XorCipher(Vec<u8>);
+
+    impl FieldCipher for XorCipher {
+        fn encrypt(&self, plaintext: &[u8]) -> crate::Result<Vec<u8>> {
+            Ok(plaintext
+                .iter()
+                .zip(self.0.iter().cycle())
+                .map(|(byte, key)| byte ^ key)
+                .collect())
+        }
+
+        fn decrypt(&self, ciphertext: &[u8]) -> crate::Result<Vec<u8>> {
+            self.encrypt(ciphertext)
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        id: Option<i64>,
+        title: String,
+        notes: Option<String>,
+    }
+
+    impl Model for Note {
+        fn table_name() -> &'static str {
+            "notes"
+        }
+
+        fn get_primary_key(&self) -> Option<i64> {
+            self.id
+        }
+
+        fn set_primary_key(&mut self, id: i64) {
+            self.id = Some(id);
+        }
+
+        fn columns() -> Vec<&'static str> {
+            vec!["id", "title", "notes"]
+        }
+
+        fn migration_sql() -> String {
+            "CREATE TABLE notes (id INTEGER PRIMARY KEY, title TEXT NOT NULL, notes BLOB)"
+                .to_string()
+        }
+
+        fn to_map(&self) -> crate::Result<HashMap<String, Value>> {
+            let mut map = HashMap::new();
+            if let Some(id) = self.id {
+                map.insert("id".to_string(), Value::Integer(id));
+            }
+            map.insert("title".to_string(), Value::Text(self.title.clone()));
+            map.insert(
+                "notes".to_string(),
+                self.notes.clone().map_or(Value::Null, Value::Text),
+            );
+            Ok(map)
+        }
+
+        fn from_map(map: HashMap<String, Value>) -> crate::Result<Self> {
+            let text = |column: &str| match map.get(column) {
+                Some(Value::Text(text)) => Some(text.clone()),
+                _ => None,
+            };
+            Ok(Note {
+                id: match map.get("id") {
+                    Some(Value::Integer(id)) => Some(*id),
+                    _ => None,
+                },
+                title: text("title").unwrap_or_default(),
+                notes: text("notes"),
+            })
+        }
+
+        fn encrypted_columns() -> EncryptedColumns {
+            ciphers()
+        }
+    }
+
+    async fn notes_db() -> Database {
+        let db = Database::new_local(":memory:").await.unwrap();
+        db.inner.execute(&Note::migration_sql(), ()).await.unwrap();
+        db
+    }
+
+    fn ciphers() -> EncryptedColumns {
+        EncryptedColumns::new().column("notes", Arc::new(XorCipher(b"secret key".to_vec())))
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_column_round_trips() {
+        let db = notes_db().await;
+        let note = Note {
+            id: None,
+            title: "pharmacy".to_string(),
+            notes: Some("ask about the prescription".to_string()),
+        };
+        let created = note.create_encrypted(&ciphers(), &db).await.unwrap();
+        Note {
+            id: None,
+            title: "empty".to_string(),
+            notes: None,
+        }
+        .create_encrypted(&ciphers(), &db)
+        .await
+        .unwrap();
+
+        let notes: Vec<Note> = QueryBuilder::new("notes")
+            .encrypted(ciphers())
+            .order_by(crate::Sort::new("id", crate::SortOrder::Asc))
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(notes[0], created);
+        assert_eq!(notes[1].notes, None);
+    }
+
+    async fn stored_notes(db: &Database) -> Vec<Vec<u8>> {
+        let mut rows = db
+            .query("SELECT notes FROM notes ORDER BY id", vec![])
+            .await
+            .unwrap();
+        let mut stored = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            stored.push(row.get::<Vec<u8>>(0).unwrap());
+        }
+        stored
+    }
+
+    #[tokio::test]
+    async fn test_model_writes_encrypt_and_reads_decrypt() {
+        let db = notes_db().await;
+        let mut note = Note {
+            id: None,
+            title: "pharmacy".to_string(),
+            notes: Some("ask about the prescription".to_string()),
+        }
+        .create(&db)
+        .await
+        .unwrap();
+        note.notes = Some("pick up the prescription".to_string());
+        note.update(&db).await.unwrap();
+
+        let found = Note::find_by_id(note.id.unwrap(), &db).await.unwrap();
+        assert_eq!(found.as_ref(), Some(&note));
+        let found = Note::find_where(
+            crate::FilterOperator::Single(crate::Filter::eq("title", "pharmacy")),
+            &db,
+        )
+        .await
+        .unwrap();
+        assert_eq!(found, vec![note.clone()]);
+
+        let bulk = Note::bulk_create(
+            &[Note {
+                id: None,
+                title: "bakery".to_string(),
+                notes: Some("order the cake".to_string()),
+            }],
+            &db,
+        )
+        .await
+        .unwrap();
+        let upserted = Note {
+            notes: Some("order the big cake".to_string()),
+            ..bulk[0].clone()
+        }
+        .upsert(&["title"], &db)
+        .await
+        .unwrap();
+        assert_eq!(Note::find_all(&db).await.unwrap(), vec![note, upserted]);
+
+        for stored in stored_notes(&db).await {
+            let stored = String::from_utf8_lossy(&stored).into_owned();
+            assert!(!stored.contains("prescription") && !stored.contains("cake"));
+        }
+
+        let upsert = Note {
+            id: None,
+            title: "pharmacy".to_string(),
+            notes: Some("x".to_string()),
+        }
+        .upsert(&["notes"], &db)
+        .await;
+        assert!(matches!(upsert, Err(crate::Error::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_column_is_not_stored_as_plaintext() {
+        let db = notes_db().await;
+        let plaintext = "ask about the prescription";
+        Note {
+            id: None,
+            title: "pharmacy".to_string(),
+            notes: Some(plaintext.to_string()),
+        }
+        .create_encrypted(&ciphers(), &db)
+        .await
+        .unwrap();
+
+        let mut rows = db
+            .query("SELECT title, notes FROM notes", vec![])
+            .await
+            .unwrap();
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get::<String>(0).unwrap(), "pharmacy");
+        let stored = row.get::<Vec<u8>>(1).unwrap();
+        assert_ne!(stored, plaintext.as_bytes());
+        assert!(!String::from_utf8_lossy(&stored).contains("prescription"));
+
+        // A row written without encryption is refused rather than passed on
+        db.execute(
+            "INSERT INTO notes (title, notes) VALUES ('legacy', 'plain')",
+            vec![],
+        )
+        .await
+        .unwrap();
+        let result: crate::Result<Vec<Note>> = QueryBuilder::new("notes")
+            .encrypted(ciphers())
+            .execute(&db)
+            .await;
+        assert!(matches!(result, Err(crate::Error::Validation(_))));
+    }
+}
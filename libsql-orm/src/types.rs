@@ -170,6 +170,130 @@ impl From<serde_json::Value> for Value {
     }
 }
 
+impl From<libsql::Value> for Value {
+    fn from(v: libsql::Value) -> Self {
+        match v {
+            libsql::Value::Null => Value::Null,
+            libsql::Value::Integer(i) => Value::Integer(i),
+            libsql::Value::Real(f) => Value::Real(f),
+            libsql::Value::Text(s) => Value::Text(s),
+            libsql::Value::Blob(b) => Value::Blob(b),
+        }
+    }
+}
+
+/// Conversion from a raw libsql value into a Rust type
+///
+/// Used by scalar helpers such as [`crate::Database::query_scalar`] to turn the
+/// first column of a result row into a plain Rust value.
+///
+/// # Examples
+///
+/// ```rust
+/// use libsql_orm::FromSql;
+///
+/// let count = i64::from_sql(libsql::Value::Integer(3)).unwrap();
+/// assert_eq!(count, 3);
+/// ```
+pub trait FromSql: Sized {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self>;
+}
+
+fn unexpected_value<T>(expected: &str, value: &libsql::Value) -> crate::Result<T> {
+    Err(crate::Error::Serialization(format!(
+        "expected {expected}, got {value:?}"
+    )))
+}
+
+impl FromSql for i64 {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        match value {
+            libsql::Value::Integer(i) => Ok(i),
+            other => unexpected_value("integer", &other),
+        }
+    }
+}
+
+/// Narrow an integer column, failing instead of wrapping when it doesn't fit
+fn narrow_integer<T: TryFrom<i64>>(value: libsql::Value, target: &str) -> crate::Result<T> {
+    let i = i64::from_sql(value)?;
+    T::try_from(i)
+        .map_err(|_| crate::Error::Serialization(format!("integer {i} doesn't fit into {target}")))
+}
+
+impl FromSql for i32 {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        narrow_integer(value, "i32")
+    }
+}
+
+impl FromSql for u64 {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        narrow_integer(value, "u64")
+    }
+}
+
+impl FromSql for u32 {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        narrow_integer(value, "u32")
+    }
+}
+
+impl FromSql for f64 {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        match value {
+            libsql::Value::Real(f) => Ok(f),
+            libsql::Value::Integer(i) => Ok(i as f64),
+            other => unexpected_value("real", &other),
+        }
+    }
+}
+
+impl FromSql for bool {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        i64::from_sql(value).map(|i| i != 0)
+    }
+}
+
+impl FromSql for String {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        match value {
+            libsql::Value::Text(s) => Ok(s),
+            other => unexpected_value("text", &other),
+        }
+    }
+}
+
+impl FromSql for Vec<u8> {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        match value {
+            libsql::Value::Blob(b) => Ok(b),
+            other => unexpected_value("blob", &other),
+        }
+    }
+}
+
+impl FromSql for Value {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        Ok(match value {
+            libsql::Value::Null => Value::Null,
+            libsql::Value::Integer(i) => Value::Integer(i),
+            libsql::Value::Real(f) => Value::Real(f),
+            libsql::Value::Text(s) => Value::Text(s),
+            libsql::Value::Blob(b) => Value::Blob(b),
+        })
+    }
+}
+
+impl<T: FromSql> FromSql for Option<T> {
+    fn from_sql(value: libsql::Value) -> crate::Result<Self> {
+        match value {
+            libsql::Value::Null => Ok(None),
+            other => T::from_sql(other).map(Some),
+        }
+    }
+}
+
 /// Sort order for queries
 ///
 /// Specifies whether query results should be sorted in ascending or descending order.
@@ -198,23 +322,91 @@ impl std::fmt::Display for SortOrder {
     }
 }
 
+/// Ranking window functions for [`crate::QueryBuilder::select_window`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowFunc {
+    /// Position within the partition, 1, 2, 3, ...
+    RowNumber,
+    /// Position with gaps after ties, 1, 1, 3, ...
+    Rank,
+    /// Position without gaps after ties, 1, 1, 2, ...
+    DenseRank,
+}
+
+impl std::fmt::Display for WindowFunc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WindowFunc::RowNumber => write!(f, "ROW_NUMBER()"),
+            WindowFunc::Rank => write!(f, "RANK()"),
+            WindowFunc::DenseRank => write!(f, "DENSE_RANK()"),
+        }
+    }
+}
+
+/// Where `NULL`s go in a sort, SQLite puts them first ascending by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NullsOrder {
+    First,
+    Last,
+}
+
+impl std::fmt::Display for NullsOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NullsOrder::First => write!(f, "NULLS FIRST"),
+            NullsOrder::Last => write!(f, "NULLS LAST"),
+        }
+    }
+}
+
+/// How consecutive top-level WHERE clauses are combined
+///
+/// Nested [`crate::FilterOperator::And`] and [`crate::FilterOperator::Or`]
+/// groups keep their own operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Combinator {
+    #[default]
+    And,
+    Or,
+}
+
+impl std::fmt::Display for Combinator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Combinator::And => write!(f, "AND"),
+            Combinator::Or => write!(f, "OR"),
+        }
+    }
+}
+
 /// Aggregate functions
 ///
 /// SQL aggregate functions for performing calculations on sets of values.
 ///
 /// # Examples
 ///
-/// ```rust
+/// ```rust,no_run
 /// use libsql_orm::{Model, Aggregate};
+/// # use libsql_orm::Database;
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Model, Clone, Serialize, Deserialize)]
+/// # struct User {
+/// #     id: Option<i64>,
+/// #     age: i64,
+/// #     salary: f64,
+/// # }
+/// # async fn example(db: &Database) -> libsql_orm::Result<()> {
 ///
 /// // Count all users
-/// let count = User::aggregate(Aggregate::Count, "*", None, &db).await?;
+/// let count = User::aggregate(Aggregate::Count, "*", None, db).await?;
 ///
 /// // Average age
-/// let avg_age = User::aggregate(Aggregate::Avg, "age", None, &db).await?;
+/// let avg_age = User::aggregate(Aggregate::Avg, "age", None, db).await?;
 ///
 /// // Maximum salary
-/// let max_salary = User::aggregate(Aggregate::Max, "salary", None, &db).await?;
+/// let max_salary = User::aggregate(Aggregate::Max, "salary", None, db).await?;
+/// # Ok(())
+/// # }
 /// ```
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Aggregate {
@@ -249,14 +441,65 @@ impl std::fmt::Display for Aggregate {
 /// let inner = JoinType::Inner; // INNER JOIN
 /// let left = JoinType::Left;   // LEFT JOIN
 /// let right = JoinType::Right; // RIGHT JOIN
-/// let full = JoinType::Full;   // FULL JOIN
+/// let full = JoinType::FullOuter; // FULL OUTER JOIN
+/// let cross = JoinType::Cross; // CROSS JOIN
 /// ```
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy)]
 pub enum JoinType {
     Inner,
     Left,
+    /// Needs SQLite 3.39 or later, older versions reject the query
     Right,
+    /// Needs SQLite 3.39 or later, older versions reject the query
+    ///
+    /// Rows without a match on either side come back with `NULL`s for the
+    /// other table's columns.
+    FullOuter,
+    /// Former name of [`JoinType::FullOuter`], renders the same join
+    #[deprecated(note = "use JoinType::FullOuter")]
     Full,
+    /// Every row paired with every row of the other table, without a condition
+    ///
+    /// See [`crate::QueryBuilder::cross_join`].
+    Cross,
+}
+
+// The serde impls are written out because the derived ones use the deprecated
+// variant and warn
+impl Serialize for JoinType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[allow(deprecated)]
+        let (index, name) = match self {
+            JoinType::Inner => (0, "Inner"),
+            JoinType::Left => (1, "Left"),
+            JoinType::Right => (2, "Right"),
+            JoinType::FullOuter => (3, "FullOuter"),
+            JoinType::Full => (4, "Full"),
+            JoinType::Cross => (5, "Cross"),
+        };
+        serializer.serialize_unit_variant("JoinType", index, name)
+    }
+}
+
+impl<'de> Deserialize<'de> for JoinType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        const VARIANTS: &[&str] = &["Inner", "Left", "Right", "FullOuter", "Full", "Cross"];
+        let name = String::deserialize(deserializer)?;
+        match name.as_str() {
+            "Inner" => Ok(JoinType::Inner),
+            "Left" => Ok(JoinType::Left),
+            "Right" => Ok(JoinType::Right),
+            "FullOuter" | "Full" => Ok(JoinType::FullOuter),
+            "Cross" => Ok(JoinType::Cross),
+            _ => Err(serde::de::Error::unknown_variant(&name, VARIANTS)),
+        }
+    }
 }
 
 impl std::fmt::Display for JoinType {
@@ -265,7 +508,9 @@ impl std::fmt::Display for JoinType {
             JoinType::Inner => write!(f, "INNER JOIN"),
             JoinType::Left => write!(f, "LEFT JOIN"),
             JoinType::Right => write!(f, "RIGHT JOIN"),
-            JoinType::Full => write!(f, "FULL JOIN"),
+            #[allow(deprecated)]
+            JoinType::FullOuter | JoinType::Full => write!(f, "FULL OUTER JOIN"),
+            JoinType::Cross => write!(f, "CROSS JOIN"),
         }
     }
 }
@@ -277,16 +522,21 @@ impl std::fmt::Display for JoinType {
 /// # Examples
 ///
 /// ```rust
-/// use libsql_orm::{Operator, FilterOperator, Value};
+/// use libsql_orm::{Filter, FilterOperator, Operator, Value};
 ///
 /// // Equal comparison
-/// let filter = FilterOperator::Eq("status".to_string(), Value::Text("active".to_string()));
+/// let filter = FilterOperator::Single(Filter::new_simple("status", Operator::Eq, "active"));
 ///
 /// // Greater than
-/// let filter = FilterOperator::Gt("age".to_string(), Value::Integer(18));
+/// let filter = FilterOperator::Single(Filter::new_simple("age", Operator::Gt, 18i64));
 ///
 /// // LIKE pattern matching
-/// let filter = FilterOperator::Like("name".to_string(), Value::Text("%john%".to_string()));
+/// let filter = FilterOperator::Single(Filter::new_simple(
+///     "name",
+///     Operator::Like,
+///     Value::Text("%john%".to_string()),
+/// ));
+/// assert_eq!(Operator::Like.to_string(), "LIKE");
 /// ```
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Operator {
@@ -374,3 +624,42 @@ where
         _ => Err(Error::custom("Expected boolean, integer, or string")),
     }
 }
+
+/// Custom deserializer for `DateTime<Utc>` fields that also reads SQLite timestamps
+///
+/// `DEFAULT CURRENT_TIMESTAMP` and `datetime('now')` store UTC as
+/// `YYYY-MM-DD HH:MM:SS`, which the RFC 3339 deserializer of chrono rejects.
+/// This one accepts both, so rows relying on the column default read back.
+///
+/// # Usage
+///
+/// ```rust
+/// use chrono::{DateTime, Utc};
+/// use libsql_orm::deserialize_datetime;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Event {
+///     #[serde(deserialize_with = "deserialize_datetime")]
+///     pub created_at: DateTime<Utc>,
+/// }
+///
+/// let event: Event = serde_json::from_str(r#"{"created_at": "2024-03-01 08:30:00"}"#).unwrap();
+/// assert_eq!(event.created_at.to_rfc3339(), "2024-03-01T08:30:00+00:00");
+/// ```
+pub fn deserialize_datetime<'de, D>(
+    deserializer: D,
+) -> Result<chrono::DateTime<chrono::Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let text = String::deserialize(deserializer)?;
+    if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(&text) {
+        return Ok(datetime.with_timezone(&chrono::Utc));
+    }
+    chrono::NaiveDateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f")
+        .map(|naive| naive.and_utc())
+        .map_err(|_| Error::custom(format!("Invalid timestamp: {text}")))
+}
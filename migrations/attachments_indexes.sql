@@ -0,0 +1,4 @@
+-- Create indexes for attachments table
+
+-- Composite index for owner + item (for listing and counting an item's attachments)
+CREATE INDEX IF NOT EXISTS idx_attachments_owner_item ON attachments(owner_id, item_id);
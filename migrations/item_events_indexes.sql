@@ -0,0 +1,4 @@
+-- Create indexes for item_events table
+
+-- Composite index for owner + id (for keyset pagination over a user's history)
+CREATE INDEX IF NOT EXISTS idx_item_events_owner_id_id ON item_events(owner_id, id);
@@ -5,5 +5,18 @@ CREATE TABLE IF NOT EXISTS items (
     task TEXT NOT NULL,
     completed INTEGER NOT NULL DEFAULT FALSE,
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-    updated_at DATETIME NOT NULL
+    updated_at DATETIME NOT NULL,
+    due_date DATETIME,
+    claimed_by TEXT,
+    normalized_task TEXT,
+    category TEXT,
+    confidence REAL,
+    needs_review INTEGER NOT NULL DEFAULT 0,
+    completed_at DATETIME,
+    deleted_at DATETIME,
+    quantity REAL,
+    unit TEXT,
+    position INTEGER,
+    price REAL,
+    currency TEXT
 );
@@ -12,8 +12,11 @@ CREATE INDEX IF NOT EXISTS idx_items_updated_at ON items(updated_at);
 -- Index on completed status for filtering by completion
 CREATE INDEX IF NOT EXISTS idx_items_completed ON items(completed);
 
--- Composite index for owner + completion status (common query pattern)
-CREATE INDEX IF NOT EXISTS idx_items_owner_completed ON items(owner_id, completed);
+-- Composite indexes for owner + completion status and owner + due date are
+-- created by database::items::ensure_indexes
+
+-- Partial index for the open, non-deleted items of a user by creation time (database::items::active_items)
+CREATE INDEX IF NOT EXISTS idx_items_owner_active ON items(owner_id, created_at) WHERE completed = 0 AND deleted_at IS NULL;
 
 -- Composite index for owner + creation time (for user's items ordered by date)
 CREATE INDEX IF NOT EXISTS idx_items_owner_created ON items(owner_id, created_at);
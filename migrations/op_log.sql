@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS op_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    command_id TEXT NOT NULL,
+    owner_id TEXT NOT NULL,
+    clock INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    item_id INTEGER,
+    payload TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_op_log_owner_clock ON op_log(owner_id, clock);
+-- Command ids are chosen by clients, so they are only unique per owner
+CREATE UNIQUE INDEX IF NOT EXISTS idx_op_log_owner_command ON op_log(owner_id, command_id);
@@ -0,0 +1,13 @@
+-- A shopping trip, freezing which items the shopper sees
+CREATE TABLE IF NOT EXISTS shopping_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    owner_id TEXT NOT NULL,
+    started_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Items on the list when the session started
+CREATE TABLE IF NOT EXISTS shopping_session_items (
+    session_id INTEGER NOT NULL,
+    item_id INTEGER NOT NULL,
+    PRIMARY KEY (session_id, item_id)
+);
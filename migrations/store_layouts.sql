@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS store_layouts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    owner_id TEXT NOT NULL,
+    store_id TEXT NOT NULL,
+    category TEXT NOT NULL,
+    position INTEGER NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Create indexes for store_layouts table
+
+-- Composite index for owner + store (for loading one store's aisle order)
+CREATE INDEX IF NOT EXISTS idx_store_layouts_owner_store ON store_layouts(owner_id, store_id);
@@ -0,0 +1,4 @@
+-- Create indexes for tags tables
+
+-- Index on tag_id for finding the items of a tag (item_id is covered by the primary key)
+CREATE INDEX IF NOT EXISTS idx_item_tags_tag_id ON item_tags(tag_id);
@@ -0,0 +1,4 @@
+-- Create indexes for webhooks table
+
+-- Index on owner_id for finding the webhooks to notify on an item change
+CREATE INDEX IF NOT EXISTS idx_webhooks_owner_id ON webhooks(owner_id);
@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+use crate::database::{self, DBClient, items::Item};
+
+/// Header of the group holding items without a category
+const UNCATEGORIZED: &str = "Other";
+
+/// Plain-text checklist of all items of `owner_id`, ready to print
+pub async fn export_checklist(client: &DBClient, owner_id: String) -> Result<String, String> {
+    let items = database::items::get_items(client, owner_id).await?;
+    Ok(items_to_checklist(&items))
+}
+
+/// Renders items grouped by category, categories sorted by name and
+/// uncategorized items last, keeping the item order within a group
+pub fn items_to_checklist(items: &[Item]) -> String {
+    let mut groups: BTreeMap<&str, Vec<&Item>> = BTreeMap::new();
+    let mut uncategorized = Vec::new();
+    for item in items {
+        match item.category.as_deref().map(str::trim) {
+            Some(category) if !category.is_empty() => {
+                groups.entry(category).or_default().push(item)
+            }
+            _ => uncategorized.push(item),
+        }
+    }
+
+    let mut sections: Vec<(&str, Vec<&Item>)> = groups.into_iter().collect();
+    if !uncategorized.is_empty() {
+        sections.push((UNCATEGORIZED, uncategorized));
+    }
+
+    sections
+        .into_iter()
+        .map(|(header, items)| {
+            let mut section = format!("{header}\n");
+            for item in items {
+                section.push_str(&checklist_line(item));
+                section.push('\n');
+            }
+            section
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn checklist_line(item: &Item) -> String {
+    if item.completed() {
+        return format!("[x] {}", item.task);
+    }
+    match item.quantity_label() {
+        Some(quantity) => format!("[ ] {} ({quantity})", item.task),
+        None => format!("[ ] {}", item.task),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(task: &str, category: Option<&str>, quantity: Option<f64>, unit: Option<&str>) -> Item {
+        let mut item = Item::new("1".to_string(), task.to_string());
+        item.category = category.map(str::to_string);
+        item.quantity = quantity;
+        item.unit = unit.map(str::to_string);
+        item
+    }
+
+    #[test]
+    fn test_checklist_groups_mixed_items_by_category() {
+        let mut butter = item("butter", Some("Dairy"), Some(2.0), None);
+        butter.set_completed(true);
+        let items = vec![
+            item("tea", None, None, Some("box")),
+            item("milk", Some("Dairy"), Some(1.5), Some("l")),
+            item("apples", Some("Bakery & Fruit"), Some(6.0), Some("pcs")),
+            butter,
+            item("yoghurt", Some("Dairy"), Some(3.0), None),
+            item("bread", Some("Bakery & Fruit"), None, None),
+        ];
+
+        assert_eq!(
+            items_to_checklist(&items),
+            "Bakery & Fruit\n\
+             [ ] apples (6 pcs)\n\
+             [ ] bread\n\
+             \n\
+             Dairy\n\
+             [ ] milk (1.5 l)\n\
+             [x] butter\n\
+             [ ] yoghurt (3)\n\
+             \n\
+             Other\n\
+             [ ] tea\n"
+        );
+    }
+
+    #[test]
+    fn test_checklist_of_no_items_is_empty() {
+        assert_eq!(items_to_checklist(&[]), "");
+    }
+}
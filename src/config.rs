@@ -1,4 +1,11 @@
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::content_filter::{ContentFilter, FilterAction};
+use crate::llm::{LlmSettings, ModelPolicy};
+use crate::llm_provider::{LlmProvider, NestProvider, OpenAiProvider};
+use crate::normalize::TextNormalization;
 
 #[derive(Clone)]
 pub struct Server {
@@ -6,9 +13,13 @@ pub struct Server {
     host: String,
     db_url: String,
     token: Option<String>,
-    nest_api: String,
-    nest_api_key: String,
+    llm_provider: Arc<dyn LlmProvider>,
     check_access_token: bool,
+    strip_emoji: bool,
+    review_threshold: f32,
+    content_filter: ContentFilter,
+    model_policy: Option<ModelPolicy>,
+    maintenance_interval: Option<Duration>,
 }
 
 impl Server {
@@ -27,12 +38,8 @@ impl Server {
         self.token.clone()
     }
 
-    pub fn nest_api(&self) -> String {
-        self.nest_api.clone()
-    }
-
-    pub fn nest_api_key(&self) -> String {
-        self.nest_api_key.clone()
+    pub fn llm_provider(&self) -> Arc<dyn LlmProvider> {
+        self.llm_provider.clone()
     }
 
     pub fn delay(&self) -> bool {
@@ -42,14 +49,77 @@ impl Server {
     pub fn check_access_token(&self) -> bool {
         self.check_access_token
     }
+
+    pub fn llm_settings(&self) -> LlmSettings {
+        LlmSettings {
+            review_threshold: self.review_threshold,
+            content_filter: self.content_filter.clone(),
+            model_policy: self.model_policy.clone(),
+            model_override: None,
+            number_locale: Default::default(),
+        }
+    }
+
+    /// How often the database maintenance runs, `None` when it's off
+    pub fn maintenance_interval(&self) -> Option<Duration> {
+        self.maintenance_interval
+    }
+
+    pub fn text_normalization(&self) -> TextNormalization {
+        TextNormalization {
+            strip_emoji: self.strip_emoji,
+        }
+    }
 }
 
 pub fn from_env() -> Server {
     let access_token = env::var("PASS_ACCESS_TOKEN").unwrap_or("false".to_string());
     let check_access_token = access_token == "true";
+    let strip_emoji = env::var("STRIP_EMOJI").unwrap_or("false".to_string()) == "true";
+    let review_threshold: f32 = env::var("REVIEW_THRESHOLD")
+        .map(|e| e.parse().expect("could not parse review threshold"))
+        .unwrap_or(0.6);
+    let content_filter_words: Vec<String> = env::var("CONTENT_FILTER_WORDS")
+        .map(|e| e.split(',').map(str::to_string).collect())
+        .unwrap_or_default();
+    let content_filter_action = match env::var("CONTENT_FILTER_ACTION").as_deref() {
+        Ok("reject") => FilterAction::Reject,
+        _ => FilterAction::Mask,
+    };
+    // Model selection is only enabled when both models are configured
+    let model_policy = match (env::var("LLM_CHEAP_MODEL"), env::var("LLM_STRONG_MODEL")) {
+        (Ok(cheap_model), Ok(strong_model)) => Some(ModelPolicy {
+            cheap_model,
+            strong_model,
+            long_input_chars: env::var("LLM_LONG_INPUT_CHARS")
+                .map(|e| e.parse().expect("could not parse long input chars"))
+                .unwrap_or(400),
+        }),
+        _ => None,
+    };
+    // Orphan cleanup and optimizing only run when an interval is configured
+    let maintenance_interval = env::var("MAINTENANCE_INTERVAL_HOURS")
+        .map(|e| {
+            let hours: u64 = e.parse().expect("could not parse maintenance interval");
+            Duration::from_secs(hours * 60 * 60)
+        })
+        .ok();
 
-    let nest_api: String = env::var("NEST_API").unwrap_or("http://0.0.0.0:9998".to_string());
-    let nest_api_key: String = env::var("NEST_API_KEY").expect("need NEST_API_KEY");
+    let llm_provider: Arc<dyn LlmProvider> = match env::var("LLM_PROVIDER").as_deref() {
+        Ok("openai") => {
+            let api: String =
+                env::var("OPENAI_API").unwrap_or("https://api.openai.com/v1".to_string());
+            let api_key: String = env::var("OPENAI_API_KEY").expect("need OPENAI_API_KEY");
+            let model: String = env::var("OPENAI_MODEL").unwrap_or("gpt-4o-mini".to_string());
+            Arc::new(OpenAiProvider::new(api, api_key, model))
+        }
+        _ => {
+            let nest_api: String =
+                env::var("NEST_API").unwrap_or("http://0.0.0.0:9998".to_string());
+            let nest_api_key: String = env::var("NEST_API_KEY").expect("need NEST_API_KEY");
+            Arc::new(NestProvider::new(nest_api, nest_api_key))
+        }
+    };
 
     let port: u16 = env::var("g_port")
         .map(|e| e.parse().expect("could not parse port"))
@@ -67,8 +137,12 @@ pub fn from_env() -> Server {
         host,
         db_url,
         token: db_token,
-        nest_api,
-        nest_api_key,
+        llm_provider,
         check_access_token,
+        strip_emoji,
+        review_threshold,
+        content_filter: ContentFilter::new(&content_filter_words, content_filter_action),
+        model_policy,
+        maintenance_interval,
     }
 }
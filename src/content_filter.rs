@@ -0,0 +1,99 @@
+use regex::Regex;
+
+/// What happens to text containing a blocked word
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum FilterAction {
+    #[default]
+    Mask,
+    Reject,
+}
+
+/// Word list filter for chat and item text, disabled unless words are configured
+#[derive(Debug, Clone, Default)]
+pub struct ContentFilter {
+    pattern: Option<Regex>,
+    action: FilterAction,
+}
+
+impl ContentFilter {
+    pub fn new(words: &[String], action: FilterAction) -> Self {
+        let words: Vec<String> = words
+            .iter()
+            .map(|w| w.trim())
+            .filter(|w| !w.is_empty())
+            .map(regex::escape)
+            .collect();
+        if words.is_empty() {
+            return ContentFilter::disabled();
+        }
+        let pattern = Regex::new(&format!(r"(?i)\b(?:{})\b", words.join("|")))
+            .expect("escaped word list is a valid pattern");
+        ContentFilter {
+            pattern: Some(pattern),
+            action,
+        }
+    }
+
+    pub fn disabled() -> Self {
+        ContentFilter::default()
+    }
+
+    /// Masks blocked words with `*`, or returns an error when the action is `Reject`
+    pub fn screen(&self, text: &str) -> Result<String, String> {
+        let Some(pattern) = &self.pattern else {
+            return Ok(text.to_string());
+        };
+        if !pattern.is_match(text) {
+            return Ok(text.to_string());
+        }
+        match self.action {
+            FilterAction::Mask => Ok(pattern
+                .replace_all(text, |caps: &regex::Captures| {
+                    "*".repeat(caps[0].chars().count())
+                })
+                .to_string()),
+            FilterAction::Reject => Err("Text contains blocked words".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words() -> Vec<String> {
+        vec!["darn".to_string(), "heck".to_string()]
+    }
+
+    #[test]
+    fn test_mask_replaces_whole_words_only() {
+        let filter = ContentFilter::new(&words(), FilterAction::Mask);
+        assert_eq!(
+            filter
+                .screen("Darn, the heckling chef forgot the heck sauce")
+                .unwrap(),
+            "****, the heckling chef forgot the **** sauce"
+        );
+    }
+
+    #[test]
+    fn test_reject_fails_on_flagged_text() {
+        let filter = ContentFilter::new(&words(), FilterAction::Reject);
+        assert!(filter.screen("what the heck").is_err());
+        assert_eq!(filter.screen("milk and eggs").unwrap(), "milk and eggs");
+    }
+
+    #[test]
+    fn test_disabled_filter_passes_through() {
+        assert_eq!(
+            ContentFilter::disabled().screen("what the heck").unwrap(),
+            "what the heck"
+        );
+        assert_eq!(
+            ContentFilter::new(&[" ".to_string()], FilterAction::Reject)
+                .screen("what the heck")
+                .unwrap(),
+            "what the heck"
+        );
+    }
+}
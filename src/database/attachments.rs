@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+use libsql_orm::{Filter, FilterOperator, JoinType, Model, QueryBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::items::Item;
+
+/// Maximum number of attachments a single item can hold
+pub const MAX_ATTACHMENTS_PER_ITEM: u64 = 5;
+
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("attachments")]
+pub struct Attachment {
+    pub id: std::option::Option<i64>,
+    pub item_id: i64,
+    pub owner_id: String,
+    pub url: String,
+    pub content_type: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Attachment {
+    pub fn new(item_id: i64, owner_id: String, url: String, content_type: String) -> Self {
+        Attachment {
+            id: None,
+            item_id,
+            owner_id,
+            url,
+            content_type,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.unwrap()
+    }
+}
+
+#[derive(Deserialize)]
+struct AttachmentCount {
+    item_id: i64,
+    attachment_count: u64,
+}
+
+fn owned_by(owner_id: &str, item_id: i64) -> FilterOperator {
+    FilterOperator::And(vec![
+        FilterOperator::Single(Filter::eq("owner_id".to_string(), owner_id.to_string())),
+        FilterOperator::Single(Filter::eq("item_id".to_string(), item_id)),
+    ])
+}
+
+pub async fn add_attachment(
+    client: &DBClient,
+    owner_id: String,
+    item_id: i64,
+    url: String,
+    content_type: String,
+) -> Result<Attachment, String> {
+    super::items::get_item(client, item_id, owner_id.clone()).await?;
+
+    let db = super::unlock_client(client).await;
+    // Count and insert in one savepoint so concurrent uploads can't both pass
+    // the limit check
+    let res = super::with_savepoint(&db, "add_attachment", async {
+        match Attachment::count_where(owned_by(&owner_id, item_id), &db).await {
+            Ok(count) if count >= MAX_ATTACHMENTS_PER_ITEM => {
+                return Err(format!(
+                    "An item can have at most {MAX_ATTACHMENTS_PER_ITEM} attachments"
+                ));
+            }
+            Ok(_) => {}
+            Err(err) => {
+                log::error!("could not count attachments for item {item_id}: {err}");
+                return Err("Database error".to_string());
+            }
+        }
+
+        let attachment = Attachment::new(item_id, owner_id.clone(), url, content_type);
+        Attachment::create(&attachment, &db).await.map_err(|err| {
+            log::error!("could not create attachment: {err}");
+            "Could not create attachment".to_string()
+        })
+    })
+    .await;
+    drop(db);
+
+    let created = res?;
+    log::info!("created attachment {} for item {item_id}", created.id());
+    Ok(created)
+}
+
+pub async fn list_attachments(
+    client: &DBClient,
+    owner_id: String,
+    item_id: i64,
+) -> Result<Vec<Attachment>, String> {
+    let db = super::unlock_client(client).await;
+    let attachments = Attachment::find_where(owned_by(&owner_id, item_id), &db).await;
+    drop(db);
+
+    attachments.map_err(|err| {
+        log::error!("could not list attachments for item {item_id}: {err}");
+        "Could not get attachments".to_string()
+    })
+}
+
+pub async fn remove_attachment(
+    client: &DBClient,
+    owner_id: String,
+    attachment_id: i64,
+) -> Result<(), String> {
+    let db = super::unlock_client(client).await;
+    let attachment = match Attachment::find_by_id(attachment_id, &db).await {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => {
+            drop(db);
+            return Err("Attachment not found".to_string());
+        }
+        Err(err) => {
+            drop(db);
+            log::error!("Error finding attachment {attachment_id}: {err:?}");
+            return Err("Database error".to_string());
+        }
+    };
+
+    if attachment.owner_id != owner_id {
+        drop(db);
+        log::error!("Unauthorized delete attempt for attachment {attachment_id}");
+        return Err("Unauthorized".to_string());
+    }
+
+    let res = attachment.delete(&db).await;
+    drop(db);
+
+    match res {
+        Ok(_) => {
+            log::info!("deleted attachment {attachment_id}");
+            Ok(())
+        }
+        Err(err) => {
+            log::error!("could not delete attachment {attachment_id}: {err:?}");
+            Err("Could not delete attachment".to_string())
+        }
+    }
+}
+
+/// Items of an owner together with how many attachments each one has
+pub async fn get_items_with_attachment_counts(
+    client: &DBClient,
+    owner_id: String,
+) -> Result<Vec<(Item, u64)>, String> {
+    let items = super::items::get_items(client, owner_id.clone()).await?;
+
+    let db = super::unlock_client(client).await;
+    let counts: Result<Vec<AttachmentCount>, _> = QueryBuilder::new("items")
+        .select(vec![
+            "items.id AS item_id",
+            "COUNT(attachments.id) AS attachment_count",
+        ])
+        .join(
+            JoinType::Left,
+            "attachments",
+            "attachments.item_id = items.id",
+        )
+        .r#where(FilterOperator::Single(Filter::eq(
+            "items.owner_id".to_string(),
+            owner_id,
+        )))
+        .group_by(vec!["items.id"])
+        .execute(&db)
+        .await;
+    drop(db);
+
+    let counts: HashMap<i64, u64> = match counts {
+        Ok(counts) => counts
+            .into_iter()
+            .map(|c| (c.item_id, c.attachment_count))
+            .collect(),
+        Err(err) => {
+            log::error!("could not count attachments: {err}");
+            return Err("Could not get attachment counts".to_string());
+        }
+    };
+
+    Ok(items
+        .into_iter()
+        .map(|item| {
+            let count = counts.get(&item.id()).copied().unwrap_or(0);
+            (item, count)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn item_for(client: &DBClient, owner_id: &str) -> Item {
+        super::super::items::create_item(
+            client,
+            Item::new(owner_id.to_string(), "oat milk".to_string()),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_list_attachments() {
+        let client = crate::database::test_client().await;
+        let item = item_for(&client, "1").await;
+
+        let attachment = add_attachment(
+            &client,
+            "1".to_string(),
+            item.id(),
+            "https://example.com/oat-milk.jpg".to_string(),
+            "image/jpeg".to_string(),
+        )
+        .await
+        .unwrap();
+
+        let listed = list_attachments(&client, "1".to_string(), item.id())
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id(), attachment.id());
+        assert_eq!(listed[0].content_type, "image/jpeg");
+
+        assert!(
+            list_attachments(&client, "2".to_string(), item.id())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            add_attachment(
+                &client,
+                "2".to_string(),
+                item.id(),
+                "https://example.com/other.jpg".to_string(),
+                "image/jpeg".to_string(),
+            )
+            .await
+            .is_err()
+        );
+
+        let counts = get_items_with_attachment_counts(&client, "1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[0].1, 1);
+
+        remove_attachment(&client, "1".to_string(), attachment.id())
+            .await
+            .unwrap();
+        assert!(
+            list_attachments(&client, "1".to_string(), item.id())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attachment_cap_per_item() {
+        let client = crate::database::test_client().await;
+        let item = item_for(&client, "1").await;
+
+        for i in 0..MAX_ATTACHMENTS_PER_ITEM {
+            add_attachment(
+                &client,
+                "1".to_string(),
+                item.id(),
+                format!("https://example.com/{i}.png"),
+                "image/png".to_string(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let over_cap = add_attachment(
+            &client,
+            "1".to_string(),
+            item.id(),
+            "https://example.com/extra.png".to_string(),
+            "image/png".to_string(),
+        )
+        .await;
+        assert!(over_cap.is_err());
+        assert_eq!(
+            list_attachments(&client, "1".to_string(), item.id())
+                .await
+                .unwrap()
+                .len() as u64,
+            MAX_ATTACHMENTS_PER_ITEM
+        );
+    }
+}
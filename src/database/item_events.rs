@@ -0,0 +1,337 @@
+use std::collections::VecDeque;
+
+use futures_util::{Stream, stream};
+use libsql_orm::{Database, Filter, FilterOperator, Model, QueryBuilder, Sort, SortOrder};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::items::Item;
+
+/// Number of events loaded per page while exporting
+const EXPORT_PAGE_SIZE: u32 = 200;
+
+/// What happened to an item
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ItemEventKind {
+    Created,
+    Updated,
+    Completed,
+    Reopened,
+    Deleted,
+}
+
+impl ItemEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ItemEventKind::Created => "created",
+            ItemEventKind::Updated => "updated",
+            ItemEventKind::Completed => "completed",
+            ItemEventKind::Reopened => "reopened",
+            ItemEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[table_name("item_events")]
+pub struct ItemEvent {
+    pub id: std::option::Option<i64>,
+    pub item_id: i64,
+    pub owner_id: String,
+    pub kind: String,
+    pub task: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ItemEvent {
+    fn new(item: &Item, kind: ItemEventKind) -> Self {
+        ItemEvent {
+            id: None,
+            item_id: item.id(),
+            owner_id: item.owner_id(),
+            kind: kind.as_str().to_string(),
+            task: item.task.clone(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.unwrap()
+    }
+}
+
+/// Appends an event to the history and the op log and notifies webhooks,
+/// failures are logged and do not fail the change
+pub(super) async fn record(db: &Database, item: &Item, kind: ItemEventKind) {
+    record_event(db, item, kind).await;
+    if let Err(err) = super::op_log::append_change(db, item, kind, None).await {
+        log::error!("{err}");
+    }
+    super::webhooks::dispatch(db, item, kind).await;
+}
+
+/// Like [`record`], logging the change under the command id a client gave it
+///
+/// Failing to write the op log entry is an error here, the caller has to roll
+/// the change back so that a resent command is applied again. Webhooks are
+/// left to the caller, who notifies them once the change is committed.
+pub(super) async fn record_command(
+    db: &Database,
+    item: &Item,
+    kind: ItemEventKind,
+    command_id: &str,
+) -> Result<(), String> {
+    record_event(db, item, kind).await;
+    super::op_log::append_change(db, item, kind, Some(command_id)).await?;
+    Ok(())
+}
+
+async fn record_event(db: &Database, item: &Item, kind: ItemEventKind) {
+    if let Err(err) = ItemEvent::new(item, kind).create(db).await {
+        log::error!(
+            "could not record {} event for item {}: {err}",
+            kind.as_str(),
+            item.id()
+        );
+    }
+}
+
+async fn events_after(
+    client: &DBClient,
+    owner_id: &str,
+    after_id: i64,
+) -> Result<Vec<ItemEvent>, String> {
+    let query = QueryBuilder::new("item_events")
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .r#where(FilterOperator::Single(Filter::gt(
+            "id".to_string(),
+            after_id,
+        )))
+        .order_by(Sort::new("id", SortOrder::Asc))
+        .limit(EXPORT_PAGE_SIZE);
+
+    let db = super::unlock_client(client).await;
+    let events = ItemEvent::query(query, &db).await;
+    drop(db);
+
+    events.map_err(|err| {
+        log::error!("could not load item events for {owner_id}: {err}");
+        "Could not load item events".to_string()
+    })
+}
+
+/// A task the owner added before, for completing what they are typing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Suggestion {
+    /// Spelling of the latest addition
+    pub task: String,
+    /// How often it was added
+    pub uses: i64,
+    pub last_used: chrono::DateTime<chrono::Utc>,
+}
+
+/// Past tasks starting with `prefix`, most often added first, then most
+/// recently added
+///
+/// Case is ignored for ASCII letters, like SQLite's `LOWER` does. Tasks
+/// differing only in case count as one. With `exclude_active`, tasks that are
+/// on the list right now are left out.
+pub async fn suggest_items(
+    client: &DBClient,
+    owner_id: String,
+    prefix: &str,
+    limit: u32,
+    exclude_active: bool,
+) -> Result<Vec<Suggestion>, String> {
+    let prefix = prefix.trim().to_ascii_lowercase();
+    if prefix.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let active: Vec<String> = if exclude_active {
+        super::items::get_items(client, owner_id.clone())
+            .await?
+            .into_iter()
+            .filter(|item| item.completed == 0 && item.deleted_at.is_none())
+            .map(|item| item.task.to_ascii_lowercase())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let query = QueryBuilder::new("item_events")
+        .select(vec![
+            "task",
+            "COUNT(*) AS uses",
+            "MAX(created_at) AS last_used",
+        ])
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.clone(),
+        )))
+        .r#where(FilterOperator::Single(Filter::eq(
+            "kind".to_string(),
+            ItemEventKind::Created.as_str(),
+        )))
+        .r#where(FilterOperator::Single(Filter::eq(
+            format!("LOWER(SUBSTR(task, 1, {}))", prefix.chars().count()),
+            prefix,
+        )))
+        .r#where(FilterOperator::Single(Filter::not_in_values(
+            "LOWER(task)".to_string(),
+            active,
+        )))
+        // The bare `task` comes from the row with the latest `created_at`
+        .group_by(vec!["LOWER(task)"])
+        .order_by(Sort::desc("uses"))
+        .order_by(Sort::desc("last_used"))
+        .limit(limit);
+
+    let db = super::unlock_client(client).await;
+    let suggestions = query.execute::<Suggestion>(&db).await;
+    drop(db);
+
+    suggestions.map_err(|err| {
+        log::error!("could not load suggestions for {owner_id}: {err}");
+        "Could not load suggestions".to_string()
+    })
+}
+
+struct ExportState {
+    client: DBClient,
+    owner_id: String,
+    after_id: i64,
+    buffer: VecDeque<ItemEvent>,
+    done: bool,
+}
+
+/// Streams the event history of an owner as NDJSON, one event per line
+///
+/// Events are loaded page by page (keyset pagination on `id`), so memory stays
+/// bounded however long the history is.
+pub fn export_events_ndjson(
+    client: DBClient,
+    owner_id: String,
+) -> impl Stream<Item = Result<String, String>> {
+    let state = ExportState {
+        client,
+        owner_id,
+        after_id: 0,
+        buffer: VecDeque::new(),
+        done: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        if state.buffer.is_empty() && !state.done {
+            match events_after(&state.client, &state.owner_id, state.after_id).await {
+                Ok(page) => {
+                    state.done = page.len() < EXPORT_PAGE_SIZE as usize;
+                    state.buffer.extend(page);
+                }
+                Err(err) => {
+                    state.done = true;
+                    return Some((Err(err), state));
+                }
+            }
+        }
+
+        let event = state.buffer.pop_front()?;
+        state.after_id = event.id();
+        let line = serde_json::to_string(&event)
+            .map(|json| format!("{json}\n"))
+            .map_err(|err| format!("Could not serialize item event: {err}"));
+        Some((line, state))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    #[tokio::test]
+    async fn test_ndjson_lines_parse_back_into_events() {
+        let client = crate::database::test_client().await;
+        let items = crate::database::items::create_items_returning(
+            &client,
+            (0..EXPORT_PAGE_SIZE + 5)
+                .map(|i| Item::new("1".to_string(), format!("item {i}")))
+                .collect(),
+        )
+        .await
+        .unwrap();
+        crate::database::items::toggle_item(&client, items[0].id(), "1".to_string())
+            .await
+            .unwrap();
+        crate::database::items::create_item(&client, Item::new("2".to_string(), "other".into()))
+            .await
+            .unwrap();
+
+        let lines: Vec<String> = export_events_ndjson(client.clone(), "1".to_string())
+            .map(|line| line.unwrap())
+            .collect()
+            .await;
+        assert!(
+            lines
+                .iter()
+                .all(|l| l.ends_with('\n') && !l.trim_end().contains('\n'))
+        );
+
+        let parsed: Vec<ItemEvent> = lines
+            .concat()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        let db = crate::database::unlock_client(&client).await;
+        let stored = ItemEvent::find_where(
+            FilterOperator::Single(Filter::eq("owner_id".to_string(), "1".to_string())),
+            &db,
+        )
+        .await
+        .unwrap();
+        drop(db);
+
+        assert_eq!(parsed.len(), EXPORT_PAGE_SIZE as usize + 6);
+        assert_eq!(parsed, stored);
+        assert_eq!(parsed.last().unwrap().kind, "completed");
+    }
+
+    #[tokio::test]
+    async fn test_suggestions_rank_frequent_items_first() {
+        let client = crate::database::test_client().await;
+        let add = |task: &str| Item::new("1".to_string(), task.to_string());
+        for task in ["Milk", "bread", "milk", "mint", "MILK", "Mineral water"] {
+            let item = crate::database::items::create_item(&client, add(task))
+                .await
+                .unwrap();
+            crate::database::items::toggle_item(&client, item.id(), "1".to_string())
+                .await
+                .unwrap();
+        }
+        crate::database::items::create_item(&client, add("mint"))
+            .await
+            .unwrap();
+        crate::database::items::create_item(&client, Item::new("2".into(), "milkshake".into()))
+            .await
+            .unwrap();
+
+        let suggestions = suggest_items(&client, "1".to_string(), "MI", 10, false)
+            .await
+            .unwrap();
+        let ranked: Vec<(&str, i64)> = suggestions
+            .iter()
+            .map(|s| (s.task.as_str(), s.uses))
+            .collect();
+        assert_eq!(ranked, vec![("MILK", 3), ("mint", 2), ("Mineral water", 1)]);
+
+        let suggestions = suggest_items(&client, "1".to_string(), "mi", 2, true)
+            .await
+            .unwrap();
+        let ranked: Vec<&str> = suggestions.iter().map(|s| s.task.as_str()).collect();
+        assert_eq!(ranked, vec!["MILK", "Mineral water"]);
+    }
+}
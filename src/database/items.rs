@@ -1,7 +1,10 @@
-use libsql_orm::{Filter, FilterOperator, Model};
+use libsql_orm::{Filter, FilterOperator, Model, Pagination, QueryBuilder, Sort, SortOrder};
 use serde::{Deserialize, Serialize};
 
 use crate::database::DBClient;
+use crate::database::item_events::{self, ItemEventKind};
+use crate::normalize::{TextNormalization, normalize_text};
+use crate::similarity;
 
 #[derive(Model, Debug, Clone, Serialize, Deserialize)]
 #[table_name("items")]
@@ -10,11 +13,63 @@ pub struct Item {
     pub owner_id: String,
     pub task: String,
     pub completed: u16,
+    /// Set by the column default when omitted, see `create_with_defaults`
+    #[serde(deserialize_with = "libsql_orm::deserialize_datetime")]
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub due_date: Option<chrono::DateTime<chrono::Utc>>,
+    pub claimed_by: Option<String>,
+    pub normalized_task: Option<String>,
+    pub category: Option<String>,
+    pub confidence: Option<f32>,
+    pub needs_review: u16,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    /// Manual order of the list, `None` for items that were never placed
+    pub position: Option<i64>,
+    /// Price of one unit of `quantity`, or of the item when it has none
+    pub price: Option<f64>,
+    /// ISO 4217 code of `price`, e.g. `EUR`
+    pub currency: Option<String>,
 }
 
+/// Open, non-deleted items, kept as literal SQL so it matches the partial
+/// index `idx_items_owner_active` (bound params can't prove the index applies)
+const ACTIVE_ITEMS_CONDITION: &str = "completed = 0 AND deleted_at IS NULL";
+
+/// Composite indexes backing the common item filters
+const ITEM_INDEXES: &[(&str, &str)] = &[
+    ("idx_items_owner_completed", "owner_id, completed"),
+    ("idx_items_owner_due_date", "owner_id, due_date"),
+];
+
 impl Item {
+    pub fn new(owner_id: String, task: String) -> Self {
+        Item {
+            id: None,
+            owner_id,
+            task,
+            completed: 0,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            due_date: None,
+            claimed_by: None,
+            normalized_task: None,
+            category: None,
+            confidence: None,
+            needs_review: 0,
+            completed_at: None,
+            deleted_at: None,
+            quantity: None,
+            unit: None,
+            position: None,
+            price: None,
+            currency: None,
+        }
+    }
+
     pub fn id(&self) -> i64 {
         self.id.unwrap()
     }
@@ -22,11 +77,26 @@ impl Item {
     pub fn update_task(&mut self, task: &str) {
         self.task = task.to_string()
     }
+
+    /// Fills `normalized_task` from `task`, the raw text is kept for display
+    pub fn normalize(&mut self, options: &TextNormalization) {
+        self.normalized_task = Some(normalize_text(&self.task, options));
+    }
     pub fn toggle(&mut self) {
-        if self.completed == 0 {
-            self.completed = 1
+        self.set_completed(!self.completed())
+    }
+
+    /// The only place `completed` changes, keeps `completed_at` in sync with it
+    pub fn set_completed(&mut self, completed: bool) {
+        if completed == self.completed() {
+            return;
+        }
+        if completed {
+            self.completed = 1;
+            self.completed_at = Some(chrono::Utc::now());
         } else {
-            self.completed = 0
+            self.completed = 0;
+            self.completed_at = None;
         }
     }
 
@@ -34,11 +104,220 @@ impl Item {
         self.completed == 1
     }
 
+    /// `"1.5 kg"`, `"2"` or `None` without a quantity
+    pub fn quantity_label(&self) -> Option<String> {
+        let quantity = self.quantity?;
+        let quantity = if quantity.fract() == 0.0 {
+            format!("{quantity:.0}")
+        } else {
+            quantity.to_string()
+        };
+        match self.unit.as_deref().map(str::trim) {
+            Some(unit) if !unit.is_empty() => Some(format!("{quantity} {unit}")),
+            _ => Some(quantity),
+        }
+    }
+
+    pub fn needs_review(&self) -> bool {
+        self.needs_review == 1
+    }
+
     pub fn owner_id(&self) -> String {
         self.owner_id.to_string()
     }
 }
 
+/// Columns the item list can be sorted by
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemSort {
+    Name,
+    #[default]
+    Created,
+    DueDate,
+}
+
+impl ItemSort {
+    fn column(&self) -> &'static str {
+        match self {
+            ItemSort::Name => "task",
+            ItemSort::Created => "created_at",
+            ItemSort::DueDate => "due_date",
+        }
+    }
+}
+
+/// Inputs of the item list: status filter, sort and page
+#[derive(Debug, Clone)]
+pub struct ItemListQuery {
+    pub completed: Option<bool>,
+    pub sort: ItemSort,
+    pub order: SortOrder,
+    pub pagination: Pagination,
+}
+
+fn item_list_builder(owner_id: &str, query: &ItemListQuery) -> QueryBuilder {
+    QueryBuilder::new("items")
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .filter_if_some(query.completed, |builder, completed| {
+            builder.r#where(FilterOperator::Single(Filter::eq(
+                "completed".to_string(),
+                completed,
+            )))
+        })
+        .order_by(Sort::new(query.sort.column(), query.order))
+        .paginate(&query.pagination)
+}
+
+fn active_items_builder(owner_id: &str) -> QueryBuilder {
+    QueryBuilder::new("items")
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .r#where(FilterOperator::Custom(ACTIVE_ITEMS_CONDITION.to_string()))
+        .order_by(Sort::asc("created_at"))
+}
+
+/// Items of `owner_id` that are neither completed nor deleted, oldest first
+pub async fn active_items(client: &DBClient, owner_id: &str) -> Result<Vec<Item>, String> {
+    let db = super::unlock_client(client).await;
+    let items = active_items_builder(owner_id).execute::<Item>(&db).await;
+    drop(db);
+
+    items.map_err(|err| {
+        log::error!("could not get active items: {err}");
+        "Could not get items".to_string()
+    })
+}
+
+/// Which ends of a due date range are part of it
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RangeBounds {
+    /// `start <= due_date <= end`
+    Inclusive,
+    /// `start < due_date < end`
+    Exclusive,
+    /// `start <= due_date < end`, e.g. a calendar day from midnight to midnight
+    #[default]
+    StartInclusive,
+    /// `start < due_date <= end`
+    EndInclusive,
+}
+
+impl RangeBounds {
+    fn start_inclusive(&self) -> bool {
+        matches!(self, RangeBounds::Inclusive | RangeBounds::StartInclusive)
+    }
+
+    fn end_inclusive(&self) -> bool {
+        matches!(self, RangeBounds::Inclusive | RangeBounds::EndInclusive)
+    }
+}
+
+/// `due_date` in a fixed-width format, so it compares as text in time order
+/// whatever fraction of a second was stored
+const DUE_DATE_MILLIS: &str = "strftime('%Y-%m-%dT%H:%M:%fZ', due_date)";
+
+/// `at` truncated to the second, without fraction or zone
+///
+/// Every stored `due_date` of that second sorts at or after it as text,
+/// whatever fraction follows, and every earlier one before it.
+fn second_prefix(at: chrono::DateTime<chrono::Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+fn due_between_builder(
+    owner_id: &str,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+    bounds: RangeBounds,
+) -> QueryBuilder {
+    let millis =
+        |at: chrono::DateTime<chrono::Utc>| at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    // The column itself is compared against whole seconds around the range,
+    // which the (owner_id, due_date) index can seek; the exact bounds then only
+    // filter the rows the index found
+    let after_end = end + chrono::Duration::seconds(1);
+    let start_filter = if bounds.start_inclusive() {
+        Filter::ge(DUE_DATE_MILLIS, millis(start))
+    } else {
+        Filter::gt(DUE_DATE_MILLIS, millis(start))
+    };
+    let end_filter = if bounds.end_inclusive() {
+        Filter::le(DUE_DATE_MILLIS, millis(end))
+    } else {
+        Filter::lt(DUE_DATE_MILLIS, millis(end))
+    };
+    QueryBuilder::new("items")
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .r#where(FilterOperator::Single(Filter::ge(
+            "due_date",
+            second_prefix(start),
+        )))
+        .r#where(FilterOperator::Single(Filter::lt(
+            "due_date",
+            second_prefix(after_end),
+        )))
+        .r#where(FilterOperator::Single(start_filter))
+        .r#where(FilterOperator::Single(end_filter))
+        .order_by(Sort::asc("due_date"))
+}
+
+/// Items of `owner_id` due between `start` and `end`, soonest first
+///
+/// Dates are compared to the millisecond; items without a due date are left out.
+pub async fn items_due_between(
+    client: &DBClient,
+    owner_id: &str,
+    start: chrono::DateTime<chrono::Utc>,
+    end: chrono::DateTime<chrono::Utc>,
+    bounds: RangeBounds,
+) -> Result<Vec<Item>, String> {
+    let db = super::unlock_client(client).await;
+    let items = due_between_builder(owner_id, start, end, bounds)
+        .execute::<Item>(&db)
+        .await;
+    drop(db);
+
+    items.map_err(|err| {
+        log::error!("could not get items due between {start} and {end}: {err}");
+        "Could not get items".to_string()
+    })
+}
+
+/// SQL and redacted params the item list would run, without executing it
+pub fn dry_run_item_list(
+    owner_id: &str,
+    query: &ItemListQuery,
+) -> Result<(String, Vec<String>), String> {
+    item_list_builder(owner_id, query)
+        .build_redacted()
+        .map_err(|err| {
+            log::error!("could not build item list query: {err}");
+            "Could not build item list query".to_string()
+        })
+}
+
+pub async fn ensure_indexes(client: &DBClient) -> Result<(), String> {
+    let db = super::unlock_client(client).await;
+    for (name, columns) in ITEM_INDEXES {
+        let sql = format!("CREATE INDEX IF NOT EXISTS {name} ON items({columns})");
+        if let Err(err) = db.get_connection().execute(&sql, ()).await {
+            log::error!("could not create index {name}: {err}");
+            return Err(format!("Could not create index {name}"));
+        }
+    }
+    Ok(())
+}
+
 pub async fn get_items(client: &DBClient, owner_id: String) -> Result<Vec<Item>, String> {
     log::info!("getting items for owner: {owner_id}");
 
@@ -62,23 +341,45 @@ pub async fn get_items(client: &DBClient, owner_id: String) -> Result<Vec<Item>,
     }
 }
 
-pub async fn create_items(client: &DBClient, items: Vec<Item>) {
+pub async fn create_items(client: &DBClient, items: Vec<Item>) -> Result<(), String> {
+    create_items_returning(client, items).await.map(|_| ())
+}
+
+pub async fn create_items_returning(
+    client: &DBClient,
+    mut items: Vec<Item>,
+) -> Result<Vec<Item>, String> {
     if items.is_empty() {
-        return;
+        return Ok(Vec::new());
     }
+    let normalization = super::text_normalization(client);
+    items.iter_mut().for_each(|i| i.normalize(&normalization));
 
     let client = super::unlock_client(client).await;
     let result = Item::bulk_create(items.as_slice(), &client).await;
     match result {
-        Ok(_) => log::info!("created items"),
-        Err(err) => log::error!("could not create items: {err}"),
+        Ok(created) => {
+            for item in &created {
+                item_events::record(&client, item, ItemEventKind::Created).await;
+            }
+            log::info!("created items");
+            Ok(created)
+        }
+        Err(err) => {
+            log::error!("could not create items: {err}");
+            Err("Could not create items".to_string())
+        }
     }
 }
 
-pub async fn create_item(client: &DBClient, item: Item) -> Result<Item, String> {
+pub async fn create_item(client: &DBClient, mut item: Item) -> Result<Item, String> {
+    item.normalize(&super::text_normalization(client));
     let db = super::unlock_client(client).await;
 
     let res = Item::create(&item, &db).await;
+    if let Ok(created_item) = &res {
+        item_events::record(&db, created_item, ItemEventKind::Created).await;
+    }
     drop(db);
 
     match res {
@@ -92,6 +393,328 @@ pub async fn create_item(client: &DBClient, item: Item) -> Result<Item, String>
         }
     }
 }
+
+/// Creates `item` at the end of its owner's manually ordered list
+///
+/// The position is computed inside the INSERT, so concurrent appends can't get
+/// the same one.
+pub async fn append_item(client: &DBClient, mut item: Item) -> Result<Item, String> {
+    item.normalize(&super::text_normalization(client));
+    let next_position = QueryBuilder::new("items")
+        .select(vec!["COALESCE(MAX(position), 0) + 1"])
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            item.owner_id(),
+        )));
+    let db = super::unlock_client(client).await;
+
+    let res = match item
+        .create_computed(vec![("position", next_position)], &db)
+        .await
+    {
+        Ok(created) => Item::find_by_id(created.id(), &db).await,
+        Err(err) => Err(err),
+    };
+    if let Ok(Some(created_item)) = &res {
+        item_events::record(&db, created_item, ItemEventKind::Created).await;
+    }
+    drop(db);
+
+    match res {
+        Ok(Some(created_item)) => {
+            log::info!("appended item {}", created_item.id());
+            Ok(created_item)
+        }
+        Ok(None) => Err("Could not create item".to_string()),
+        Err(err) => {
+            log::error!("{err:?}");
+            Err("Could not create item".to_string())
+        }
+    }
+}
+
+/// Moves an item to `new_position` of its owner's list, shifting the items in
+/// between by one so positions stay gap-free
+///
+/// Positions past the end move the item to the end.
+pub async fn move_item(
+    client: &DBClient,
+    owner_id: String,
+    item_id: i64,
+    new_position: i64,
+) -> Result<(), String> {
+    let db = super::unlock_client(client).await;
+    let res = super::with_savepoint(&db, "move_item", async {
+        let item = match Item::find_by_id(item_id, &db).await {
+            Ok(Some(item)) if item.owner_id == owner_id => item,
+            Ok(_) => return Err(format!("item {item_id} not found")),
+            Err(err) => return Err(err.to_string()),
+        };
+        let Some(old_position) = item.position else {
+            return Err(format!("item {item_id} has no position"));
+        };
+        let last: Option<i64> = db
+            .query_scalar(
+                "SELECT MAX(position) FROM items WHERE owner_id = ?",
+                vec![owner_id.clone().into()],
+            )
+            .await
+            .map_err(|err| err.to_string())?;
+        let new_position = new_position.clamp(1, last.unwrap_or(1));
+
+        let shift = if new_position > old_position {
+            "UPDATE items SET position = position - 1 \
+             WHERE owner_id = ? AND position > ? AND position <= ?"
+        } else {
+            "UPDATE items SET position = position + 1 \
+             WHERE owner_id = ? AND position >= ? AND position < ?"
+        };
+        let (from, to) = if new_position > old_position {
+            (old_position, new_position)
+        } else {
+            (new_position, old_position)
+        };
+        db.execute(shift, vec![owner_id.clone().into(), from.into(), to.into()])
+            .await
+            .map_err(|err| err.to_string())?;
+        db.execute(
+            "UPDATE items SET position = ? WHERE id = ?",
+            vec![new_position.into(), item_id.into()],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+        Ok(())
+    })
+    .await;
+    drop(db);
+
+    res.map_err(|err| {
+        log::error!("could not move item {item_id}: {err}");
+        "Could not move item".to_string()
+    })
+}
+
+/// Rewrites the positions of the owner's list to `ordered_ids`, first to last
+///
+/// The ids must include every item of the owner that has a position, each once;
+/// unplaced items may be included and get placed. All positions are written in
+/// one UPDATE.
+pub async fn reorder(
+    client: &DBClient,
+    owner_id: String,
+    ordered_ids: &[i64],
+) -> Result<(), String> {
+    if ordered_ids.is_empty() {
+        return Ok(());
+    }
+    let unique: std::collections::HashSet<&i64> = ordered_ids.iter().collect();
+    if unique.len() != ordered_ids.len() {
+        return Err("Each item can only appear once".to_string());
+    }
+
+    let placeholders = vec!["?"; ordered_ids.len()].join(", ");
+    let cases = vec!["WHEN ? THEN ?"; ordered_ids.len()].join(" ");
+    let mut params = Vec::new();
+    for (index, id) in ordered_ids.iter().enumerate() {
+        params.push((*id).into());
+        params.push((index as i64 + 1).into());
+    }
+    params.push(owner_id.clone().into());
+    params.extend(ordered_ids.iter().map(|id| (*id).into()));
+
+    let db = super::unlock_client(client).await;
+    let res = super::with_savepoint(&db, "reorder_items", async {
+        let mut check_params = vec![owner_id.clone().into()];
+        check_params.extend(ordered_ids.iter().map(|id| (*id).into()));
+        let left_out: Option<i64> = db
+            .query_scalar(
+                &format!(
+                    "SELECT COUNT(*) FROM items WHERE owner_id = ? \
+                     AND position IS NOT NULL AND id NOT IN ({placeholders})"
+                ),
+                check_params,
+            )
+            .await
+            .map_err(|err| err.to_string())?;
+        if left_out.unwrap_or(0) > 0 {
+            return Err("order must include every placed item".to_string());
+        }
+
+        let updated = db
+            .execute(
+                &format!(
+                    "UPDATE items SET position = CASE id {cases} END \
+                     WHERE owner_id = ? AND id IN ({placeholders})"
+                ),
+                params,
+            )
+            .await
+            .map_err(|err| err.to_string())?;
+        if updated != ordered_ids.len() as u64 {
+            return Err("order contains unknown items".to_string());
+        }
+        Ok(())
+    })
+    .await;
+    drop(db);
+
+    res.map_err(|err| {
+        log::error!("could not reorder items of {owner_id}: {err}");
+        "Could not reorder items".to_string()
+    })
+}
+
+/// How [`quantity_totals`] counts items without a quantity
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingQuantity {
+    /// "flour" is one flour
+    #[default]
+    CountAsOne,
+    /// Left out of the totals
+    Exclude,
+}
+
+/// Sum of the quantities of one task in one unit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantityTotal {
+    /// Normalized task text, lowercase
+    pub task: String,
+    pub unit: Option<String>,
+    pub total: f64,
+    /// Number of items summed up
+    pub items: i64,
+}
+
+/// Totals of the active items per task and unit, e.g. 2 kg and 1 kg of flour
+/// as 3 kg flour
+///
+/// Different units of the same task stay separate rows, they aren't converted.
+pub async fn quantity_totals(
+    client: &DBClient,
+    owner_id: &str,
+    missing: MissingQuantity,
+) -> Result<Vec<QuantityTotal>, String> {
+    let quantity = match missing {
+        MissingQuantity::CountAsOne => "SUM(COALESCE(quantity, 1)) AS total",
+        MissingQuantity::Exclude => "SUM(quantity) AS total",
+    };
+    let mut query = QueryBuilder::new("items")
+        .select(vec![
+            "LOWER(COALESCE(normalized_task, task)) AS task",
+            "unit",
+            quantity,
+            "COUNT(*) AS items",
+        ])
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .r#where(FilterOperator::Custom(ACTIVE_ITEMS_CONDITION.to_string()));
+    if missing == MissingQuantity::Exclude {
+        query = query.r#where(FilterOperator::Single(Filter::is_not_null("quantity")));
+    }
+    let query = query
+        .group_by(vec!["LOWER(COALESCE(normalized_task, task))", "unit"])
+        .order_by(Sort::asc("task"))
+        .order_by(Sort::asc("unit"));
+
+    let db = super::unlock_client(client).await;
+    let totals = query.execute::<QuantityTotal>(&db).await;
+    drop(db);
+
+    totals.map_err(|err| {
+        log::error!("could not sum quantities for {owner_id}: {err}");
+        "Could not sum quantities".to_string()
+    })
+}
+
+/// What the active items of one currency cost together
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurrencyTotal {
+    /// `None` for prices entered without a currency
+    pub currency: Option<String>,
+    pub total: f64,
+    /// Number of priced items in the total
+    pub items: i64,
+}
+
+/// Cost of the active items, `price * quantity` summed per currency
+///
+/// Items without a quantity count once, items without a price are left out,
+/// so a partly priced list gives a lower bound. Currencies aren't converted.
+pub async fn list_total(client: &DBClient, owner_id: &str) -> Result<Vec<CurrencyTotal>, String> {
+    let query = QueryBuilder::new("items")
+        .select(vec![
+            "UPPER(currency) AS currency",
+            "SUM(price * COALESCE(quantity, 1)) AS total",
+            "COUNT(*) AS items",
+        ])
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .r#where(FilterOperator::Custom(ACTIVE_ITEMS_CONDITION.to_string()))
+        .r#where(FilterOperator::Single(Filter::is_not_null("price")))
+        .group_by(vec!["UPPER(currency)"])
+        .order_by(Sort::asc("currency"));
+
+    let db = super::unlock_client(client).await;
+    let totals = query.execute::<CurrencyTotal>(&db).await;
+    drop(db);
+
+    totals.map_err(|err| {
+        log::error!("could not sum prices for {owner_id}: {err}");
+        "Could not sum prices".to_string()
+    })
+}
+
+/// An item whose name is close to a searched one
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarItem {
+    pub item: Item,
+    /// Edits between the two names, 0 means equal ignoring case
+    pub distance: usize,
+}
+
+/// Items on the list within `max_distance` edits of `task`, closest first
+///
+/// Deleted items are skipped. `task` is compared as given, the items by their
+/// normalized text.
+pub fn rank_similar(items: &[Item], task: &str, max_distance: usize) -> Vec<SimilarItem> {
+    let mut similar: Vec<SimilarItem> = items
+        .iter()
+        .filter(|item| item.deleted_at.is_none())
+        .filter_map(|item| {
+            let name = item.normalized_task.as_deref().unwrap_or(&item.task);
+            let distance = similarity::levenshtein(task, name);
+            (distance <= max_distance).then(|| SimilarItem {
+                item: item.clone(),
+                distance,
+            })
+        })
+        .collect();
+    similar.sort_by(|a, b| {
+        a.distance
+            .cmp(&b.distance)
+            .then(a.item.task.cmp(&b.item.task))
+    });
+    similar
+}
+
+/// Finds the owner's items that are spelled almost like `task`, e.g.
+/// "tomatoes" for "tomatos"
+pub async fn find_similar(
+    client: &DBClient,
+    owner_id: String,
+    task: &str,
+    max_distance: usize,
+) -> Result<Vec<SimilarItem>, String> {
+    let task = normalize_text(task, &super::text_normalization(client));
+    let items = get_items(client, owner_id).await?;
+    Ok(rank_similar(&items, &task, max_distance))
+}
+
 pub async fn delete_item(client: &DBClient, item_id: i64, owner_id: String) {
     let db = super::unlock_client(client).await;
     let item_result = Item::find_by_id(item_id, &db).await;
@@ -105,6 +728,9 @@ pub async fn delete_item(client: &DBClient, item_id: i64, owner_id: String) {
             }
 
             let delete_result = item.delete(&db).await;
+            if delete_result.is_ok() {
+                item_events::record(&db, &item, ItemEventKind::Deleted).await;
+            }
             drop(db);
 
             match delete_result {
@@ -152,6 +778,14 @@ pub async fn toggle_item(
     item.toggle();
 
     let update_result = item.update(&db).await;
+    if let Ok(updated_item) = &update_result {
+        let kind = if updated_item.completed() {
+            ItemEventKind::Completed
+        } else {
+            ItemEventKind::Reopened
+        };
+        item_events::record(&db, updated_item, kind).await;
+    }
     drop(db);
 
     match update_result {
@@ -218,10 +852,643 @@ pub async fn update_item(
     }
 
     item.update_task(&new_task);
+    item.normalize(&super::text_normalization(client));
     item.updated_at = chrono::Utc::now();
 
     let update_result = item.update(&db).await;
+    if let Ok(updated_item) = &update_result {
+        item_events::record(&db, updated_item, ItemEventKind::Updated).await;
+    }
     drop(db);
 
     update_result.map_err(|e| e.to_string())
 }
+
+/// Claims one of `user_id`'s items unless it's held already
+///
+/// The check and the write happen in one statement, so of two concurrent
+/// claims only one succeeds. Returns whether this claim won, `None` when
+/// `user_id` has no such item.
+pub async fn claim_item(
+    client: &DBClient,
+    item_id: i64,
+    user_id: String,
+) -> Result<Option<bool>, String> {
+    let db = super::unlock_client(client).await;
+    let res = db
+        .execute(
+            "UPDATE items SET claimed_by = ?, updated_at = ? \
+             WHERE id = ? AND owner_id = ? AND claimed_by IS NULL",
+            vec![
+                user_id.clone().into(),
+                chrono::Utc::now().to_rfc3339().into(),
+                item_id.into(),
+                user_id.clone().into(),
+            ],
+        )
+        .await;
+    let claimed = match res {
+        Ok(affected) => affected == 1,
+        Err(err) => {
+            drop(db);
+            log::error!("could not claim item {item_id}: {err}");
+            return Err("Could not claim item".to_string());
+        }
+    };
+    if claimed {
+        drop(db);
+        log::info!("item {item_id} claimed by {user_id}");
+        return Ok(Some(true));
+    }
+
+    let found = Item::find_by_id(item_id, &db).await;
+    drop(db);
+    match found {
+        Ok(Some(item)) if item.owner_id() == user_id => Ok(Some(false)),
+        Ok(_) => Ok(None),
+        Err(err) => {
+            log::error!("Error finding item {item_id}: {err:?}");
+            Err("Database error".to_string())
+        }
+    }
+}
+
+/// Releases a claim `user_id` holds on one of their items, returns whether a
+/// claim was cleared
+pub async fn release_claim(
+    client: &DBClient,
+    item_id: i64,
+    user_id: String,
+) -> Result<bool, String> {
+    let db = super::unlock_client(client).await;
+    let res = db
+        .execute(
+            "UPDATE items SET claimed_by = NULL, updated_at = ? \
+             WHERE id = ? AND owner_id = ? AND claimed_by = ?",
+            vec![
+                chrono::Utc::now().to_rfc3339().into(),
+                item_id.into(),
+                user_id.clone().into(),
+                user_id.into(),
+            ],
+        )
+        .await;
+    drop(db);
+
+    match res {
+        Ok(affected) => Ok(affected == 1),
+        Err(err) => {
+            log::error!("could not release claim on item {item_id}: {err}");
+            Err("Could not release claim".to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_owner_completed_filter_uses_index() {
+        let client = crate::database::test_client().await;
+        ensure_indexes(&client).await.unwrap();
+        ensure_indexes(&client).await.unwrap();
+
+        let db = crate::database::unlock_client(&client).await;
+        let mut rows = db
+            .query(
+                "EXPLAIN QUERY PLAN SELECT * FROM items WHERE owner_id = ? AND completed = ?",
+                vec!["1".into(), 0.into()],
+            )
+            .await
+            .unwrap();
+        let mut plan = Vec::new();
+        while let Some(row) = rows.next().await.unwrap() {
+            plan.push(row.get::<String>(3).unwrap());
+        }
+
+        assert!(
+            plan.iter()
+                .any(|d| d.starts_with("SEARCH items USING INDEX idx_items_owner_completed")),
+            "unexpected plan: {plan:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_active_items_uses_partial_index() {
+        let client = crate::database::test_client().await;
+        // Mostly finished items, as on a list in use, with the planner
+        // statistics the maintenance job gathers
+        let mut items = Vec::new();
+        for i in 0..50 {
+            let mut item = Item::new("1".to_string(), format!("item {i}"));
+            item.set_completed(i % 10 != 0);
+            items.push(item);
+        }
+        create_items(&client, items).await.unwrap();
+        crate::database::maintenance::optimize(&client)
+            .await
+            .unwrap();
+
+        let db = crate::database::unlock_client(&client).await;
+        let plan = active_items_builder("1")
+            .explain_query_plan(&db)
+            .await
+            .unwrap();
+
+        assert!(
+            plan.iter()
+                .any(|d| d.starts_with("SEARCH items USING INDEX idx_items_owner_active")),
+            "unexpected plan: {plan:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_active_items_skips_completed_and_deleted() {
+        let client = crate::database::test_client().await;
+        let milk = create_item(&client, Item::new("1".to_string(), "milk".to_string()))
+            .await
+            .unwrap();
+        let mut eggs = Item::new("1".to_string(), "eggs".to_string());
+        eggs.set_completed(true);
+        create_item(&client, eggs).await.unwrap();
+        let mut bread = Item::new("1".to_string(), "bread".to_string());
+        bread.deleted_at = Some(chrono::Utc::now());
+        create_item(&client, bread).await.unwrap();
+        create_item(&client, Item::new("2".to_string(), "tea".to_string()))
+            .await
+            .unwrap();
+
+        let active = active_items(&client, "1").await.unwrap();
+        assert_eq!(
+            active.iter().map(|i| i.id()).collect::<Vec<_>>(),
+            vec![milk.id()]
+        );
+    }
+
+    #[test]
+    fn test_completing_sets_and_clears_completed_at() {
+        let mut item = Item::new("1".to_string(), "milk".to_string());
+        assert_eq!(item.completed_at, None);
+
+        item.toggle();
+        assert!(item.completed());
+        let completed_at = item.completed_at.expect("completed_at should be set");
+
+        item.set_completed(true);
+        assert_eq!(item.completed_at, Some(completed_at));
+
+        item.toggle();
+        assert!(!item.completed());
+        assert_eq!(item.completed_at, None);
+    }
+
+    #[tokio::test]
+    async fn test_toggle_item_persists_completed_at() {
+        let client = crate::database::test_client().await;
+        let item = create_item(&client, Item::new("1".to_string(), "milk".to_string()))
+            .await
+            .unwrap();
+
+        toggle_item(&client, item.id(), "1".to_string())
+            .await
+            .unwrap();
+        let stored = get_item(&client, item.id(), "1".to_string()).await.unwrap();
+        assert!(stored.completed());
+        assert!(stored.completed_at.is_some());
+
+        toggle_item(&client, item.id(), "1".to_string())
+            .await
+            .unwrap();
+        let stored = get_item(&client, item.id(), "1".to_string()).await.unwrap();
+        assert!(!stored.completed());
+        assert_eq!(stored.completed_at, None);
+    }
+
+    #[test]
+    fn test_dry_run_active_items_by_name_page_two() {
+        let query = ItemListQuery {
+            completed: Some(false),
+            sort: ItemSort::Name,
+            order: SortOrder::Asc,
+            pagination: Pagination::page(2, 20),
+        };
+
+        let (sql, params) = dry_run_item_list("user-1", &query).unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM items WHERE owner_id = ? AND completed = ? ORDER BY task ASC LIMIT 20 OFFSET 20"
+        );
+        assert_eq!(params, vec!["<text>", "<integer>"]);
+    }
+
+    #[tokio::test]
+    async fn test_second_claim_fails_until_released() {
+        let client = crate::database::test_client().await;
+        let item = create_item(&client, Item::new("1".to_string(), "milk".to_string()))
+            .await
+            .unwrap();
+
+        let (first, second) = tokio::join!(
+            claim_item(&client, item.id(), "1".to_string()),
+            claim_item(&client, item.id(), "1".to_string()),
+        );
+        let (first, second) = (first.unwrap().unwrap(), second.unwrap().unwrap());
+        assert!(first ^ second, "exactly one claim should win");
+
+        let claimed = get_item(&client, item.id(), "1".to_string()).await.unwrap();
+        assert_eq!(claimed.claimed_by.as_deref(), Some("1"));
+
+        assert!(
+            release_claim(&client, item.id(), "1".to_string())
+                .await
+                .unwrap()
+        );
+        let released = get_item(&client, item.id(), "1".to_string()).await.unwrap();
+        assert_eq!(released.claimed_by, None);
+
+        assert_eq!(
+            claim_item(&client, item.id(), "1".to_string())
+                .await
+                .unwrap(),
+            Some(true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_foreign_user_cannot_claim_or_release() {
+        let client = crate::database::test_client().await;
+        let item = create_item(&client, Item::new("1".to_string(), "milk".to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            claim_item(&client, item.id(), "2".to_string())
+                .await
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            claim_item(&client, 9999, "1".to_string()).await.unwrap(),
+            None
+        );
+        let unclaimed = get_item(&client, item.id(), "1".to_string()).await.unwrap();
+        assert_eq!(unclaimed.claimed_by, None);
+
+        claim_item(&client, item.id(), "1".to_string())
+            .await
+            .unwrap();
+        assert!(
+            !release_claim(&client, item.id(), "2".to_string())
+                .await
+                .unwrap()
+        );
+        let claimed = get_item(&client, item.id(), "1".to_string()).await.unwrap();
+        assert_eq!(claimed.claimed_by.as_deref(), Some("1"));
+    }
+
+    #[tokio::test]
+    async fn test_items_due_between_respects_bounds() {
+        use chrono::TimeZone;
+
+        let client = crate::database::test_client().await;
+        let at = |hour| {
+            chrono::Utc
+                .with_ymd_and_hms(2026, 3, 1, hour, 0, 0)
+                .unwrap()
+        };
+        for (task, due_date) in [
+            ("at start", Some(at(8))),
+            ("inside", Some(at(10))),
+            ("at end", Some(at(12))),
+            ("after", Some(at(13))),
+            ("undated", None),
+        ] {
+            let mut item = Item::new("1".to_string(), task.to_string());
+            item.due_date = due_date;
+            create_item(&client, item).await.unwrap();
+        }
+
+        for (bounds, expected) in [
+            (RangeBounds::Inclusive, vec!["at start", "inside", "at end"]),
+            (RangeBounds::Exclusive, vec!["inside"]),
+            (RangeBounds::StartInclusive, vec!["at start", "inside"]),
+            (RangeBounds::EndInclusive, vec!["inside", "at end"]),
+        ] {
+            let items = items_due_between(&client, "1", at(8), at(12), bounds)
+                .await
+                .unwrap();
+            let tasks: Vec<&str> = items.iter().map(|i| i.task.as_str()).collect();
+            assert_eq!(tasks, expected, "{bounds:?}");
+        }
+        assert!(
+            items_due_between(&client, "2", at(0), at(23), RangeBounds::Inclusive)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_items_due_between_compares_fractions_and_uses_index() {
+        use chrono::TimeZone;
+
+        let client = crate::database::test_client().await;
+        ensure_indexes(&client).await.unwrap();
+        let start = chrono::Utc.with_ymd_and_hms(2026, 3, 1, 8, 0, 0).unwrap();
+        for (task, offset_ms) in [("just before", -1), ("half past", 500), ("late", 1500)] {
+            let mut item = Item::new("1".to_string(), task.to_string());
+            item.due_date = Some(start + chrono::Duration::milliseconds(offset_ms));
+            create_item(&client, item).await.unwrap();
+        }
+
+        let end = start + chrono::Duration::milliseconds(1000);
+        let items = items_due_between(&client, "1", start, end, RangeBounds::Inclusive)
+            .await
+            .unwrap();
+        let tasks: Vec<&str> = items.iter().map(|i| i.task.as_str()).collect();
+        assert_eq!(tasks, vec!["half past"]);
+
+        let db = crate::database::unlock_client(&client).await;
+        let plan = due_between_builder("1", start, end, RangeBounds::Inclusive)
+            .explain_query_plan(&db)
+            .await
+            .unwrap();
+        assert!(
+            plan.iter().any(
+                |d| d.starts_with("SEARCH items USING INDEX idx_items_owner_due_date")
+                    && d.contains("due_date>")
+            ),
+            "unexpected plan: {plan:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_omitted_created_at_uses_db_default() {
+        let client = crate::database::test_client().await;
+        let db = crate::database::unlock_client(&client).await;
+        let item = Item::new("1".to_string(), "milk".to_string())
+            .create_with_defaults(&["created_at"], &db)
+            .await
+            .unwrap();
+
+        let created_at: Option<String> = db
+            .query_scalar(
+                "SELECT created_at FROM items WHERE id = ?",
+                vec![item.id().into()],
+            )
+            .await
+            .unwrap();
+        let created_at = created_at.expect("created_at should come from the default");
+        let stored = chrono::NaiveDateTime::parse_from_str(&created_at, "%Y-%m-%d %H:%M:%S")
+            .unwrap_or_else(|_| panic!("unexpected default: {created_at}"));
+
+        let reloaded = Item::find_by_id(item.id(), &db).await.unwrap().unwrap();
+        assert_eq!(reloaded.created_at.naive_utc(), stored);
+
+        assert!(
+            Item::new("1".to_string(), "eggs".to_string())
+                .create_with_defaults(&["created"], &db)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_appends_get_consecutive_positions() {
+        let client = crate::database::test_client().await;
+        create_item(&client, Item::new("1".to_string(), "unplaced".to_string()))
+            .await
+            .unwrap();
+        let milk = append_item(&client, Item::new("1".to_string(), "milk".to_string()))
+            .await
+            .unwrap();
+        let eggs = append_item(&client, Item::new("1".to_string(), "eggs".to_string()))
+            .await
+            .unwrap();
+        let tea = append_item(&client, Item::new("2".to_string(), "tea".to_string()))
+            .await
+            .unwrap();
+
+        assert_eq!(milk.position, Some(1));
+        assert_eq!(eggs.position, Some(2));
+        assert_eq!(tea.position, Some(1));
+    }
+
+    async fn positions(client: &DBClient, owner_id: &str) -> Vec<(String, Option<i64>)> {
+        let mut items = get_items(client, owner_id.to_string()).await.unwrap();
+        items.sort_by_key(|i| i.position);
+        items.into_iter().map(|i| (i.task, i.position)).collect()
+    }
+
+    async fn placed_list(client: &DBClient, tasks: &[&str]) -> Vec<Item> {
+        let mut items = Vec::new();
+        for task in tasks {
+            let item = append_item(client, Item::new("1".to_string(), task.to_string()))
+                .await
+                .unwrap();
+            items.push(item);
+        }
+        items
+    }
+
+    fn placed(tasks: &[&str]) -> Vec<(String, Option<i64>)> {
+        tasks
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.to_string(), Some(i as i64 + 1)))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_move_item_shifts_items_in_between() {
+        let client = crate::database::test_client().await;
+        let items = placed_list(&client, &["milk", "eggs", "bread", "tea"]).await;
+
+        move_item(&client, "1".to_string(), items[0].id(), 3)
+            .await
+            .unwrap();
+        assert_eq!(
+            positions(&client, "1").await,
+            placed(&["eggs", "bread", "milk", "tea"])
+        );
+
+        move_item(&client, "1".to_string(), items[3].id(), 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            positions(&client, "1").await,
+            placed(&["tea", "eggs", "bread", "milk"])
+        );
+
+        move_item(&client, "1".to_string(), items[1].id(), 99)
+            .await
+            .unwrap();
+        assert_eq!(
+            positions(&client, "1").await,
+            placed(&["tea", "bread", "milk", "eggs"])
+        );
+        assert!(
+            move_item(&client, "2".to_string(), items[1].id(), 1)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reorder_writes_requested_order() {
+        let client = crate::database::test_client().await;
+        let items = placed_list(&client, &["milk", "eggs", "bread"]).await;
+        let ids: Vec<i64> = items.iter().map(Item::id).collect();
+
+        reorder(&client, "1".to_string(), &[ids[2], ids[0], ids[1]])
+            .await
+            .unwrap();
+        assert_eq!(
+            positions(&client, "1").await,
+            placed(&["bread", "milk", "eggs"])
+        );
+
+        // Incomplete, duplicated or foreign orders leave the list alone
+        assert!(
+            reorder(&client, "1".to_string(), &[ids[0], ids[1]])
+                .await
+                .is_err()
+        );
+        assert!(
+            reorder(&client, "1".to_string(), &[ids[0], ids[0], ids[1], ids[2]])
+                .await
+                .is_err()
+        );
+        assert!(
+            reorder(&client, "1".to_string(), &[ids[0], ids[1], ids[2], 9999])
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            positions(&client, "1").await,
+            placed(&["bread", "milk", "eggs"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_similar_matches_typos_only() {
+        let client = crate::database::test_client().await;
+        for task in ["tomatoes", "potatoes", "milk"] {
+            create_item(&client, Item::new("1".to_string(), task.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let similar = find_similar(&client, "1".to_string(), "Tomatos", 1)
+            .await
+            .unwrap();
+        let found: Vec<(&str, usize)> = similar
+            .iter()
+            .map(|s| (s.item.task.as_str(), s.distance))
+            .collect();
+        assert_eq!(found, vec![("tomatoes", 1)]);
+
+        let similar = find_similar(&client, "1".to_string(), "bread", 2)
+            .await
+            .unwrap();
+        assert!(similar.is_empty());
+        let similar = find_similar(&client, "2".to_string(), "tomatos", 1)
+            .await
+            .unwrap();
+        assert!(similar.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_quantity_totals_sum_per_unit() {
+        let client = crate::database::test_client().await;
+        let measured = |task: &str, quantity: Option<f64>, unit: Option<&str>| {
+            let mut item = Item::new("1".to_string(), task.to_string());
+            item.quantity = quantity;
+            item.unit = unit.map(str::to_string);
+            item
+        };
+        create_items(
+            &client,
+            vec![
+                measured("flour", Some(2.0), Some("kg")),
+                measured("Flour", Some(1.0), Some("kg")),
+                measured("flour", None, None),
+                measured("eggs", Some(6.0), None),
+            ],
+        )
+        .await
+        .unwrap();
+        let total = |task: &str, unit: Option<&str>, total: f64, items: i64| QuantityTotal {
+            task: task.to_string(),
+            unit: unit.map(str::to_string),
+            total,
+            items,
+        };
+
+        assert_eq!(
+            quantity_totals(&client, "1", MissingQuantity::CountAsOne)
+                .await
+                .unwrap(),
+            vec![
+                total("eggs", None, 6.0, 1),
+                total("flour", None, 1.0, 1),
+                total("flour", Some("kg"), 3.0, 2),
+            ]
+        );
+        assert_eq!(
+            quantity_totals(&client, "1", MissingQuantity::Exclude)
+                .await
+                .unwrap(),
+            vec![
+                total("eggs", None, 6.0, 1),
+                total("flour", Some("kg"), 3.0, 2)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_total_multiplies_quantity_per_currency() {
+        let client = crate::database::test_client().await;
+        let priced = |task: &str, quantity: Option<f64>, price: Option<f64>, currency: &str| {
+            let mut item = Item::new("1".to_string(), task.to_string());
+            item.quantity = quantity;
+            item.price = price;
+            item.currency = Some(currency.to_string());
+            item
+        };
+        let mut done = priced("coffee", None, Some(9.0), "EUR");
+        done.set_completed(true);
+        create_items(
+            &client,
+            vec![
+                priced("milk", Some(3.0), Some(1.25), "EUR"),
+                priced("bread", None, Some(2.5), "eur"),
+                priced("eggs", Some(2.0), None, "EUR"),
+                priced("tea", Some(2.0), Some(4.0), "CHF"),
+                done,
+            ],
+        )
+        .await
+        .unwrap();
+
+        let totals = list_total(&client, "1").await.unwrap();
+        assert_eq!(
+            totals,
+            vec![
+                CurrencyTotal {
+                    currency: Some("CHF".to_string()),
+                    total: 8.0,
+                    items: 1,
+                },
+                CurrencyTotal {
+                    currency: Some("EUR".to_string()),
+                    total: 6.25,
+                    items: 2,
+                },
+            ]
+        );
+        assert!(list_total(&client, "2").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+use libsql_orm::Database;
+use std::time::Duration;
+
+use crate::database::DBClient;
+
+/// Rows removed by [`cleanup_orphans`], per table
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CleanupReport {
+    pub item_tags: u64,
+    pub attachments: u64,
+}
+
+/// Child rows whose parent no longer exists; `item_events` is left alone on
+/// purpose, the history of deleted items is kept
+const ORPHANED_ITEM_TAGS: &str = "DELETE FROM item_tags \
+    WHERE NOT EXISTS (SELECT 1 FROM items WHERE items.id = item_tags.item_id) \
+    OR NOT EXISTS (SELECT 1 FROM tags WHERE tags.id = item_tags.tag_id)";
+const ORPHANED_ATTACHMENTS: &str = "DELETE FROM attachments \
+    WHERE NOT EXISTS (SELECT 1 FROM items WHERE items.id = attachments.item_id)";
+
+const SAVEPOINT: &str = "cleanup_orphans";
+
+/// Deletes child rows left behind by item deletions that didn't cascade
+///
+/// Runs in a savepoint, so either all tables are cleaned or none. Running it
+/// again is harmless, it then reports zero rows.
+pub async fn cleanup_orphans(client: &DBClient) -> Result<CleanupReport, String> {
+    let db = super::unlock_client(client).await;
+    if let Err(err) = db.savepoint(SAVEPOINT).await {
+        log::error!("could not start orphan cleanup: {err}");
+        return Err("Could not clean up orphans".to_string());
+    }
+
+    match delete_orphans(&db).await {
+        Ok(report) => match db.release_savepoint(SAVEPOINT).await {
+            Ok(()) => {
+                log::info!("removed orphans: {report:?}");
+                Ok(report)
+            }
+            Err(err) => {
+                log::error!("could not commit orphan cleanup: {err}");
+                Err("Could not clean up orphans".to_string())
+            }
+        },
+        Err(err) => {
+            if let Err(rollback_err) = db.rollback_to_savepoint(SAVEPOINT).await {
+                log::error!("could not roll back orphan cleanup: {rollback_err}");
+            }
+            log::error!("could not clean up orphans: {err}");
+            Err("Could not clean up orphans".to_string())
+        }
+    }
+}
+
+async fn delete_orphans(db: &Database) -> Result<CleanupReport, String> {
+    let delete = |sql: &'static str| async move {
+        db.execute(sql, vec![]).await.map_err(|err| err.to_string())
+    };
+    Ok(CleanupReport {
+        item_tags: delete(ORPHANED_ITEM_TAGS).await?,
+        attachments: delete(ORPHANED_ATTACHMENTS).await?,
+    })
+}
+
+/// Compacts the database file and refreshes the query planner statistics
+///
+/// Runs `VACUUM`, `ANALYZE` and `PRAGMA optimize` in that order. VACUUM can't
+/// run inside a transaction, so don't call this from within a savepoint. It's
+/// skipped for remote databases, whose server manages its files.
+pub async fn optimize(client: &DBClient) -> Result<(), String> {
+    let local = super::is_local(client);
+    let db = super::unlock_client(client).await;
+    let res = async {
+        if local {
+            db.vacuum().await.map_err(|err| err.to_string())?;
+        }
+        db.analyze().await.map_err(|err| err.to_string())?;
+        db.execute("PRAGMA optimize", vec![])
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok::<_, String>(())
+    }
+    .await;
+    drop(db);
+
+    res.map_err(|err| {
+        log::error!("could not optimize database: {err}");
+        "Could not optimize database".to_string()
+    })
+}
+
+/// Runs [`cleanup_orphans`] and then [`optimize`] once per `every`, starting
+/// one interval after the call, for as long as the server lives
+///
+/// Failures are logged by the steps themselves and don't stop the schedule.
+pub async fn run_periodically(client: DBClient, every: Duration) {
+    let start = tokio::time::Instant::now() + every;
+    let mut interval = tokio::time::interval_at(start, every);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let _ = cleanup_orphans(&client).await;
+        let _ = optimize(&client).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{items, tags};
+
+    #[tokio::test]
+    async fn test_cleanup_removes_orphaned_tags_only() {
+        let client = crate::database::test_client().await;
+        let kept = items::create_item(&client, items::Item::new("1".into(), "milk".into()))
+            .await
+            .unwrap();
+        let removed = items::create_item(&client, items::Item::new("1".into(), "eggs".into()))
+            .await
+            .unwrap();
+        tags::add_tag(&client, "1".to_string(), kept.id(), "dairy")
+            .await
+            .unwrap();
+        tags::add_tag(&client, "1".to_string(), removed.id(), "dairy")
+            .await
+            .unwrap();
+        tags::add_tag(&client, "1".to_string(), removed.id(), "organic")
+            .await
+            .unwrap();
+        items::delete_item(&client, removed.id(), "1".to_string()).await;
+
+        let report = cleanup_orphans(&client).await.unwrap();
+        assert_eq!(
+            report,
+            CleanupReport {
+                item_tags: 2,
+                attachments: 0
+            }
+        );
+        assert_eq!(
+            cleanup_orphans(&client).await.unwrap(),
+            CleanupReport::default()
+        );
+
+        let tagged = tags::items_with_tag(&client, "1".to_string(), "dairy")
+            .await
+            .unwrap();
+        assert_eq!(tagged.len(), 1);
+        assert_eq!(tagged[0].id(), kept.id());
+    }
+
+    #[tokio::test]
+    async fn test_optimize_gathers_stats() {
+        let client = crate::database::test_client().await;
+        items::create_item(&client, items::Item::new("1".into(), "milk".into()))
+            .await
+            .unwrap();
+
+        optimize(&client).await.unwrap();
+        let db = crate::database::unlock_client(&client).await;
+        let tables: Option<i64> = db
+            .query_scalar(
+                "SELECT COUNT(*) FROM sqlite_stat1 WHERE tbl = 'items'",
+                vec![],
+            )
+            .await
+            .unwrap();
+        assert!(tables.unwrap() > 0);
+    }
+}
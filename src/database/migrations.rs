@@ -13,6 +13,25 @@ pub async fn run(client: &DBClient) {
             .await
             .expect("items migration failed");
     }
+    ensure_column(client, "items", "due_date", "DATETIME").await;
+    ensure_column(client, "items", "claimed_by", "TEXT").await;
+    ensure_column(client, "items", "normalized_task", "TEXT").await;
+    ensure_column(client, "items", "category", "TEXT").await;
+    ensure_column(client, "items", "confidence", "REAL").await;
+    ensure_column(
+        client,
+        "items",
+        "needs_review",
+        "INTEGER NOT NULL DEFAULT 0",
+    )
+    .await;
+    ensure_column(client, "items", "completed_at", "DATETIME").await;
+    ensure_column(client, "items", "deleted_at", "DATETIME").await;
+    ensure_column(client, "items", "quantity", "REAL").await;
+    ensure_column(client, "items", "unit", "TEXT").await;
+    ensure_column(client, "items", "position", "INTEGER").await;
+    ensure_column(client, "items", "price", "REAL").await;
+    ensure_column(client, "items", "currency", "TEXT").await;
     log::info!("Items table migration completed");
 
     let messages_sql = include_str!("../../migrations/messages.sql");
@@ -37,6 +56,83 @@ pub async fn run(client: &DBClient) {
     }
     log::info!("Recipes table migration completed");
 
+    let attachments_sql = include_str!("../../migrations/attachments.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(attachments_sql)
+            .await
+            .expect("attachments migration failed");
+    }
+    log::info!("Attachments table migration completed");
+
+    let store_layouts_sql = include_str!("../../migrations/store_layouts.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(store_layouts_sql)
+            .await
+            .expect("store layouts migration failed");
+    }
+    log::info!("Store layouts table migration completed");
+
+    let tags_sql = include_str!("../../migrations/tags.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(tags_sql)
+            .await
+            .expect("tags migration failed");
+    }
+    log::info!("Tags table migration completed");
+
+    let webhooks_sql = include_str!("../../migrations/webhooks.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(webhooks_sql)
+            .await
+            .expect("webhooks migration failed");
+    }
+    log::info!("Webhooks table migration completed");
+
+    let shopping_sessions_sql = include_str!("../../migrations/shopping_sessions.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(shopping_sessions_sql)
+            .await
+            .expect("shopping sessions migration failed");
+    }
+    log::info!("Shopping sessions table migration completed");
+
+    let op_log_sql = include_str!("../../migrations/op_log.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(op_log_sql)
+            .await
+            .expect("op log migration failed");
+    }
+    log::info!("Op log table migration completed");
+
+    let item_events_sql = include_str!("../../migrations/item_events.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(item_events_sql)
+            .await
+            .expect("item events migration failed");
+    }
+    log::info!("Item events table migration completed");
+
     // Run index migrations
     let items_indexes_sql = include_str!("../../migrations/items_indexes.sql");
     {
@@ -47,6 +143,9 @@ pub async fn run(client: &DBClient) {
             .await
             .expect("items indexes migration failed");
     }
+    super::items::ensure_indexes(client)
+        .await
+        .expect("items composite indexes migration failed");
     log::info!("Items indexes migration completed");
 
     let messages_indexes_sql = include_str!("../../migrations/messages_indexes.sql");
@@ -71,5 +170,83 @@ pub async fn run(client: &DBClient) {
     }
     log::info!("Recipes indexes migration completed");
 
+    let attachments_indexes_sql = include_str!("../../migrations/attachments_indexes.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(attachments_indexes_sql)
+            .await
+            .expect("attachments indexes migration failed");
+    }
+    log::info!("Attachments indexes migration completed");
+
+    let store_layouts_indexes_sql = include_str!("../../migrations/store_layouts_indexes.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(store_layouts_indexes_sql)
+            .await
+            .expect("store layouts indexes migration failed");
+    }
+    log::info!("Store layouts indexes migration completed");
+
+    let tags_indexes_sql = include_str!("../../migrations/tags_indexes.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(tags_indexes_sql)
+            .await
+            .expect("tags indexes migration failed");
+    }
+    log::info!("Tags indexes migration completed");
+
+    let webhooks_indexes_sql = include_str!("../../migrations/webhooks_indexes.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(webhooks_indexes_sql)
+            .await
+            .expect("webhooks indexes migration failed");
+    }
+    log::info!("Webhooks indexes migration completed");
+
+    let item_events_indexes_sql = include_str!("../../migrations/item_events_indexes.sql");
+    {
+        let client = super::unlock_client(client).await;
+        client
+            .get_connection()
+            .execute_batch(item_events_indexes_sql)
+            .await
+            .expect("item events indexes migration failed");
+    }
+    log::info!("Item events indexes migration completed");
+
     log::info!("All database migrations completed successfully");
 }
+
+/// Adds a column to an existing table unless it is already there
+async fn ensure_column(client: &DBClient, table: &str, column: &str, definition: &str) {
+    let client = super::unlock_client(client).await;
+    let mut rows = client
+        .query(&format!("PRAGMA table_info({table})"), vec![])
+        .await
+        .expect("could not read table info");
+    while let Some(row) = rows.next().await.expect("could not read table info") {
+        if row.get::<String>(1).ok().as_deref() == Some(column) {
+            return;
+        }
+    }
+    client
+        .get_connection()
+        .execute(
+            &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+            (),
+        )
+        .await
+        .expect("add column migration failed");
+    log::info!("Added column {table}.{column}");
+}
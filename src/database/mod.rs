@@ -1,19 +1,54 @@
 use libsql_orm::Database;
 use std::sync::{Arc, Mutex};
 
+use crate::normalize::TextNormalization;
+
 pub type DBClient = Arc<Mutex<DB>>;
 
 pub struct DB {
     url: String,
     token: Option<String>,
+    normalization: TextNormalization,
+    #[cfg(test)]
+    temp_file: Option<TempDbFile>,
+}
+
+/// A database file of a test, removed with its journal once the client is gone
+#[cfg(test)]
+struct TempDbFile(std::path::PathBuf);
+
+#[cfg(test)]
+impl Drop for TempDbFile {
+    fn drop(&mut self) {
+        for suffix in ["", "-journal", "-wal", "-shm"] {
+            let mut path = self.0.clone().into_os_string();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
 }
 
 impl DB {
     pub fn new(url: String, token: Option<String>) -> Self {
-        DB { url, token }
+        DB {
+            url,
+            token,
+            normalization: TextNormalization::default(),
+            #[cfg(test)]
+            temp_file: None,
+        }
+    }
+
+    /// Sets how item text is normalized on insert
+    pub fn with_normalization(mut self, normalization: TextNormalization) -> Self {
+        self.normalization = normalization;
+        self
     }
 
     pub async fn connect(&self) -> libsql_orm::Database {
+        if let Some(path) = self.url.strip_prefix("file:") {
+            return Database::new_local(path).await.unwrap();
+        }
         let token = self.token.clone().unwrap_or_default();
         Database::new_connect(&self.url, &token).await.unwrap()
     }
@@ -24,6 +59,38 @@ pub async fn unlock_client(client: &DBClient) -> libsql_orm::Database {
     client.lock().unwrap().connect().await
 }
 
+pub fn text_normalization(client: &DBClient) -> TextNormalization {
+    client.lock().unwrap().normalization
+}
+
+/// Whether the client opens a local database file rather than a remote one
+pub fn is_local(client: &DBClient) -> bool {
+    client.lock().unwrap().url.starts_with("file:")
+}
+
+/// Runs `work` in a savepoint, rolling its changes back when it fails
+pub(crate) async fn with_savepoint<T>(
+    db: &Database,
+    name: &str,
+    work: impl std::future::Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+    db.savepoint(name).await.map_err(|err| err.to_string())?;
+    match work.await {
+        Ok(value) => {
+            db.release_savepoint(name)
+                .await
+                .map_err(|err| err.to_string())?;
+            Ok(value)
+        }
+        Err(err) => {
+            if let Err(rollback_err) = db.rollback_to_savepoint(name).await {
+                log::error!("could not roll back {name}: {rollback_err}");
+            }
+            Err(err)
+        }
+    }
+}
+
 pub async fn create_orm_client(url: String, token: Option<String>) -> DB {
     DB::new(url, token)
 }
@@ -35,3 +102,34 @@ pub mod messages;
 pub mod recipes;
 
 pub mod items;
+
+pub mod attachments;
+
+pub mod store_layouts;
+
+pub mod item_events;
+
+pub mod tags;
+
+pub mod webhooks;
+
+pub mod maintenance;
+
+pub mod shopping_sessions;
+
+pub mod user_data;
+
+pub mod op_log;
+
+#[cfg(test)]
+pub async fn test_client() -> DBClient {
+    let path = std::env::temp_dir().join(format!(
+        "rezi-test-{}.db",
+        crate::routes::random_id().unsigned_abs()
+    ));
+    let mut db = DB::new(format!("file:{}", path.display()), None);
+    db.temp_file = Some(TempDbFile(path));
+    let client: DBClient = Arc::new(Mutex::new(db));
+    migrations::run(&client).await;
+    client
+}
@@ -0,0 +1,476 @@
+use libsql_orm::{Database, Filter, FilterOperator, Model, QueryBuilder, Sort, SortOrder};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::item_events::{self, ItemEventKind};
+use crate::database::items::Item;
+
+/// A client-side reference to the item a command changes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ItemRef {
+    /// An item the server already knows
+    Id(i64),
+    /// The item made by the `Create` command with this command id, for items
+    /// created offline that have no id yet
+    CreatedBy(String),
+}
+
+/// One mutation of the list, as recorded in the op log and replayed by
+/// [`apply_commands`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Command {
+    Create { task: String },
+    Update { item: ItemRef, task: String },
+    Complete { item: ItemRef },
+    Reopen { item: ItemRef },
+    Delete { item: ItemRef },
+}
+
+impl Command {
+    fn kind(&self) -> &'static str {
+        match self {
+            Command::Create { .. } => "create",
+            Command::Update { .. } => "update",
+            Command::Complete { .. } => "complete",
+            Command::Reopen { .. } => "reopen",
+            Command::Delete { .. } => "delete",
+        }
+    }
+
+    /// The command that repeats a change to `item` made on the server
+    fn of(item: &Item, kind: ItemEventKind) -> Self {
+        let target = ItemRef::Id(item.id());
+        match kind {
+            ItemEventKind::Created => Command::Create {
+                task: item.task.clone(),
+            },
+            ItemEventKind::Updated => Command::Update {
+                item: target,
+                task: item.task.clone(),
+            },
+            ItemEventKind::Completed => Command::Complete { item: target },
+            ItemEventKind::Reopened => Command::Reopen { item: target },
+            ItemEventKind::Deleted => Command::Delete { item: target },
+        }
+    }
+}
+
+/// A command with the id its author gave it, the key that makes replaying
+/// idempotent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncCommand {
+    pub command_id: String,
+    pub command: Command,
+}
+
+/// Append-only record of every mutation of an owner's items
+#[derive(Model, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[table_name("op_log")]
+pub struct OpLogEntry {
+    pub id: std::option::Option<i64>,
+    pub command_id: String,
+    pub owner_id: String,
+    /// Logical clock, counting the owner's commands from 1 in the order they
+    /// were applied on the server
+    pub clock: i64,
+    pub kind: String,
+    /// The changed item, for `create` the one it made
+    pub item_id: Option<i64>,
+    /// The [`Command`] as JSON
+    pub payload: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl OpLogEntry {
+    pub fn command(&self) -> Result<Command, String> {
+        serde_json::from_str(&self.payload).map_err(|err| err.to_string())
+    }
+}
+
+pub fn new_command_id() -> String {
+    libsql_orm::Uuid::new_v4().to_string()
+}
+
+/// Appends `command` to the owner's log with the next clock value
+///
+/// The clock is computed inside the INSERT, so concurrent writers can't get
+/// the same one.
+pub(super) async fn append(
+    db: &Database,
+    owner_id: &str,
+    command_id: &str,
+    command: &Command,
+    item_id: Option<i64>,
+) -> libsql_orm::Result<OpLogEntry> {
+    let entry = OpLogEntry {
+        id: None,
+        command_id: command_id.to_string(),
+        owner_id: owner_id.to_string(),
+        clock: 0,
+        kind: command.kind().to_string(),
+        item_id,
+        payload: serde_json::to_string(command)?,
+        created_at: chrono::Utc::now(),
+    };
+    let next_clock = QueryBuilder::new("op_log")
+        .select(vec!["COALESCE(MAX(clock), 0) + 1"])
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )));
+    entry.create_computed(vec![("clock", next_clock)], db).await
+}
+
+/// Logs a change under `command_id`, or a fresh one for changes made on the
+/// server
+///
+/// The command is rebuilt from the change, so items are referred to by id
+/// even when the client's command named them by their `Create`.
+pub(super) async fn append_change(
+    db: &Database,
+    item: &Item,
+    kind: ItemEventKind,
+    command_id: Option<&str>,
+) -> Result<(), String> {
+    let command_id = command_id.map_or_else(new_command_id, str::to_string);
+    let command = Command::of(item, kind);
+    append(db, &item.owner_id, &command_id, &command, Some(item.id()))
+        .await
+        .map(|_| ())
+        .map_err(|err| {
+            format!(
+                "could not log {} command for item {}: {err}",
+                command.kind(),
+                item.id()
+            )
+        })
+}
+
+/// The owner's commands with a clock after `after_clock`, oldest first, for
+/// clients catching up
+pub async fn commands_since(
+    client: &DBClient,
+    owner_id: &str,
+    after_clock: i64,
+) -> Result<Vec<OpLogEntry>, String> {
+    let query = QueryBuilder::new("op_log")
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .r#where(FilterOperator::Single(Filter::gt(
+            "clock".to_string(),
+            after_clock,
+        )))
+        .order_by(Sort::new("clock", SortOrder::Asc));
+
+    let db = super::unlock_client(client).await;
+    let entries = OpLogEntry::query(query, &db).await;
+    drop(db);
+
+    entries.map_err(|err| {
+        log::error!("could not load op log of {owner_id}: {err}");
+        "Could not load commands".to_string()
+    })
+}
+
+/// Outcome of [`apply_commands`]
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ApplyReport {
+    pub applied: usize,
+    /// Commands whose id was in the log already
+    pub skipped: usize,
+}
+
+/// Applies a batch of commands from a client, skipping those applied before
+///
+/// A command id that is in the log already is a no-op, so a client can resend
+/// a batch after a lost response. Each command runs in its own savepoint
+/// together with its log entry; the first one failing, e.g. completing an
+/// item that was deleted meanwhile, stops the batch with the earlier ones
+/// applied. A command whose log entry can't be written is rolled back too,
+/// it would be applied again when resent.
+pub async fn apply_commands(
+    client: &DBClient,
+    owner_id: &str,
+    commands: &[SyncCommand],
+) -> Result<ApplyReport, String> {
+    let normalization = super::text_normalization(client);
+    let db = super::unlock_client(client).await;
+    let mut report = ApplyReport::default();
+    for sync in commands {
+        let applied = super::with_savepoint(&db, "apply_command", async {
+            if is_logged(&db, owner_id, &sync.command_id).await? {
+                return Ok(None);
+            }
+            let (item, kind) = match &sync.command {
+                Command::Create { task } => {
+                    let mut item = Item::new(owner_id.to_string(), task.clone());
+                    item.normalize(&normalization);
+                    let item = item.create(&db).await.map_err(|err| err.to_string())?;
+                    (item, ItemEventKind::Created)
+                }
+                Command::Update { item, task } => {
+                    let mut item = resolve(&db, owner_id, item).await?;
+                    item.update_task(task);
+                    item.normalize(&normalization);
+                    (update(&db, item).await?, ItemEventKind::Updated)
+                }
+                Command::Complete { item } | Command::Reopen { item } => {
+                    let complete = matches!(sync.command, Command::Complete { .. });
+                    let mut item = resolve(&db, owner_id, item).await?;
+                    item.set_completed(complete);
+                    let kind = if complete {
+                        ItemEventKind::Completed
+                    } else {
+                        ItemEventKind::Reopened
+                    };
+                    (update(&db, item).await?, kind)
+                }
+                Command::Delete { item } => {
+                    let item = resolve(&db, owner_id, item).await?;
+                    item.delete(&db).await.map_err(|err| err.to_string())?;
+                    (item, ItemEventKind::Deleted)
+                }
+            };
+            item_events::record_command(&db, &item, kind, &sync.command_id).await?;
+            Ok(Some((item, kind)))
+        })
+        .await;
+
+        match applied {
+            Ok(Some((item, kind))) => {
+                // Only now, a rolled back command must not reach webhooks
+                super::webhooks::dispatch(&db, &item, kind).await;
+                report.applied += 1;
+            }
+            Ok(None) => report.skipped += 1,
+            Err(err) => {
+                drop(db);
+                log::error!("could not apply command {}: {err}", sync.command_id);
+                return Err(format!("Could not apply command {}", sync.command_id));
+            }
+        }
+    }
+    drop(db);
+    Ok(report)
+}
+
+async fn is_logged(db: &Database, owner_id: &str, command_id: &str) -> Result<bool, String> {
+    QueryBuilder::new("op_log")
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .r#where(FilterOperator::Single(Filter::eq(
+            "command_id".to_string(),
+            command_id.to_string(),
+        )))
+        .exists(db)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn resolve(db: &Database, owner_id: &str, target: &ItemRef) -> Result<Item, String> {
+    let item_id = match target {
+        ItemRef::Id(id) => Some(*id),
+        ItemRef::CreatedBy(command_id) => OpLogEntry::find_where(
+            FilterOperator::And(vec![
+                FilterOperator::Single(Filter::eq("command_id".to_string(), command_id.clone())),
+                FilterOperator::Single(Filter::eq("owner_id".to_string(), owner_id.to_string())),
+            ]),
+            db,
+        )
+        .await
+        .map_err(|err| err.to_string())?
+        .into_iter()
+        .next()
+        .and_then(|entry| entry.item_id),
+    };
+    let item = match item_id {
+        Some(id) => Item::find_by_id(id, db)
+            .await
+            .map_err(|err| err.to_string())?,
+        None => None,
+    };
+    item.filter(|item| item.owner_id == owner_id)
+        .ok_or_else(|| format!("item {target:?} not found"))
+}
+
+async fn update(db: &Database, mut item: Item) -> Result<Item, String> {
+    item.updated_at = chrono::Utc::now();
+    item.update(db).await.map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::items;
+
+    fn command(command_id: &str, command: Command) -> SyncCommand {
+        SyncCommand {
+            command_id: command_id.to_string(),
+            command,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_then_complete_yields_completed_item() {
+        let client = crate::database::test_client().await;
+        let batch = vec![
+            command(
+                "c1",
+                Command::Create {
+                    task: "milk".into(),
+                },
+            ),
+            command(
+                "c2",
+                Command::Complete {
+                    item: ItemRef::CreatedBy("c1".into()),
+                },
+            ),
+        ];
+
+        let report = apply_commands(&client, "1", &batch).await.unwrap();
+        assert_eq!(
+            report,
+            ApplyReport {
+                applied: 2,
+                skipped: 0
+            }
+        );
+
+        let list = items::get_items(&client, "1".to_string()).await.unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].task, "milk");
+        assert!(list[0].completed());
+
+        let log = commands_since(&client, "1", 0).await.unwrap();
+        let logged: Vec<(&str, i64, Option<i64>)> = log
+            .iter()
+            .map(|e| (e.command_id.as_str(), e.clock, e.item_id))
+            .collect();
+        assert_eq!(
+            logged,
+            vec![("c1", 1, Some(list[0].id())), ("c2", 2, Some(list[0].id()))]
+        );
+        // Logged with the resolved id, readers don't need to look up `c1`
+        assert_eq!(
+            log[1].command().unwrap(),
+            Command::Complete {
+                item: ItemRef::Id(list[0].id())
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replaying_a_command_is_a_noop() {
+        let client = crate::database::test_client().await;
+        let batch = vec![command(
+            "c1",
+            Command::Create {
+                task: "milk".into(),
+            },
+        )];
+        apply_commands(&client, "1", &batch).await.unwrap();
+
+        let report = apply_commands(&client, "1", &batch).await.unwrap();
+        assert_eq!(
+            report,
+            ApplyReport {
+                applied: 0,
+                skipped: 1
+            }
+        );
+        let list = items::get_items(&client, "1".to_string()).await.unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(commands_since(&client, "1", 0).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_command_ids_are_scoped_to_the_owner() {
+        let client = crate::database::test_client().await;
+        let batch = vec![command(
+            "c1",
+            Command::Create {
+                task: "milk".into(),
+            },
+        )];
+        apply_commands(&client, "1", &batch).await.unwrap();
+
+        let report = apply_commands(&client, "2", &batch).await.unwrap();
+        assert_eq!(report.applied, 1);
+        assert_eq!(
+            items::get_items(&client, "2".to_string())
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failing_log_append_rolls_back_the_change() {
+        let client = crate::database::test_client().await;
+        {
+            let db = crate::database::unlock_client(&client).await;
+            db.execute(
+                "CREATE TRIGGER reject_op_log BEFORE INSERT ON op_log \
+                 BEGIN SELECT RAISE(ABORT, 'op log unavailable'); END",
+                vec![],
+            )
+            .await
+            .unwrap();
+        }
+        let batch = vec![command(
+            "c1",
+            Command::Create {
+                task: "milk".into(),
+            },
+        )];
+
+        assert!(apply_commands(&client, "1", &batch).await.is_err());
+        assert!(
+            items::get_items(&client, "1".to_string())
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(commands_since(&client, "1", 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_server_writes_are_logged() {
+        let client = crate::database::test_client().await;
+        let item = items::create_item(&client, Item::new("1".into(), "eggs".into()))
+            .await
+            .unwrap();
+        items::toggle_item(&client, item.id(), "1".to_string())
+            .await
+            .unwrap();
+        items::delete_item(&client, item.id(), "1".to_string()).await;
+
+        let commands: Vec<Command> = commands_since(&client, "1", 0)
+            .await
+            .unwrap()
+            .iter()
+            .map(|entry| entry.command().unwrap())
+            .collect();
+        let target = ItemRef::Id(item.id());
+        assert_eq!(
+            commands,
+            vec![
+                Command::Create {
+                    task: "eggs".into()
+                },
+                Command::Complete {
+                    item: target.clone()
+                },
+                Command::Delete { item: target },
+            ]
+        );
+    }
+}
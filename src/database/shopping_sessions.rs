@@ -0,0 +1,246 @@
+use libsql_orm::{Filter, FilterOperator, Model, QueryBuilder, Sort};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::items::Item;
+
+pub type SessionId = i64;
+
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("shopping_sessions")]
+pub struct ShoppingSession {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ShoppingSession {
+    pub fn id(&self) -> SessionId {
+        self.id.unwrap()
+    }
+}
+
+async fn find_session(
+    db: &libsql_orm::Database,
+    owner_id: &str,
+    session_id: SessionId,
+) -> Result<ShoppingSession, String> {
+    match ShoppingSession::find_by_id(session_id, db).await {
+        Ok(Some(session)) if session.owner_id == owner_id => Ok(session),
+        Ok(_) => Err("Shopping session not found".to_string()),
+        Err(err) => {
+            log::error!("Error finding shopping session {session_id}: {err:?}");
+            Err("Database error".to_string())
+        }
+    }
+}
+
+/// Starts a shopping trip over the items currently on the owner's list
+///
+/// Items added later don't show up in [`session_items`], so a partner adding
+/// things mid-trip doesn't reshuffle the shopper's view.
+pub async fn begin_shopping_session(
+    client: &DBClient,
+    owner_id: String,
+) -> Result<SessionId, String> {
+    let session = ShoppingSession {
+        id: None,
+        owner_id: owner_id.clone(),
+        started_at: chrono::Utc::now(),
+    };
+    let db = super::unlock_client(client).await;
+    let res = super::with_savepoint(&db, "begin_shopping_session", async {
+        let session = session.create(&db).await.map_err(|err| {
+            log::error!("could not create shopping session: {err}");
+            "Could not start shopping".to_string()
+        })?;
+        let count = db
+            .execute(
+                "INSERT INTO shopping_session_items (session_id, item_id) \
+                 SELECT ?, id FROM items WHERE owner_id = ? AND deleted_at IS NULL",
+                vec![session.id().into(), owner_id.into()],
+            )
+            .await
+            .map_err(|err| {
+                log::error!(
+                    "could not snapshot items for session {}: {err}",
+                    session.id()
+                );
+                "Could not start shopping".to_string()
+            })?;
+        Ok((session.id(), count))
+    })
+    .await;
+    drop(db);
+
+    let (session_id, count) = res?;
+    log::info!("started shopping session {session_id} with {count} items");
+    Ok(session_id)
+}
+
+fn session_items_builder(owner_id: String, session_id: SessionId) -> QueryBuilder {
+    let frozen = QueryBuilder::new("shopping_session_items")
+        .select(vec!["item_id"])
+        .r#where(FilterOperator::Single(Filter::eq(
+            "session_id".to_string(),
+            session_id,
+        )));
+    QueryBuilder::new("items")
+        .r#where(FilterOperator::Single(Filter::eq(
+            "owner_id".to_string(),
+            owner_id,
+        )))
+        .where_in("id", frozen)
+        .order_by(Sort::asc("created_at"))
+}
+
+/// The items frozen by [`begin_shopping_session`], with their current state
+pub async fn session_items(
+    client: &DBClient,
+    owner_id: String,
+    session_id: SessionId,
+) -> Result<Vec<Item>, String> {
+    let db = super::unlock_client(client).await;
+    if let Err(err) = find_session(&db, &owner_id, session_id).await {
+        drop(db);
+        return Err(err);
+    }
+
+    let items = session_items_builder(owner_id, session_id)
+        .execute::<Item>(&db)
+        .await;
+    drop(db);
+
+    items.map_err(|err| {
+        log::error!("could not get items of shopping session {session_id}: {err}");
+        "Could not get items".to_string()
+    })
+}
+
+/// Ends a shopping trip and discards its snapshot
+pub async fn end_shopping_session(
+    client: &DBClient,
+    owner_id: String,
+    session_id: SessionId,
+) -> Result<(), String> {
+    let db = super::unlock_client(client).await;
+    let session = match find_session(&db, &owner_id, session_id).await {
+        Ok(session) => session,
+        Err(err) => {
+            drop(db);
+            return Err(err);
+        }
+    };
+
+    let res = super::with_savepoint(&db, "end_shopping_session", async {
+        db.execute(
+            "DELETE FROM shopping_session_items WHERE session_id = ?",
+            vec![session_id.into()],
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+        session.delete(&db).await.map_err(|err| err.to_string())
+    })
+    .await;
+    drop(db);
+
+    res.map(|_| log::info!("ended shopping session {session_id}"))
+        .map_err(|err| {
+            log::error!("could not end shopping session {session_id}: {err}");
+            "Could not end shopping".to_string()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::items::{create_item, toggle_item};
+
+    fn tasks(items: &[Item]) -> Vec<(&str, bool)> {
+        items
+            .iter()
+            .map(|i| (i.task.as_str(), i.completed()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_session_is_frozen_but_tracks_completion() {
+        let client = crate::database::test_client().await;
+        let milk = create_item(&client, Item::new("1".into(), "milk".into()))
+            .await
+            .unwrap();
+        create_item(&client, Item::new("1".into(), "eggs".into()))
+            .await
+            .unwrap();
+
+        let session = begin_shopping_session(&client, "1".to_string())
+            .await
+            .unwrap();
+        create_item(&client, Item::new("1".into(), "added by partner".into()))
+            .await
+            .unwrap();
+        toggle_item(&client, milk.id(), "1".to_string())
+            .await
+            .unwrap();
+
+        let items = session_items(&client, "1".to_string(), session)
+            .await
+            .unwrap();
+        assert_eq!(tasks(&items), vec![("milk", true), ("eggs", false)]);
+        assert!(
+            session_items(&client, "2".to_string(), session)
+                .await
+                .is_err()
+        );
+
+        end_shopping_session(&client, "1".to_string(), session)
+            .await
+            .unwrap();
+        assert!(
+            session_items(&client, "1".to_string(), session)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_session_items_query_binds_the_session() {
+        let builder = session_items_builder("1".to_string(), 7);
+        assert!(builder.validate_no_raw().is_ok());
+        let (sql, params) = builder.build().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items WHERE owner_id = ? AND id IN \
+             (SELECT item_id FROM shopping_session_items WHERE session_id = ?) \
+             ORDER BY created_at ASC"
+        );
+        assert_eq!(params.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failing_snapshot_leaves_no_session() {
+        let client = crate::database::test_client().await;
+        create_item(&client, Item::new("1".into(), "milk".into()))
+            .await
+            .unwrap();
+        {
+            let db = crate::database::unlock_client(&client).await;
+            db.execute(
+                "CREATE TRIGGER reject_snapshot BEFORE INSERT ON shopping_session_items \
+                 BEGIN SELECT RAISE(ABORT, 'snapshot unavailable'); END",
+                vec![],
+            )
+            .await
+            .unwrap();
+        }
+
+        assert!(
+            begin_shopping_session(&client, "1".to_string())
+                .await
+                .is_err()
+        );
+        let db = crate::database::unlock_client(&client).await;
+        let sessions = ShoppingSession::find_all(&db).await.unwrap();
+        assert!(sessions.is_empty());
+    }
+}
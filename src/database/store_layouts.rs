@@ -0,0 +1,245 @@
+use libsql_orm::{Filter, FilterOperator, JoinType, Model, QueryBuilder, Sort};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::items::Item;
+
+/// Position of a category in the aisle order of one store
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("store_layouts")]
+pub struct StoreLayout {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub store_id: String,
+    pub category: String,
+    pub position: i64,
+}
+
+fn layout_of(owner_id: &str, store_id: &str) -> FilterOperator {
+    FilterOperator::And(vec![
+        FilterOperator::Single(Filter::eq("owner_id".to_string(), owner_id.to_string())),
+        FilterOperator::Single(Filter::eq("store_id".to_string(), store_id.to_string())),
+    ])
+}
+
+fn category_key(category: &str) -> String {
+    category.trim().to_lowercase()
+}
+
+/// Replaces the layout of a store, `categories` are given in walking order
+pub async fn set_store_layout(
+    client: &DBClient,
+    owner_id: String,
+    store_id: String,
+    categories: Vec<String>,
+) -> Result<(), String> {
+    let layout: Vec<StoreLayout> = categories
+        .into_iter()
+        .enumerate()
+        .map(|(position, category)| StoreLayout {
+            id: None,
+            owner_id: owner_id.clone(),
+            store_id: store_id.clone(),
+            category: category_key(&category),
+            position: position as i64,
+        })
+        .collect();
+
+    let db = super::unlock_client(client).await;
+    let res = super::with_savepoint(&db, "set_store_layout", async {
+        StoreLayout::delete_where(layout_of(&owner_id, &store_id), &db)
+            .await
+            .map_err(|err| format!("could not clear layout: {err}"))?;
+        // bulk_create opens its own transaction, which can't nest in the savepoint
+        for entry in &layout {
+            entry
+                .create(&db)
+                .await
+                .map_err(|err| format!("could not insert layout: {err}"))?;
+        }
+        Ok(())
+    })
+    .await;
+    drop(db);
+
+    match res {
+        Ok(()) => {
+            log::info!("stored layout of store {store_id} for {owner_id}");
+            Ok(())
+        }
+        Err(err) => {
+            log::error!("could not store layout of store {store_id}: {err}");
+            Err("Could not update store layout".to_string())
+        }
+    }
+}
+
+/// Items of an owner in the aisle order of a store
+///
+/// Items whose category is missing or not part of the layout come last, in
+/// their stored order.
+pub async fn order_by_store_layout(
+    client: &DBClient,
+    owner_id: String,
+    store_id: String,
+) -> Result<Vec<Item>, String> {
+    let db = super::unlock_client(client).await;
+    // Layout categories are stored through category_key, trimmed and lowercased
+    let items = QueryBuilder::new("items")
+        .select(vec!["items.*"])
+        .join(
+            JoinType::Left,
+            "store_layouts",
+            "store_layouts.owner_id = items.owner_id \
+             AND store_layouts.store_id = :store \
+             AND store_layouts.category = lower(trim(items.category))",
+        )
+        .r#where(FilterOperator::Single(Filter::eq(
+            "items.owner_id".to_string(),
+            owner_id,
+        )))
+        .bind("store", store_id.clone().into())
+        .order_by(Sort::asc("store_layouts.position").nulls_last())
+        .order_by(Sort::asc("items.id"))
+        .execute::<Item>(&db)
+        .await;
+    drop(db);
+
+    items.map_err(|err| {
+        log::error!("could not order items by layout of store {store_id}: {err}");
+        "Could not get store layout".to_string()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categorized(task: &str, category: Option<&str>) -> Item {
+        let mut item = Item::new("1".to_string(), task.to_string());
+        item.category = category.map(str::to_string);
+        item
+    }
+
+    #[tokio::test]
+    async fn test_items_follow_aisle_order() {
+        let client = crate::database::test_client().await;
+        crate::database::items::create_items(
+            &client,
+            vec![
+                categorized("batteries", None),
+                categorized("milk", Some("Dairy")),
+                categorized("soap", Some("household")),
+                categorized("apples", Some("produce")),
+                categorized("bread", Some("bakery")),
+            ],
+        )
+        .await
+        .unwrap();
+
+        set_store_layout(
+            &client,
+            "1".to_string(),
+            "corner-shop".to_string(),
+            vec![
+                "produce".to_string(),
+                "bakery".to_string(),
+                "dairy".to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+
+        let items = order_by_store_layout(&client, "1".to_string(), "corner-shop".to_string())
+            .await
+            .unwrap();
+        let tasks: Vec<&str> = items.iter().map(|i| i.task.as_str()).collect();
+        assert_eq!(tasks, vec!["apples", "bread", "milk", "batteries", "soap"]);
+    }
+
+    #[tokio::test]
+    async fn test_layouts_are_per_store() {
+        let client = crate::database::test_client().await;
+        crate::database::items::create_items(
+            &client,
+            vec![
+                categorized("milk", Some("dairy")),
+                categorized("apples", Some("produce")),
+            ],
+        )
+        .await
+        .unwrap();
+        set_store_layout(
+            &client,
+            "1".to_string(),
+            "market".to_string(),
+            vec!["dairy".to_string(), "produce".to_string()],
+        )
+        .await
+        .unwrap();
+        set_store_layout(
+            &client,
+            "1".to_string(),
+            "supermarket".to_string(),
+            vec!["produce".to_string(), "dairy".to_string()],
+        )
+        .await
+        .unwrap();
+
+        let items = order_by_store_layout(&client, "1".to_string(), "market".to_string())
+            .await
+            .unwrap();
+        assert_eq!(items[0].task, "milk");
+        let items = order_by_store_layout(&client, "1".to_string(), "supermarket".to_string())
+            .await
+            .unwrap();
+        assert_eq!(items[0].task, "apples");
+    }
+
+    #[tokio::test]
+    async fn test_failed_replace_keeps_old_layout() {
+        let client = crate::database::test_client().await;
+        crate::database::items::create_items(
+            &client,
+            vec![
+                categorized("milk", Some("dairy")),
+                categorized("apples", Some("produce")),
+            ],
+        )
+        .await
+        .unwrap();
+        set_store_layout(
+            &client,
+            "1".to_string(),
+            "market".to_string(),
+            vec!["produce".to_string(), "dairy".to_string()],
+        )
+        .await
+        .unwrap();
+        {
+            let db = crate::database::unlock_client(&client).await;
+            db.execute(
+                "CREATE TRIGGER reject_layout BEFORE INSERT ON store_layouts \
+                 BEGIN SELECT RAISE(ABORT, 'layout unavailable'); END",
+                vec![],
+            )
+            .await
+            .unwrap();
+        }
+
+        assert!(
+            set_store_layout(
+                &client,
+                "1".to_string(),
+                "market".to_string(),
+                vec!["dairy".to_string(), "produce".to_string()],
+            )
+            .await
+            .is_err()
+        );
+        let items = order_by_store_layout(&client, "1".to_string(), "market".to_string())
+            .await
+            .unwrap();
+        assert_eq!(items[0].task, "apples");
+    }
+}
@@ -0,0 +1,324 @@
+use libsql_orm::{Filter, FilterOperator, Model, QueryBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::items::Item;
+
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("tags")]
+pub struct Tag {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub name: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Tag {
+    pub fn id(&self) -> i64 {
+        self.id.unwrap()
+    }
+}
+
+/// How several tags are combined when filtering items
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    /// Items carrying every tag
+    All,
+    /// Items carrying at least one of the tags
+    #[default]
+    Any,
+}
+
+fn tag_name(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+async fn find_tags(
+    db: &libsql_orm::Database,
+    owner_id: &str,
+    names: Vec<String>,
+) -> libsql_orm::Result<Vec<Tag>> {
+    Tag::find_where(
+        FilterOperator::And(vec![
+            FilterOperator::Single(Filter::eq("owner_id".to_string(), owner_id.to_string())),
+            FilterOperator::Single(Filter::in_values("name".to_string(), names)),
+        ]),
+        db,
+    )
+    .await
+}
+
+/// Tags an item, creating the tag on first use
+pub async fn add_tag(
+    client: &DBClient,
+    owner_id: String,
+    item_id: i64,
+    name: &str,
+) -> Result<Tag, String> {
+    let name = tag_name(name);
+    if name.is_empty() {
+        return Err("Tag name must not be empty".to_string());
+    }
+    super::items::get_item(client, item_id, owner_id.clone()).await?;
+
+    let db = super::unlock_client(client).await;
+    if let Err(err) = db
+        .execute(
+            "INSERT OR IGNORE INTO tags (owner_id, name, created_at) VALUES (?, ?, ?)",
+            vec![
+                owner_id.clone().into(),
+                name.clone().into(),
+                chrono::Utc::now().to_rfc3339().into(),
+            ],
+        )
+        .await
+    {
+        drop(db);
+        log::error!("could not create tag {name}: {err}");
+        return Err("Could not create tag".to_string());
+    }
+
+    let tag = match find_tags(&db, &owner_id, vec![name.clone()]).await {
+        Ok(tags) if !tags.is_empty() => tags[0].clone(),
+        Ok(_) => {
+            drop(db);
+            return Err("Tag not found".to_string());
+        }
+        Err(err) => {
+            drop(db);
+            log::error!("could not find tag {name}: {err}");
+            return Err("Database error".to_string());
+        }
+    };
+
+    let res = db
+        .execute(
+            "INSERT OR IGNORE INTO item_tags (item_id, tag_id) VALUES (?, ?)",
+            vec![item_id.into(), tag.id().into()],
+        )
+        .await;
+    drop(db);
+
+    match res {
+        Ok(_) => {
+            log::info!("tagged item {item_id} with {name}");
+            Ok(tag)
+        }
+        Err(err) => {
+            log::error!("could not tag item {item_id}: {err}");
+            Err("Could not tag item".to_string())
+        }
+    }
+}
+
+/// Removes a tag from an item, the tag itself is kept for other items
+pub async fn remove_tag(
+    client: &DBClient,
+    owner_id: String,
+    item_id: i64,
+    name: &str,
+) -> Result<(), String> {
+    let db = super::unlock_client(client).await;
+    let res = db
+        .execute(
+            "DELETE FROM item_tags WHERE item_id = ? AND tag_id IN \
+             (SELECT id FROM tags WHERE owner_id = ? AND name = ?)",
+            vec![item_id.into(), owner_id.into(), tag_name(name).into()],
+        )
+        .await;
+    drop(db);
+
+    match res {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            log::error!("could not remove tag from item {item_id}: {err}");
+            Err("Could not remove tag".to_string())
+        }
+    }
+}
+
+/// Items of `owner_id` tagged with `tag`
+pub async fn items_with_tag(
+    client: &DBClient,
+    owner_id: String,
+    tag: &str,
+) -> Result<Vec<Item>, String> {
+    items_with_tags(client, owner_id, &[tag], TagMatch::Any).await
+}
+
+/// Items of `owner_id` carrying all or any of `tags`
+pub async fn items_with_tags(
+    client: &DBClient,
+    owner_id: String,
+    tags: &[&str],
+    matching: TagMatch,
+) -> Result<Vec<Item>, String> {
+    let mut names: Vec<String> = tags.iter().map(|t| tag_name(t)).collect();
+    // A name given twice is still one tag
+    names.sort();
+    names.dedup();
+    let db = super::unlock_client(client).await;
+    let tag_ids: Vec<i64> = match find_tags(&db, &owner_id, names.clone()).await {
+        Ok(found) => found.iter().map(Tag::id).collect(),
+        Err(err) => {
+            drop(db);
+            log::error!("could not find tags: {err}");
+            return Err("Database error".to_string());
+        }
+    };
+    let unknown_tag = tag_ids.len() < names.len();
+    if tag_ids.is_empty() || (matching == TagMatch::All && unknown_tag) {
+        drop(db);
+        return Ok(Vec::new());
+    }
+
+    let items = tagged_items_builder(&owner_id, &tag_ids, matching)
+        .execute::<Item>(&db)
+        .await;
+    drop(db);
+
+    items.map_err(|err| {
+        log::error!("could not get tagged items: {err}");
+        "Could not get items".to_string()
+    })
+}
+
+/// Items of `owner_id` tagged with any or each of `tag_ids`, which are bound
+fn tagged_items_builder(owner_id: &str, tag_ids: &[i64], matching: TagMatch) -> QueryBuilder {
+    let tagged_with = |ids: &[i64]| {
+        QueryBuilder::new("item_tags")
+            .select(vec!["item_id"])
+            .r#where(FilterOperator::Single(Filter::in_values(
+                "tag_id",
+                ids.to_vec(),
+            )))
+    };
+    let builder = QueryBuilder::new("items").r#where(FilterOperator::Single(Filter::eq(
+        "owner_id".to_string(),
+        owner_id.to_string(),
+    )));
+    match matching {
+        TagMatch::Any => builder.where_in("id", tagged_with(tag_ids)),
+        TagMatch::All => tag_ids.iter().fold(builder, |builder, id| {
+            builder.where_in("id", tagged_with(std::slice::from_ref(id)))
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn tagged_item(client: &DBClient, task: &str, tags: &[&str]) -> Item {
+        let item =
+            super::super::items::create_item(client, Item::new("1".to_string(), task.to_string()))
+                .await
+                .unwrap();
+        for tag in tags {
+            add_tag(client, "1".to_string(), item.id(), tag)
+                .await
+                .unwrap();
+        }
+        item
+    }
+
+    fn tasks(items: &[Item]) -> Vec<&str> {
+        let mut tasks: Vec<&str> = items.iter().map(|i| i.task.as_str()).collect();
+        tasks.sort();
+        tasks
+    }
+
+    #[tokio::test]
+    async fn test_tag_and_untag_item() {
+        let client = crate::database::test_client().await;
+        let item = tagged_item(&client, "apples", &["Organic", "organic "]).await;
+
+        let tagged = items_with_tag(&client, "1".to_string(), "ORGANIC")
+            .await
+            .unwrap();
+        assert_eq!(tasks(&tagged), vec!["apples"]);
+        assert!(
+            items_with_tag(&client, "2".to_string(), "organic")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            add_tag(&client, "2".to_string(), item.id(), "sale")
+                .await
+                .is_err()
+        );
+
+        remove_tag(&client, "1".to_string(), item.id(), "organic")
+            .await
+            .unwrap();
+        assert!(
+            items_with_tag(&client, "1".to_string(), "organic")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_two_tags_all_and_any() {
+        let client = crate::database::test_client().await;
+        tagged_item(&client, "apples", &["organic", "sale"]).await;
+        tagged_item(&client, "bananas", &["organic"]).await;
+        tagged_item(&client, "coffee", &["sale"]).await;
+        tagged_item(&client, "bread", &[]).await;
+
+        let all = items_with_tags(
+            &client,
+            "1".to_string(),
+            &["organic", "sale"],
+            TagMatch::All,
+        )
+        .await
+        .unwrap();
+        assert_eq!(tasks(&all), vec!["apples"]);
+
+        let any = items_with_tags(
+            &client,
+            "1".to_string(),
+            &["organic", "sale"],
+            TagMatch::Any,
+        )
+        .await
+        .unwrap();
+        assert_eq!(tasks(&any), vec!["apples", "bananas", "coffee"]);
+
+        let unknown = items_with_tags(&client, "1".to_string(), &["sale", "vegan"], TagMatch::All)
+            .await
+            .unwrap();
+        assert!(unknown.is_empty());
+
+        let repeated = items_with_tags(
+            &client,
+            "1".to_string(),
+            &["organic", "sale", "organic", "Sale"],
+            TagMatch::All,
+        )
+        .await
+        .unwrap();
+        assert_eq!(tasks(&repeated), vec!["apples"]);
+    }
+
+    #[test]
+    fn test_tag_ids_are_bound() {
+        let (sql, params) = tagged_items_builder("1", &[4, 9], TagMatch::All)
+            .build()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM items WHERE owner_id = ? \
+             AND id IN (SELECT item_id FROM item_tags WHERE tag_id IN (?)) \
+             AND id IN (SELECT item_id FROM item_tags WHERE tag_id IN (?))"
+        );
+        assert_eq!(
+            format!("{params:?}"),
+            r#"[Text("1"), Integer(4), Integer(9)]"#
+        );
+    }
+}
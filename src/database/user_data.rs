@@ -0,0 +1,318 @@
+use libsql_orm::{Database, Filter, FilterOperator, JoinType, Model, QueryBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::attachments::Attachment;
+use crate::database::item_events::ItemEvent;
+use crate::database::items::Item;
+use crate::database::messages::ChatMessage;
+use crate::database::op_log::OpLogEntry;
+use crate::database::recipes::Recipe;
+use crate::database::shopping_sessions::ShoppingSession;
+use crate::database::store_layouts::StoreLayout;
+use crate::database::tags::Tag;
+
+/// Everything stored for one owner, for data-portability requests
+///
+/// Soft-deleted items are included, they are still stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserBundle {
+    pub owner_id: String,
+    pub items: Vec<Item>,
+    pub events: Vec<ItemEvent>,
+    pub tags: Vec<Tag>,
+    pub item_tags: Vec<ItemTagRef>,
+    pub attachments: Vec<Attachment>,
+    pub store_layouts: Vec<StoreLayout>,
+    pub shopping_sessions: Vec<ShoppingSession>,
+    pub shopping_session_items: Vec<SessionItemRef>,
+    pub messages: Vec<ChatMessage>,
+    pub recipes: Vec<Recipe>,
+    pub webhooks: Vec<WebhookRef>,
+    pub op_log: Vec<OpLogEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemTagRef {
+    pub item_id: i64,
+    pub tag_id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionItemRef {
+    pub session_id: i64,
+    pub item_id: i64,
+}
+
+/// A registered webhook without its signing secret
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookRef {
+    pub url: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Rows removed by [`delete_user_data`], per table
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct DeletionReport {
+    pub items: u64,
+    pub item_events: u64,
+    pub tags: u64,
+    pub item_tags: u64,
+    pub attachments: u64,
+    pub store_layouts: u64,
+    pub shopping_sessions: u64,
+    pub shopping_session_items: u64,
+    pub messages: u64,
+    pub recipes: u64,
+    pub webhooks: u64,
+    pub op_log: u64,
+}
+
+impl DeletionReport {
+    pub fn total(&self) -> u64 {
+        self.items
+            + self.item_events
+            + self.tags
+            + self.item_tags
+            + self.attachments
+            + self.store_layouts
+            + self.shopping_sessions
+            + self.shopping_session_items
+            + self.messages
+            + self.recipes
+            + self.webhooks
+            + self.op_log
+    }
+}
+
+fn owned_by(owner_id: &str) -> FilterOperator {
+    FilterOperator::Single(Filter::eq("owner_id".to_string(), owner_id.to_string()))
+}
+
+/// Collects every row stored for `owner_id`
+pub async fn export_user_bundle(client: &DBClient, owner_id: &str) -> Result<UserBundle, String> {
+    let db = super::unlock_client(client).await;
+    let res = collect_bundle(&db, owner_id).await;
+    drop(db);
+
+    res.map_err(|err| {
+        log::error!("could not export data of {owner_id}: {err}");
+        "Could not export user data".to_string()
+    })
+}
+
+async fn collect_bundle(db: &Database, owner_id: &str) -> libsql_orm::Result<UserBundle> {
+    let item_tags = QueryBuilder::new("item_tags")
+        .select(vec!["item_tags.item_id", "item_tags.tag_id"])
+        .join(JoinType::Inner, "tags", "tags.id = item_tags.tag_id")
+        .r#where(FilterOperator::Single(Filter::eq(
+            "tags.owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .execute(db)
+        .await?;
+    let shopping_session_items = QueryBuilder::new("shopping_session_items")
+        .select(vec![
+            "shopping_session_items.session_id",
+            "shopping_session_items.item_id",
+        ])
+        .join(
+            JoinType::Inner,
+            "shopping_sessions",
+            "shopping_sessions.id = shopping_session_items.session_id",
+        )
+        .r#where(FilterOperator::Single(Filter::eq(
+            "shopping_sessions.owner_id".to_string(),
+            owner_id.to_string(),
+        )))
+        .execute(db)
+        .await?;
+    let webhooks = QueryBuilder::new("webhooks")
+        .select(vec!["url", "created_at"])
+        .r#where(owned_by(owner_id))
+        .execute(db)
+        .await?;
+
+    Ok(UserBundle {
+        owner_id: owner_id.to_string(),
+        items: Item::find_where(owned_by(owner_id), db).await?,
+        events: ItemEvent::find_where(owned_by(owner_id), db).await?,
+        tags: Tag::find_where(owned_by(owner_id), db).await?,
+        item_tags,
+        attachments: Attachment::find_where(owned_by(owner_id), db).await?,
+        store_layouts: StoreLayout::find_where(owned_by(owner_id), db).await?,
+        shopping_sessions: ShoppingSession::find_where(owned_by(owner_id), db).await?,
+        shopping_session_items,
+        messages: ChatMessage::find_where(owned_by(owner_id), db).await?,
+        recipes: Recipe::find_where(owned_by(owner_id), db).await?,
+        webhooks,
+        op_log: OpLogEntry::find_where(owned_by(owner_id), db).await?,
+    })
+}
+
+/// Join rows go first, they are found through the parents deleted after them
+const DELETE_SHOPPING_SESSION_ITEMS: &str = "DELETE FROM shopping_session_items \
+    WHERE session_id IN (SELECT id FROM shopping_sessions WHERE owner_id = ?)";
+const DELETE_ITEM_TAGS: &str = "DELETE FROM item_tags \
+    WHERE tag_id IN (SELECT id FROM tags WHERE owner_id = ?) \
+    OR item_id IN (SELECT id FROM items WHERE owner_id = ?)";
+
+/// Removes every row stored for `owner_id`, in all tables
+///
+/// Runs in a savepoint, so either everything is gone or nothing is. No item
+/// events or webhooks are emitted for the removed items.
+pub async fn delete_user_data(client: &DBClient, owner_id: &str) -> Result<DeletionReport, String> {
+    let db = super::unlock_client(client).await;
+    let res = super::with_savepoint(&db, "delete_user_data", delete_rows(&db, owner_id)).await;
+    drop(db);
+
+    match res {
+        Ok(report) => {
+            log::info!("deleted data of {owner_id}: {report:?}");
+            Ok(report)
+        }
+        Err(err) => {
+            log::error!("could not delete data of {owner_id}: {err}");
+            Err("Could not delete user data".to_string())
+        }
+    }
+}
+
+async fn delete_rows(db: &Database, owner_id: &str) -> Result<DeletionReport, String> {
+    let delete = |sql: String, binds: usize| async move {
+        let params = (0..binds).map(|_| owner_id.to_string().into()).collect();
+        db.execute(&sql, params)
+            .await
+            .map_err(|err| err.to_string())
+    };
+    let shopping_session_items = delete(DELETE_SHOPPING_SESSION_ITEMS.to_string(), 1).await?;
+    let item_tags = delete(DELETE_ITEM_TAGS.to_string(), 2).await?;
+    let mut report = DeletionReport {
+        shopping_session_items,
+        item_tags,
+        ..DeletionReport::default()
+    };
+    for (table, count) in [
+        ("shopping_sessions", &mut report.shopping_sessions),
+        ("tags", &mut report.tags),
+        ("attachments", &mut report.attachments),
+        ("item_events", &mut report.item_events),
+        ("store_layouts", &mut report.store_layouts),
+        ("messages", &mut report.messages),
+        ("recipes", &mut report.recipes),
+        ("webhooks", &mut report.webhooks),
+        ("op_log", &mut report.op_log),
+        ("items", &mut report.items),
+    ] {
+        *count = delete(format!("DELETE FROM {table} WHERE owner_id = ?"), 1).await?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{attachments, items, shopping_sessions, store_layouts, tags};
+
+    async fn seed(client: &DBClient, owner_id: &str) -> Vec<Item> {
+        let mut created = Vec::new();
+        for task in ["milk", "eggs"] {
+            let item = items::create_item(client, Item::new(owner_id.into(), task.into()))
+                .await
+                .unwrap();
+            tags::add_tag(client, owner_id.to_string(), item.id(), "dairy")
+                .await
+                .unwrap();
+            created.push(item);
+        }
+        attachments::add_attachment(
+            client,
+            owner_id.to_string(),
+            created[0].id(),
+            "https://example.com/milk.png".to_string(),
+            "image/png".to_string(),
+        )
+        .await
+        .unwrap();
+        store_layouts::set_store_layout(
+            client,
+            owner_id.to_string(),
+            "corner".to_string(),
+            vec!["dairy".to_string()],
+        )
+        .await
+        .unwrap();
+        shopping_sessions::begin_shopping_session(client, owner_id.to_string())
+            .await
+            .unwrap();
+        created
+    }
+
+    #[tokio::test]
+    async fn test_export_captures_item_rows() {
+        let client = crate::database::test_client().await;
+        let created = seed(&client, "1").await;
+        seed(&client, "2").await;
+
+        let bundle = export_user_bundle(&client, "1").await.unwrap();
+        let mut ids: Vec<i64> = bundle.items.iter().map(Item::id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![created[0].id(), created[1].id()]);
+        assert_eq!(bundle.events.len(), 2);
+        assert_eq!(bundle.tags.len(), 1);
+        assert_eq!(bundle.item_tags.len(), 2);
+        assert!(
+            bundle
+                .item_tags
+                .iter()
+                .all(|t| t.tag_id == bundle.tags[0].id())
+        );
+        assert_eq!(bundle.attachments.len(), 1);
+        assert_eq!(bundle.store_layouts.len(), 1);
+        assert_eq!(bundle.shopping_sessions.len(), 1);
+        assert_eq!(bundle.shopping_session_items.len(), 2);
+        assert_eq!(bundle.op_log.len(), 2);
+        assert!(serde_json::to_string(&bundle).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_leaves_no_rows_for_owner() {
+        let client = crate::database::test_client().await;
+        seed(&client, "1").await;
+        seed(&client, "2").await;
+
+        let report = delete_user_data(&client, "1").await.unwrap();
+        assert_eq!(
+            report,
+            DeletionReport {
+                items: 2,
+                item_events: 2,
+                tags: 1,
+                item_tags: 2,
+                attachments: 1,
+                store_layouts: 1,
+                shopping_sessions: 1,
+                shopping_session_items: 2,
+                op_log: 2,
+                ..DeletionReport::default()
+            }
+        );
+
+        let bundle = export_user_bundle(&client, "1").await.unwrap();
+        assert!(bundle.items.is_empty());
+        assert!(bundle.events.is_empty());
+        assert!(bundle.tags.is_empty());
+        assert!(bundle.item_tags.is_empty());
+        assert!(bundle.attachments.is_empty());
+        assert!(bundle.store_layouts.is_empty());
+        assert!(bundle.shopping_sessions.is_empty());
+        assert!(bundle.shopping_session_items.is_empty());
+        assert!(bundle.op_log.is_empty());
+
+        let other = export_user_bundle(&client, "2").await.unwrap();
+        assert_eq!(other.items.len(), 2);
+        assert_eq!(other.item_tags.len(), 2);
+        assert_eq!(other.shopping_session_items.len(), 2);
+        assert_eq!(delete_user_data(&client, "1").await.unwrap().total(), 0);
+    }
+}
@@ -0,0 +1,98 @@
+use libsql_orm::{Database, Filter, FilterOperator, Model};
+use serde::{Deserialize, Serialize};
+
+use crate::database::DBClient;
+use crate::database::item_events::ItemEventKind;
+use crate::database::items::Item;
+use crate::webhooks::{self as dispatcher, WebhookPayload};
+
+#[derive(Model, Debug, Clone, Serialize, Deserialize)]
+#[table_name("webhooks")]
+pub struct Webhook {
+    pub id: std::option::Option<i64>,
+    pub owner_id: String,
+    pub url: String,
+    /// Key of the HMAC signature sent with every delivery
+    pub secret: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Webhook {
+    pub fn new(owner_id: String, url: String, secret: String) -> Self {
+        Webhook {
+            id: None,
+            owner_id,
+            url,
+            secret,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn id(&self) -> i64 {
+        self.id.unwrap()
+    }
+}
+
+fn owned_by(owner_id: &str) -> FilterOperator {
+    FilterOperator::Single(Filter::eq("owner_id".to_string(), owner_id.to_string()))
+}
+
+pub async fn register_webhook(
+    client: &DBClient,
+    owner_id: String,
+    url: String,
+    secret: String,
+) -> Result<Webhook, String> {
+    dispatcher::resolve_target(&url, dispatcher::TARGETS).await?;
+    let db = super::unlock_client(client).await;
+    let res = Webhook::new(owner_id, url, secret).create(&db).await;
+    drop(db);
+
+    match res {
+        Ok(created) => {
+            log::info!("registered webhook {}", created.id());
+            Ok(created)
+        }
+        Err(err) => {
+            log::error!("could not register webhook: {err}");
+            Err("Could not register webhook".to_string())
+        }
+    }
+}
+
+pub async fn list_webhooks(client: &DBClient, owner_id: String) -> Result<Vec<Webhook>, String> {
+    let db = super::unlock_client(client).await;
+    let webhooks = Webhook::find_where(owned_by(&owner_id), &db).await;
+    drop(db);
+
+    webhooks.map_err(|err| {
+        log::error!("could not list webhooks of {owner_id}: {err}");
+        "Could not get webhooks".to_string()
+    })
+}
+
+/// Notifies the owner's webhooks of an item change, without waiting for delivery
+pub(super) async fn dispatch(db: &Database, item: &Item, kind: ItemEventKind) {
+    if !matches!(
+        kind,
+        ItemEventKind::Created | ItemEventKind::Completed | ItemEventKind::Deleted
+    ) {
+        return;
+    }
+    let webhooks = match Webhook::find_where(owned_by(&item.owner_id), db).await {
+        Ok(webhooks) => webhooks,
+        Err(err) => {
+            log::error!("could not load webhooks of {}: {err}", item.owner_id);
+            return;
+        }
+    };
+    for webhook in webhooks {
+        dispatcher::spawn_delivery(
+            webhook,
+            WebhookPayload {
+                event: kind.as_str(),
+                item: item.clone(),
+            },
+        );
+    }
+}
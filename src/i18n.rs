@@ -0,0 +1,106 @@
+/// Language of the texts shown to a user
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+/// A text shown to the user, with the values it mentions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Text<'a> {
+    ItemsAdded(usize),
+    AlreadyOnList(usize),
+    ItemsRejected(usize),
+    NeedsReview(usize),
+    LooksLike { task: &'a str, existing: &'a str },
+    ChunksProcessed { done: usize, total: usize },
+}
+
+/// The primary subtag of a language tag like `de-AT` or of the first language
+/// of an `Accept-Language` value, lowercase
+pub fn primary_language(language: &str) -> String {
+    language
+        .split(',')
+        .next()
+        .and_then(|tag| tag.split(';').next())
+        .and_then(|tag| tag.trim().split(['-', '_']).next())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+impl Language {
+    /// Language of a tag or `Accept-Language` value, English for languages
+    /// without translations
+    pub fn from_language(language: &str) -> Self {
+        match primary_language(language).as_str() {
+            "de" => Language::German,
+            _ => Language::English,
+        }
+    }
+
+    pub fn text(self, text: Text) -> String {
+        match self {
+            Language::English => english(text),
+            Language::German => german(text),
+        }
+    }
+
+    /// Joins the parts of a summary into one sentence
+    pub fn sentence(self, parts: &[String]) -> String {
+        format!("{}.", parts.join(", "))
+    }
+}
+
+fn english(text: Text) -> String {
+    let items = |n: usize| {
+        if n == 1 {
+            "1 item".to_string()
+        } else {
+            format!("{n} items")
+        }
+    };
+    match text {
+        Text::ItemsAdded(n) => format!("Added {}", items(n)),
+        Text::AlreadyOnList(1) => "1 was already on your list".to_string(),
+        Text::AlreadyOnList(n) => format!("{n} were already on your list"),
+        Text::ItemsRejected(n) => format!("{} could not be used", items(n)),
+        Text::NeedsReview(n) => format!("{n} should be reviewed"),
+        Text::LooksLike { task, existing } => {
+            format!("\"{task}\" looks like \"{existing}\" on your list")
+        }
+        Text::ChunksProcessed { done, total } => format!("{done} of {total} processed"),
+    }
+}
+
+fn german(text: Text) -> String {
+    match text {
+        Text::ItemsAdded(n) => format!("{n} Artikel hinzugefügt"),
+        Text::AlreadyOnList(1) => "1 war schon auf deiner Liste".to_string(),
+        Text::AlreadyOnList(n) => format!("{n} waren schon auf deiner Liste"),
+        Text::ItemsRejected(1) => "1 Artikel konnte nicht verwendet werden".to_string(),
+        Text::ItemsRejected(n) => format!("{n} Artikel konnten nicht verwendet werden"),
+        Text::NeedsReview(1) => "1 sollte geprüft werden".to_string(),
+        Text::NeedsReview(n) => format!("{n} sollten geprüft werden"),
+        Text::LooksLike { task, existing } => {
+            format!("\"{task}\" sieht aus wie \"{existing}\" auf deiner Liste")
+        }
+        Text::ChunksProcessed { done, total } => format!("{done} von {total} verarbeitet"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_from_accept_language() {
+        assert_eq!(
+            Language::from_language("de-AT,de;q=0.9,en;q=0.8"),
+            Language::German
+        );
+        assert_eq!(Language::from_language("en-US"), Language::English);
+        assert_eq!(Language::from_language("fr"), Language::English);
+        assert_eq!(Language::from_language(""), Language::English);
+    }
+}
@@ -1,144 +1,1612 @@
+use futures_util::{Stream, stream};
 use log::info;
+use regex::Regex;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
 
-use crate::database::{self, DBClient, items::Item};
+use crate::content_filter::ContentFilter;
+use crate::database::items::{Item, rank_similar};
+use crate::database::{self, DBClient};
+use crate::i18n::{Language, Text};
+use crate::llm_provider::LlmProvider;
+use crate::normalize::{TextNormalization, normalize_text};
+use crate::quantity::{self, NumberLocale};
+use crate::similarity;
 
 #[derive(Debug)]
 pub enum LlmError {
     Request(String),
     Auth(String),
     Parse(String),
+    Filtered(String),
 }
 
-#[derive(Debug, Serialize)]
-pub struct Prompt {
-    prompt: String,
+/// Deployment settings of the LLM flows
+#[derive(Debug, Clone)]
+pub struct LlmSettings {
+    /// Items with a confidence below this are flagged for review
+    pub review_threshold: f32,
+    /// Screens user messages and model output
+    pub content_filter: ContentFilter,
+    /// Picks the model per request, without one the API's default model is used
+    pub model_policy: Option<ModelPolicy>,
+    /// Model for this call regardless of the policy
+    pub model_override: Option<String>,
+    /// How quantities in extracted items are written, from the request's language
+    pub number_locale: NumberLocale,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct TaskList {
-    list: Vec<String>,
+impl LlmSettings {
+    /// Same settings, but the next calls use `model` whatever the input
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model_override = Some(model.into());
+        self
+    }
+
+    /// Same settings, parsing quantities as written in `locale`
+    pub fn with_number_locale(mut self, locale: NumberLocale) -> Self {
+        self.number_locale = locale;
+        self
+    }
+
+    fn model_for(&self, task: LlmTask, user_message: &str) -> Option<String> {
+        self.model_override.clone().or_else(|| {
+            self.model_policy
+                .as_ref()
+                .map(|policy| policy.select(task, user_message).to_string())
+        })
+    }
+}
+
+/// Which flow a request belongs to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LlmTask {
+    Items,
+    Chat,
+}
+
+/// Sends simple item extractions to a cheap model and everything else, i.e.
+/// chat, long inputs and recipes, to a stronger one
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelPolicy {
+    pub cheap_model: String,
+    pub strong_model: String,
+    /// Item inputs longer than this many characters count as complex
+    pub long_input_chars: usize,
+}
+
+lazy_static::lazy_static! {
+    static ref RECIPE_HINT_REGEX: Regex = Regex::new(
+        r"(?i)\b(?:recipe|ingredients|preheat|bake|simmer|tbsp|tsp|tablespoons?|teaspoons?|rezept|zutaten)\b"
+    )
+    .unwrap();
+}
+
+/// One of the two models of a [`ModelPolicy`], for clients that ask for one
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelTier {
+    Cheap,
+    Strong,
+}
+
+impl ModelPolicy {
+    /// The configured model of `tier`
+    pub fn model(&self, tier: ModelTier) -> &str {
+        match tier {
+            ModelTier::Cheap => &self.cheap_model,
+            ModelTier::Strong => &self.strong_model,
+        }
+    }
+
+    pub fn select(&self, task: LlmTask, user_message: &str) -> &str {
+        let complex = task == LlmTask::Chat
+            || user_message.chars().count() > self.long_input_chars
+            || looks_like_recipe(user_message);
+        if complex {
+            &self.strong_model
+        } else {
+            &self.cheap_model
+        }
+    }
+}
+
+/// Cooking vocabulary, or several lines that start with a quantity
+fn looks_like_recipe(text: &str) -> bool {
+    let quantity_lines = text
+        .lines()
+        .filter(|line| {
+            line.trim_start_matches(|c: char| c == '-' || c == '*' || c.is_whitespace())
+                .starts_with(|c: char| c.is_ascii_digit())
+        })
+        .count();
+    quantity_lines >= 3 || RECIPE_HINT_REGEX.is_match(text)
+}
+
+/// How an LLM call ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LlmOutcome {
+    Success,
+    RequestError,
+    AuthError,
+    ParseError,
+    Filtered,
+}
+
+impl LlmOutcome {
+    fn of<T>(result: &Result<T, LlmError>) -> Self {
+        match result {
+            Ok(_) => LlmOutcome::Success,
+            Err(LlmError::Request(_)) => LlmOutcome::RequestError,
+            Err(LlmError::Auth(_)) => LlmOutcome::AuthError,
+            Err(LlmError::Parse(_)) => LlmOutcome::ParseError,
+            Err(LlmError::Filtered(_)) => LlmOutcome::Filtered,
+        }
+    }
+}
+
+/// Measurements of a single LLM call
+#[derive(Debug, Clone)]
+pub struct LlmCallMetrics {
+    pub endpoint: &'static str,
+    pub duration: Duration,
+    pub input_len: usize,
+    pub items_created: usize,
+    pub outcome: LlmOutcome,
+}
+
+/// Sink for per-call LLM metrics, e.g. to feed a dashboard
+pub trait LlmMetrics: Send + Sync {
+    fn record(&self, metrics: &LlmCallMetrics);
+}
+
+/// Writes one structured log line per call
+pub struct LogMetrics;
+
+impl LlmMetrics for LogMetrics {
+    fn record(&self, m: &LlmCallMetrics) {
+        info!(
+            "llm_call endpoint={} duration_ms={} input_len={} items_created={} outcome={:?}",
+            m.endpoint,
+            m.duration.as_millis(),
+            m.input_len,
+            m.items_created,
+            m.outcome
+        );
+    }
+}
+
+/// One extracted item, either a bare task or a task with the model's
+/// confidence and the price the user mentioned
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum TaskEntry {
+    Plain(String),
+    Scored {
+        task: String,
+        confidence: Option<f32>,
+        #[serde(default)]
+        price: Option<f64>,
+        #[serde(default)]
+        currency: Option<String>,
+    },
+}
+
+impl TaskEntry {
+    pub fn task(&self) -> &str {
+        match self {
+            TaskEntry::Plain(task) | TaskEntry::Scored { task, .. } => task,
+        }
+    }
+
+    pub fn confidence(&self) -> Option<f32> {
+        match self {
+            TaskEntry::Plain(_) => None,
+            TaskEntry::Scored { confidence, .. } => *confidence,
+        }
+    }
+
+    /// Price and currency, meaningless without a price so both or neither
+    pub fn price(&self) -> Option<(f64, Option<&str>)> {
+        match self {
+            TaskEntry::Scored {
+                price: Some(price),
+                currency,
+                ..
+            } if price.is_finite() && *price >= 0.0 => Some((*price, currency.as_deref())),
+            _ => None,
+        }
+    }
 }
 
 pub async fn simple_item_response(
-    nest_api: &str,
-    nest_api_key: &str,
+    provider: &dyn LlmProvider,
+    user_message: &str,
+    user_id: String,
+    db_client: &DBClient,
+    settings: &LlmSettings,
+    metrics: &dyn LlmMetrics,
+) -> Result<ItemSummary, LlmError> {
+    let start = Instant::now();
+    let result = item_response(provider, user_message, user_id, db_client, settings).await;
+    metrics.record(&LlmCallMetrics {
+        endpoint: "/api/task",
+        duration: start.elapsed(),
+        input_len: user_message.len(),
+        items_created: result.as_ref().map(|s| s.added).unwrap_or(0),
+        outcome: LlmOutcome::of(&result),
+    });
+    result
+}
+
+async fn item_response(
+    provider: &dyn LlmProvider,
     user_message: &str,
     user_id: String,
     db_client: &DBClient,
-) -> Result<String, LlmError> {
+    settings: &LlmSettings,
+) -> Result<ItemSummary, LlmError> {
     let client = Client::new();
+    let user_message = settings
+        .content_filter
+        .screen(user_message)
+        .map_err(LlmError::Filtered)?;
 
-    let with_sys = format!(
-        "{}{}",
-        "Create only grocery items out of this, ignore everything else: ", user_message
-    );
+    let existing = database::items::get_items(db_client, user_id.clone())
+        .await
+        .map_err(LlmError::Request)?;
+    let open_tasks: Vec<&str> = existing
+        .iter()
+        .filter(|i| !i.completed())
+        .map(|i| i.task.as_str())
+        .collect();
+
+    let prompt = build_item_prompt(&user_message, &open_tasks);
+    let model = settings.model_for(LlmTask::Items, &user_message);
+    let tasks = provider
+        .create_items(&client, &prompt, model.as_deref())
+        .await?;
+
+    store_items(db_client, user_id, &existing, &tasks, settings).await
+}
+
+/// Pastes longer than this many characters are extracted in several calls
+const CHUNK_CHARS: usize = 2000;
+
+/// Progress of a chunked extraction, sent after each chunk is stored
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ExtractionProgress {
+    /// Zero-based index of the chunk just stored
+    pub chunk_index: usize,
+    pub total_chunks: usize,
+    /// Items added by this extraction so far, including this chunk
+    pub items_so_far: usize,
+}
+
+impl ExtractionProgress {
+    pub fn message(&self, language: Language) -> String {
+        language.sentence(&[language.text(Text::ChunksProcessed {
+            done: self.chunk_index + 1,
+            total: self.total_chunks,
+        })])
+    }
+}
 
-    let prompt = Prompt {
-        prompt: with_sys.to_string(),
+struct ChunkState<'a> {
+    provider: &'a dyn LlmProvider,
+    db_client: &'a DBClient,
+    settings: &'a LlmSettings,
+    user_id: String,
+    chunks: Vec<String>,
+    next_chunk: usize,
+    items_so_far: usize,
+    failed: bool,
+}
+
+/// Extracts the items of a long paste chunk by chunk, reporting progress
+///
+/// The paste is split at line breaks into chunks of about [`CHUNK_CHARS`]
+/// characters, each one is extracted and stored before the next is sent, so
+/// later chunks are deduplicated against the items of earlier ones. The stream
+/// ends after the first error; the chunks before it stay stored and the
+/// extraction can be resumed by passing the failed chunk's index as
+/// `from_chunk`.
+pub fn chunked_item_response<'a>(
+    provider: &'a dyn LlmProvider,
+    user_message: &str,
+    user_id: String,
+    db_client: &'a DBClient,
+    settings: &'a LlmSettings,
+    from_chunk: usize,
+) -> impl Stream<Item = Result<ExtractionProgress, LlmError>> + 'a {
+    let state = ChunkState {
+        provider,
+        db_client,
+        settings,
+        user_id,
+        chunks: split_into_chunks(user_message, CHUNK_CHARS),
+        next_chunk: from_chunk,
+        items_so_far: 0,
+        failed: false,
     };
 
-    let full_url = format!("{}{}", nest_api, "/api/task");
+    stream::unfold(state, |mut state| async move {
+        if state.failed {
+            return None;
+        }
+        let chunk_index = state.next_chunk;
+        let chunk = state.chunks.get(chunk_index)?;
+        let result = item_response(
+            state.provider,
+            chunk,
+            state.user_id.clone(),
+            state.db_client,
+            state.settings,
+        )
+        .await;
+        match result {
+            Ok(summary) => {
+                state.next_chunk += 1;
+                state.items_so_far += summary.added;
+                let progress = ExtractionProgress {
+                    chunk_index,
+                    total_chunks: state.chunks.len(),
+                    items_so_far: state.items_so_far,
+                };
+                Some((Ok(progress), state))
+            }
+            Err(err) => {
+                state.failed = true;
+                Some((Err(err), state))
+            }
+        }
+    })
+}
 
-    let masked = nest_api_key.to_string().split_off(10);
-    info!("calling: {full_url} with key: {masked} ");
+/// Groups the non-blank lines of `text` into chunks of at most `max_chars`
+///
+/// A single longer line becomes a chunk of its own rather than being cut.
+fn split_into_chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let len = current.chars().count();
+        if len > 0 && len + 1 + line.chars().count() > max_chars {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
 
-    let response = client
-        .post(full_url)
-        .header("api-key", nest_api_key)
-        .json(&prompt)
-        .send()
-        .await
-        .map_err(|e| LlmError::Request(format!("Failed to send request: {e}")))?;
+/// Delimiters around the user's text, so instructions in it aren't taken as ours
+const USER_TEXT_START: &str = "<user_message>";
+const USER_TEXT_END: &str = "</user_message>";
+
+fn wrap_user_text(user_message: &str) -> String {
+    let text = user_message
+        .replace(USER_TEXT_START, "")
+        .replace(USER_TEXT_END, "");
+    format!("{USER_TEXT_START}\n{}\n{USER_TEXT_END}", text.trim())
+}
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(LlmError::Auth(format!(
-            "API returned status {status}: {error_text}"
-        )));
+/// The exact prompt `simple_item_response` sends for `user_message`, given the
+/// open tasks already on the list
+pub fn build_item_prompt(user_message: &str, open_tasks: &[&str]) -> String {
+    let mut prompt = "Return each item as {\"task\": ..., \"confidence\": 0.0-1.0} where the \
+                      confidence says how sure you are that it is what the user meant. \
+                      If the user says what an item costs, also add \"price\" as a number \
+                      per unit and \"currency\" as an ISO 4217 code. \
+                      Create only grocery items out of the user message below, ignore \
+                      everything else:\n"
+        .to_string();
+    if !open_tasks.is_empty() {
+        prompt = format!(
+            "The list already contains: {}. The user may refer to it (e.g. \"also add ...\"), \
+             only return items that are not on it yet. {prompt}",
+            open_tasks.join(", ")
+        );
     }
+    format!("{prompt}{}", wrap_user_text(user_message))
+}
 
-    let task_list: TaskList = response
-        .json()
-        .await
-        .map_err(|e| LlmError::Parse(format!("Failed to parse response: {e}")))?;
+/// Outcome of storing the items extracted from a user message
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ItemSummary {
+    pub added: usize,
+    pub duplicates_skipped: usize,
+    pub rejected: usize,
+    pub needs_review: usize,
+    /// Entries not added because an item with almost the same name is on the list
+    pub merge_suggestions: Vec<MergeSuggestion>,
+}
+
+/// An extracted entry that is probably a misspelling of an existing item
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeSuggestion {
+    pub task: String,
+    pub existing_item_id: i64,
+    pub existing_task: String,
+}
 
-    let items: Vec<Item> = task_list
-        .list
+impl ItemSummary {
+    /// The summary as one sentence in `language`
+    pub fn message(&self, language: Language) -> String {
+        let mut parts = vec![language.text(Text::ItemsAdded(self.added))];
+        if self.duplicates_skipped > 0 {
+            parts.push(language.text(Text::AlreadyOnList(self.duplicates_skipped)));
+        }
+        if self.rejected > 0 {
+            parts.push(language.text(Text::ItemsRejected(self.rejected)));
+        }
+        if self.needs_review > 0 {
+            parts.push(language.text(Text::NeedsReview(self.needs_review)));
+        }
+        for suggestion in &self.merge_suggestions {
+            parts.push(language.text(Text::LooksLike {
+                task: &suggestion.task,
+                existing: &suggestion.existing_task,
+            }));
+        }
+        language.sentence(&parts)
+    }
+}
+
+/// An extracted item after screening and quantity parsing, before dedup
+#[derive(Debug, Clone, PartialEq)]
+struct ParsedEntry {
+    task: String,
+    quantity: Option<f64>,
+    unit: Option<String>,
+    confidence: Option<f32>,
+    price: Option<f64>,
+    currency: Option<String>,
+    too_long: bool,
+}
+
+/// Screens and splits the model's entries, counting unusable ones as rejected
+fn parse_entries(
+    tasks: &[TaskEntry],
+    settings: &LlmSettings,
+    summary: &mut ItemSummary,
+) -> Vec<ParsedEntry> {
+    let mut entries = Vec::new();
+    for entry in tasks {
+        let parts = split_long_entry(entry);
+        // A price can't be told apart between the parts of a split entry
+        let price = entry.price().filter(|_| parts.len() == 1);
+        for (task, confidence, too_long) in parts {
+            let Ok(task) = settings.content_filter.screen(task.trim()) else {
+                summary.rejected += 1;
+                continue;
+            };
+            let (quantity, unit, task) = quantity::split_quantity(&task, settings.number_locale);
+            if task.is_empty() {
+                summary.rejected += 1;
+                continue;
+            }
+            entries.push(ParsedEntry {
+                task,
+                quantity,
+                unit,
+                confidence,
+                price: price.map(|(price, _)| price),
+                currency: price.and_then(|(_, currency)| currency.map(str::to_uppercase)),
+                too_long,
+            });
+        }
+    }
+    entries
+}
+
+async fn store_items(
+    db_client: &DBClient,
+    user_id: String,
+    existing: &[Item],
+    tasks: &[TaskEntry],
+    settings: &LlmSettings,
+) -> Result<ItemSummary, LlmError> {
+    let mut summary = ItemSummary::default();
+    let entries = parse_entries(tasks, settings, &mut summary);
+    store_entries(db_client, user_id, existing, entries, settings, summary).await
+}
+
+async fn store_entries(
+    db_client: &DBClient,
+    user_id: String,
+    existing: &[Item],
+    entries: Vec<ParsedEntry>,
+    settings: &LlmSettings,
+    mut summary: ItemSummary,
+) -> Result<ItemSummary, LlmError> {
+    let normalization = database::text_normalization(db_client);
+    // Bought or deleted items can go on the list again
+    let open: Vec<Item> = existing
         .iter()
-        .map(|t| Item {
-            owner_id: user_id.clone(),
-            id: None,
-            task: t.clone(),
-            completed: 0,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-        })
+        .filter(|i| !i.completed() && i.deleted_at.is_none())
+        .cloned()
+        .collect();
+    let mut seen: HashSet<String> = open
+        .iter()
+        .map(|i| dedup_key(&i.task, &normalization))
         .collect();
 
-    database::items::create_items(db_client, items).await;
+    let mut items = Vec::new();
+    for entry in entries {
+        let key = dedup_key(&entry.task, &normalization);
+        if !seen.insert(key.clone()) {
+            summary.duplicates_skipped += 1;
+            continue;
+        }
+        let max_distance = similarity::near_duplicate_distance(&key);
+        if let Some(similar) = rank_similar(&open, &key, max_distance).first() {
+            summary.merge_suggestions.push(MergeSuggestion {
+                task: entry.task,
+                existing_item_id: similar.item.id(),
+                existing_task: similar.item.task.clone(),
+            });
+            continue;
+        }
+        let mut item = Item::new(user_id.clone(), entry.task);
+        item.confidence = entry.confidence;
+        item.quantity = entry.quantity;
+        item.unit = entry.unit;
+        item.price = entry.price;
+        item.currency = entry.currency;
+        if entry.too_long
+            || item
+                .confidence
+                .is_some_and(|c| c < settings.review_threshold)
+        {
+            item.needs_review = 1;
+            summary.needs_review += 1;
+        }
+        items.push(item);
+    }
+
+    summary.added = items.len();
+    database::items::create_items(db_client, items)
+        .await
+        .map_err(LlmError::Request)?;
+
+    Ok(summary)
+}
 
-    let tasks_string = task_list.list.join("\n");
+/// Items longer than this are most likely a paragraph the model failed to split
+const MAX_ITEM_LEN: usize = 80;
 
-    let answer = format!("Created {tasks_string}");
+lazy_static::lazy_static! {
+    static ref ITEM_SEPARATOR_REGEX: Regex =
+        Regex::new(r"(?i)[,;\n]|\s+(?:and|&|und)\s+").unwrap();
+}
 
-    Ok(answer)
+/// Splits an over-long entry on commas and conjunctions
+///
+/// Yields `(task, confidence, too_long)`; when splitting does not produce short
+/// parts the entry is kept whole and `too_long` marks it for review.
+fn split_long_entry(entry: &TaskEntry) -> Vec<(String, Option<f32>, bool)> {
+    let task = entry.task().trim();
+    if task.chars().count() <= MAX_ITEM_LEN {
+        return vec![(task.to_string(), entry.confidence(), false)];
+    }
+    let parts: Vec<&str> = ITEM_SEPARATOR_REGEX
+        .split(task)
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    if parts.len() > 1 && parts.iter().all(|p| p.chars().count() <= MAX_ITEM_LEN) {
+        parts
+            .into_iter()
+            .map(|p| (p.to_string(), entry.confidence(), false))
+            .collect()
+    } else {
+        vec![(task.to_string(), entry.confidence(), true)]
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct ChatResponse {
+fn dedup_key(task: &str, normalization: &TextNormalization) -> String {
+    normalize_text(task, normalization).to_lowercase()
+}
+
+/// Chat answer together with the ids of the stored items it referred to
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatAnswer {
     pub content: String,
+    pub referenced_item_ids: Vec<i64>,
+}
+
+lazy_static::lazy_static! {
+    static ref ITEM_MARKER_REGEX: Regex = Regex::new(r"\s?\[item:(\d+)\]").unwrap();
 }
 
 pub async fn simple_chat_response(
-    nest_api: &str,
-    nest_api_key: &str,
+    provider: &dyn LlmProvider,
+    user_message: &str,
+    user_id: String,
+    db_client: &DBClient,
+    settings: &LlmSettings,
+    metrics: &dyn LlmMetrics,
+) -> Result<ChatAnswer, LlmError> {
+    let start = Instant::now();
+    let result = chat_response(provider, user_message, user_id, db_client, settings).await;
+    metrics.record(&LlmCallMetrics {
+        endpoint: "/api/chat",
+        duration: start.elapsed(),
+        input_len: user_message.len(),
+        items_created: 0,
+        outcome: LlmOutcome::of(&result),
+    });
+    result
+}
+
+async fn chat_response(
+    provider: &dyn LlmProvider,
     user_message: &str,
-) -> Result<String, LlmError> {
+    user_id: String,
+    db_client: &DBClient,
+    settings: &LlmSettings,
+) -> Result<ChatAnswer, LlmError> {
     let client = Client::new();
+    let user_message = settings
+        .content_filter
+        .screen(user_message)
+        .map_err(LlmError::Filtered)?;
+
+    let items = database::items::get_items(db_client, user_id)
+        .await
+        .map_err(LlmError::Request)?;
+    let prompt = build_chat_prompt(&user_message, &items);
+    let model = settings.model_for(LlmTask::Chat, &user_message);
+    let content = provider.chat(&client, &prompt, model.as_deref()).await?;
+
+    let content = settings
+        .content_filter
+        .screen(&content)
+        .map_err(LlmError::Filtered)?;
+    Ok(parse_chat_answer(&content, &items))
+}
 
-    let with_insctructions = format!(
+/// The exact prompt `simple_chat_response` sends for `user_message`, given the
+/// user's items
+pub fn build_chat_prompt(user_message: &str, items: &[Item]) -> String {
+    let item_context = chat_item_context(items);
+    let user_text = wrap_user_text(user_message);
+    format!(
         "
         Only answer in commonmark markdown format.
         You are Rezi a helpful assistant for recipes, cooking, ingredients and groceries.
+        {item_context}
 
+        this is the message from the user:
+{user_text}
 
-        this is the message from the user: {user_message}
+        "
+    )
+}
 
+fn chat_item_context(items: &[Item]) -> String {
+    let listed: Vec<String> = items
+        .iter()
+        .filter(|i| !i.completed())
+        .map(|i| format!("- [item:{}] {}", i.id(), i.task))
+        .collect();
+    if listed.is_empty() {
+        return String::new();
+    }
+    format!(
         "
+        These are the items currently on the user's list:
+        {}
+        When your answer uses one of these items, cite it right after mentioning it with its marker, e.g. [item:12].
+        ",
+        listed.join("\n        ")
+    )
+}
+
+/// Strips `[item:ID]` markers from the answer and keeps the ids that belong to the user
+fn parse_chat_answer(content: &str, items: &[Item]) -> ChatAnswer {
+    let known: HashSet<i64> = items.iter().filter_map(|i| i.id).collect();
+    let mut referenced_item_ids = Vec::new();
+    for capture in ITEM_MARKER_REGEX.captures_iter(content) {
+        if let Ok(id) = capture[1].parse::<i64>()
+            && known.contains(&id)
+            && !referenced_item_ids.contains(&id)
+        {
+            referenced_item_ids.push(id);
+        }
+    }
+    ChatAnswer {
+        content: ITEM_MARKER_REGEX.replace_all(content, "").to_string(),
+        referenced_item_ids,
+    }
+}
+
+/// Longest meal plan that is asked for
+const MAX_MEAL_PLAN_DAYS: u8 = 14;
+
+/// A generated meal plan and the grocery items stored for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MealPlan {
+    /// The plan as markdown
+    pub content: String,
+    pub summary: ItemSummary,
+}
+
+/// Plans `days` days of meals and puts their ingredients on the list
+///
+/// The plan comes from the chat flow; its ingredients are then extracted per
+/// meal and summed in Rust, so an onion used in two meals becomes one item
+/// with both quantities added up. Ingredients already on the list are skipped
+/// like in [`simple_item_response`]. `days` is clamped to 1-14.
+pub async fn meal_plan_to_groceries(
+    provider: &dyn LlmProvider,
+    days: u8,
+    preferences: &str,
+    user_id: String,
+    db_client: &DBClient,
+    settings: &LlmSettings,
+    metrics: &dyn LlmMetrics,
+) -> Result<MealPlan, LlmError> {
+    let start = Instant::now();
+    let result =
+        meal_plan_response(provider, days, preferences, user_id, db_client, settings).await;
+    metrics.record(&LlmCallMetrics {
+        endpoint: "meal_plan",
+        duration: start.elapsed(),
+        input_len: preferences.len(),
+        items_created: result.as_ref().map(|p| p.summary.added).unwrap_or(0),
+        outcome: LlmOutcome::of(&result),
+    });
+    result
+}
+
+async fn meal_plan_response(
+    provider: &dyn LlmProvider,
+    days: u8,
+    preferences: &str,
+    user_id: String,
+    db_client: &DBClient,
+    settings: &LlmSettings,
+) -> Result<MealPlan, LlmError> {
+    let client = Client::new();
+    let preferences = settings
+        .content_filter
+        .screen(preferences)
+        .map_err(LlmError::Filtered)?;
+
+    let prompt = build_meal_plan_prompt(days, &preferences);
+    let model = settings.model_for(LlmTask::Chat, &preferences);
+    let content = provider.chat(&client, &prompt, model.as_deref()).await?;
+    let content = settings
+        .content_filter
+        .screen(&content)
+        .map_err(LlmError::Filtered)?;
+
+    // Ingredient lists are recipe-like, which the policy sends to the strong model
+    let prompt = build_ingredients_prompt(&content);
+    let model = settings.model_for(LlmTask::Items, &prompt);
+    let tasks = provider
+        .create_items(&client, &prompt, model.as_deref())
+        .await?;
+
+    let existing = database::items::get_items(db_client, user_id.clone())
+        .await
+        .map_err(LlmError::Request)?;
+    let normalization = database::text_normalization(db_client);
+    let mut summary = ItemSummary::default();
+    let entries = sum_shared_ingredients(
+        parse_entries(&tasks, settings, &mut summary),
+        &normalization,
     );
+    let summary = store_entries(db_client, user_id, &existing, entries, settings, summary).await?;
+    Ok(MealPlan { content, summary })
+}
 
-    let prompt = Prompt {
-        prompt: with_insctructions,
-    };
+/// The exact prompt `meal_plan_to_groceries` sends for the plan itself
+pub fn build_meal_plan_prompt(days: u8, preferences: &str) -> String {
+    let days = days.clamp(1, MAX_MEAL_PLAN_DAYS);
+    format!(
+        "
+        Only answer in commonmark markdown format.
+        You are Rezi a helpful assistant for recipes, cooking, ingredients and groceries.
+        Plan a main meal for each of the next {days} days. For every meal, list its
+        ingredients with the quantities needed.
+
+        these are the preferences of the user:
+{}
+
+        ",
+        wrap_user_text(preferences)
+    )
+}
+
+/// The exact prompt `meal_plan_to_groceries` sends to extract the ingredients
+/// of `meal_plan`
+pub fn build_ingredients_prompt(meal_plan: &str) -> String {
+    format!(
+        "Return every ingredient of every meal in the meal plan below as {{\"task\": ..., \
+         \"confidence\": 0.0-1.0}}, starting with the quantity that meal needs, e.g. \
+         \"2 onion\" or \"500 g lentils\". List an ingredient again for each meal that \
+         uses it, do not add them up. Use the singular for names:\n{}",
+        wrap_user_text(meal_plan)
+    )
+}
+
+/// Merges entries for the same ingredient and unit, adding up their quantities
+///
+/// An entry without a quantity counts as one of it. Entries with different
+/// units stay separate, grams and kilograms aren't converted.
+fn sum_shared_ingredients(
+    entries: Vec<ParsedEntry>,
+    normalization: &TextNormalization,
+) -> Vec<ParsedEntry> {
+    let mut summed: Vec<ParsedEntry> = Vec::new();
+    for entry in entries {
+        let key = dedup_key(&entry.task, normalization);
+        let shared = summed
+            .iter_mut()
+            .find(|other| dedup_key(&other.task, normalization) == key && other.unit == entry.unit);
+        match shared {
+            Some(other) => {
+                other.quantity =
+                    Some(other.quantity.unwrap_or(1.0) + entry.quantity.unwrap_or(1.0));
+                other.confidence = match (other.confidence, entry.confidence) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                if other.price.is_none() {
+                    other.price = entry.price;
+                    other.currency = entry.currency;
+                }
+                other.too_long |= entry.too_long;
+            }
+            None => summed.push(entry),
+        }
+    }
+    summed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_provider::NestProvider;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Discards all metrics
+    struct NoopMetrics;
+
+    impl LlmMetrics for NoopMetrics {
+        fn record(&self, _metrics: &LlmCallMetrics) {}
+    }
+
+    const TEST_KEY: &str = "test-api-key-0000";
+
+    /// Minimal stand-in for the nest API: answers each request with the next
+    /// canned response and records the request bodies it received.
+    struct MockNest {
+        url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockNest {
+        async fn start(responses: Vec<impl Into<String>>) -> Self {
+            let responses: Vec<String> = responses.into_iter().map(Into::into).collect();
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            tokio::spawn(async move {
+                for response in responses {
+                    let (mut socket, _) = listener.accept().await.unwrap();
+                    let body = read_body(&mut socket).await;
+                    recorded.lock().unwrap().push(body);
+                    let reply = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}",
+                        response.len()
+                    );
+                    socket.write_all(reply.as_bytes()).await.unwrap();
+                }
+            });
+            MockNest { url, requests }
+        }
+
+        fn provider(&self) -> NestProvider {
+            NestProvider::new(&self.url, TEST_KEY)
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    async fn read_body(socket: &mut tokio::net::TcpStream) -> String {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&buf).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|l| {
+                        let (name, value) = l.split_once(':')?;
+                        if name.eq_ignore_ascii_case("content-length") {
+                            value.trim().parse::<usize>().ok()
+                        } else {
+                            None
+                        }
+                    })
+                    .unwrap_or(0);
+                if buf.len() >= header_end + 4 + content_length {
+                    return String::from_utf8_lossy(&buf[header_end + 4..]).to_string();
+                }
+            }
+            if n == 0 {
+                return String::new();
+            }
+        }
+    }
+
+    fn test_settings() -> LlmSettings {
+        LlmSettings {
+            review_threshold: 0.5,
+            content_filter: ContentFilter::disabled(),
+            model_policy: None,
+            model_override: None,
+            number_locale: NumberLocale::default(),
+        }
+    }
+
+    fn test_policy() -> ModelPolicy {
+        ModelPolicy {
+            cheap_model: "small".to_string(),
+            strong_model: "large".to_string(),
+            long_input_chars: 200,
+        }
+    }
+
+    #[test]
+    fn test_short_shopping_input_selects_cheap_model() {
+        let policy = test_policy();
+        assert_eq!(
+            policy.select(LlmTask::Items, "milk, eggs and 2 apples"),
+            "small"
+        );
+        assert_eq!(policy.select(LlmTask::Chat, "milk?"), "large");
+    }
+
+    #[test]
+    fn test_long_recipe_selects_strong_model() {
+        let policy = test_policy();
+        let recipe = "Pancakes\n\
+                      200 g flour\n\
+                      2 eggs\n\
+                      300 ml milk\n\
+                      Whisk everything and let the batter rest for half an hour.";
+        assert_eq!(policy.select(LlmTask::Items, recipe), "large");
+        assert_eq!(
+            policy.select(LlmTask::Items, "the ingredients for lasagne"),
+            "large"
+        );
+        assert_eq!(policy.select(LlmTask::Items, &"milk ".repeat(50)), "large");
+    }
+
+    #[test]
+    fn test_clients_can_only_ask_for_policy_models() {
+        let policy = test_policy();
+        let tier: ModelTier = serde_json::from_str("\"strong\"").unwrap();
+        assert_eq!(policy.model(tier), "large");
+        assert_eq!(policy.model(ModelTier::Cheap), "small");
+        assert!(serde_json::from_str::<ModelTier>("\"gpt-4o\"").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_model_is_sent_and_overridable_per_call() {
+        let client = database::test_client().await;
+        let nest = MockNest::start(vec![r#"{"list": []}"#, r#"{"list": []}"#]).await;
+        let settings = LlmSettings {
+            model_policy: Some(test_policy()),
+            ..test_settings()
+        };
+        for settings in [settings.clone(), settings.with_model("custom")] {
+            simple_item_response(
+                &nest.provider(),
+                "milk",
+                "1".to_string(),
+                &client,
+                &settings,
+                &NoopMetrics,
+            )
+            .await
+            .unwrap();
+        }
+
+        let requests = nest.requests();
+        assert!(requests[0].contains(r#""model":"small""#));
+        assert!(requests[1].contains(r#""model":"custom""#));
+    }
+
+    fn test_item(owner_id: &str, task: &str) -> Item {
+        Item::new(owner_id.to_string(), task.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_follow_up_only_creates_new_items() {
+        let client = database::test_client().await;
+        database::items::create_items(
+            &client,
+            vec![test_item("1", "milk"), test_item("1", "eggs")],
+        )
+        .await
+        .unwrap();
+        let nest = MockNest::start(vec![r#"{"list": ["cheese", "milk"]}"#]).await;
+
+        let summary = simple_item_response(
+            &nest.provider(),
+            "also cheese",
+            "1".to_string(),
+            &client,
+            &test_settings(),
+            &NoopMetrics,
+        )
+        .await
+        .unwrap();
+
+        let prompt = &nest.requests()[0];
+        assert!(prompt.contains("milk, eggs"));
+        assert!(prompt.contains("also cheese"));
+        assert_eq!(summary.added, 1);
+        let tasks: Vec<String> = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|i| i.task)
+            .collect();
+        assert_eq!(tasks, vec!["milk", "eggs", "cheese"]);
+    }
+
+    #[tokio::test]
+    async fn test_store_items_parses_quantities_in_request_locale() {
+        let client = database::test_client().await;
+        let tasks: Vec<TaskEntry> = ["1,5 kg Mehl", "2 Zitronen"]
+            .iter()
+            .map(|t| TaskEntry::Plain(t.to_string()))
+            .collect();
+        let settings = test_settings().with_number_locale(NumberLocale::from_language("de"));
+        store_items(&client, "1".to_string(), &[], &tasks, &settings)
+            .await
+            .unwrap();
+
+        let items = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let parsed: Vec<(&str, Option<f64>, Option<&str>)> = items
+            .iter()
+            .map(|i| (i.task.as_str(), i.quantity, i.unit.as_deref()))
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                ("Mehl", Some(1.5), Some("kg")),
+                ("Zitronen", Some(2.0), None)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_items_adds_bought_and_deleted_items_again() {
+        let client = database::test_client().await;
+        let mut bought = test_item("1", "Milk");
+        bought.toggle();
+        let mut deleted = test_item("1", "Tomatoes");
+        deleted.deleted_at = Some(chrono::Utc::now());
+        database::items::create_items(&client, vec![bought, deleted])
+            .await
+            .unwrap();
 
-    let full_url = format!("{}{}", nest_api, "/api/chat");
+        let existing: Vec<Item> = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let tasks: Vec<TaskEntry> = ["milk", "tomatos"]
+            .iter()
+            .map(|t| TaskEntry::Plain(t.to_string()))
+            .collect();
+        let summary = store_items(
+            &client,
+            "1".to_string(),
+            &existing,
+            &tasks,
+            &test_settings(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.added, 2);
+        assert_eq!(summary.duplicates_skipped, 0);
+        assert!(summary.merge_suggestions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_items_counts_added_and_duplicates() {
+        let client = database::test_client().await;
+        database::items::create_items(&client, vec![test_item("1", "Milk")])
+            .await
+            .unwrap();
+
+        let existing = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let tasks: Vec<TaskEntry> = ["milk", "eggs", "Bread", "eggs", "  "]
+            .iter()
+            .map(|t| TaskEntry::Plain(t.to_string()))
+            .collect();
+        let summary = store_items(
+            &client,
+            "1".to_string(),
+            &existing,
+            &tasks,
+            &test_settings(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            summary,
+            ItemSummary {
+                added: 2,
+                duplicates_skipped: 2,
+                rejected: 1,
+                needs_review: 0,
+                merge_suggestions: vec![],
+            }
+        );
+        let items = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_near_duplicates_are_suggested_not_added() {
+        let client = database::test_client().await;
+        database::items::create_items(&client, vec![test_item("1", "Tomatoes")])
+            .await
+            .unwrap();
+
+        let existing = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let tasks: Vec<TaskEntry> = ["tomatos", "potatoes", "rice"]
+            .iter()
+            .map(|t| TaskEntry::Plain(t.to_string()))
+            .collect();
+        let summary = store_items(
+            &client,
+            "1".to_string(),
+            &existing,
+            &tasks,
+            &test_settings(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(summary.added, 2);
+        assert_eq!(
+            summary.merge_suggestions,
+            vec![MergeSuggestion {
+                task: "tomatos".to_string(),
+                existing_item_id: existing[0].id(),
+                existing_task: "Tomatoes".to_string(),
+            }]
+        );
+        assert_eq!(
+            summary.message(Language::English),
+            "Added 2 items, \"tomatos\" looks like \"Tomatoes\" on your list."
+        );
+    }
+
+    #[tokio::test]
+    async fn test_curly_and_straight_quotes_dedup_together() {
+        let client = database::test_client().await;
+        database::items::create_items(&client, vec![test_item("1", "don\u{2019}t buy milk")])
+            .await
+            .unwrap();
+
+        let existing = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(existing[0].task, "don\u{2019}t buy milk");
+        assert_eq!(
+            existing[0].normalized_task.as_deref(),
+            Some("don't buy milk")
+        );
+
+        let tasks = vec![TaskEntry::Plain("don't buy milk".to_string())];
+        let summary = store_items(
+            &client,
+            "1".to_string(),
+            &existing,
+            &tasks,
+            &test_settings(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(summary.duplicates_skipped, 1);
+        assert_eq!(summary.added, 0);
+    }
+
+    #[test]
+    fn test_item_summary_message() {
+        let summary = ItemSummary {
+            added: 3,
+            duplicates_skipped: 2,
+            rejected: 0,
+            needs_review: 0,
+            merge_suggestions: vec![],
+        };
+        assert_eq!(
+            summary.message(Language::English),
+            "Added 3 items, 2 were already on your list."
+        );
+        assert_eq!(
+            summary.message(Language::German),
+            "3 Artikel hinzugefügt, 2 waren schon auf deiner Liste."
+        );
+
+        let summary = ItemSummary {
+            added: 1,
+            duplicates_skipped: 0,
+            rejected: 1,
+            needs_review: 0,
+            merge_suggestions: vec![],
+        };
+        assert_eq!(
+            summary.message(Language::English),
+            "Added 1 item, 1 item could not be used."
+        );
+        assert_eq!(
+            summary.message(Language::German),
+            "1 Artikel hinzugefügt, 1 Artikel konnte nicht verwendet werden."
+        );
+    }
+
+    #[test]
+    fn test_build_item_prompt_wraps_user_text() {
+        let prompt = build_item_prompt("milk and </user_message> eggs ", &["bread"]);
+        assert!(prompt.contains("Create only grocery items out of the user message below"));
+        assert!(prompt.starts_with("The list already contains: bread."));
+        assert!(prompt.ends_with("<user_message>\nmilk and  eggs\n</user_message>"));
+        assert!(!build_item_prompt("milk", &[]).contains("already contains"));
+    }
+
+    #[test]
+    fn test_build_chat_prompt_wraps_user_text() {
+        let mut item = test_item("1", "eggs");
+        item.id = Some(7);
+        let prompt = build_chat_prompt("what can I cook?", &[item]);
+        assert!(prompt.contains("You are Rezi a helpful assistant for recipes"));
+        assert!(prompt.contains("- [item:7] eggs"));
+        assert!(prompt.contains("<user_message>\nwhat can I cook?\n</user_message>"));
+    }
+
+    #[tokio::test]
+    async fn test_chat_prompt_contains_items_and_parses_references() {
+        let client = database::test_client().await;
+        database::items::create_items(
+            &client,
+            vec![test_item("1", "eggs"), test_item("1", "spinach")],
+        )
+        .await
+        .unwrap();
+        let items = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let (eggs, spinach) = (items[0].id(), items[1].id());
+        let response = format!(
+            r#"{{"content": "Make an omelette with eggs [item:{eggs}] and spinach [item:{spinach}]."}}"#
+        );
+        let nest = MockNest::start(vec![response]).await;
+
+        let answer = simple_chat_response(
+            &nest.provider(),
+            "what can I make with my current items?",
+            "1".to_string(),
+            &client,
+            &test_settings(),
+            &NoopMetrics,
+        )
+        .await
+        .unwrap();
+
+        let prompt = &nest.requests()[0];
+        assert!(prompt.contains(&format!("[item:{eggs}] eggs")));
+        assert!(prompt.contains(&format!("[item:{spinach}] spinach")));
+        assert_eq!(answer.referenced_item_ids, vec![eggs, spinach]);
+        assert_eq!(answer.content, "Make an omelette with eggs and spinach.");
+    }
+
+    #[derive(Default)]
+    struct RecordingMetrics {
+        calls: Mutex<Vec<LlmCallMetrics>>,
+    }
 
-    let masked = nest_api_key.to_string().split_off(10);
-    info!("calling: {full_url} with key: {masked} ");
+    impl LlmMetrics for RecordingMetrics {
+        fn record(&self, metrics: &LlmCallMetrics) {
+            self.calls.lock().unwrap().push(metrics.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_metrics_record_successful_item_call() {
+        let client = database::test_client().await;
+        let nest = MockNest::start(vec![r#"{"list": ["cheese", "milk", "bread"]}"#]).await;
+        let metrics = RecordingMetrics::default();
 
-    let response = client
-        .post(full_url)
-        .header("api-key", nest_api_key)
-        .json(&prompt)
-        .send()
+        simple_item_response(
+            &nest.provider(),
+            "cheese, milk and bread",
+            "1".to_string(),
+            &client,
+            &test_settings(),
+            &metrics,
+        )
         .await
-        .map_err(|e| LlmError::Request(format!("Failed to send request: {e}")))?;
+        .unwrap();
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_default();
-        return Err(LlmError::Auth(format!(
-            "API returned status {status}: {error_text}"
-        )));
+        let calls = metrics.calls.lock().unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].endpoint, "/api/task");
+        assert_eq!(calls[0].items_created, 3);
+        assert_eq!(calls[0].input_len, "cheese, milk and bread".len());
+        assert_eq!(calls[0].outcome, LlmOutcome::Success);
+        assert!(calls[0].duration > Duration::ZERO);
     }
 
-    let chat_response: ChatResponse = response
-        .json()
+    #[tokio::test]
+    async fn test_low_confidence_items_are_flagged_for_review() {
+        let client = database::test_client().await;
+        let nest = MockNest::start(vec![
+            r#"{"list": [{"task": "milk", "confidence": 0.95}, {"task": "that green thing", "confidence": 0.2}, "bread"]}"#,
+        ])
+        .await;
+
+        let summary = simple_item_response(
+            &nest.provider(),
+            "milk, that green thing and bread",
+            "1".to_string(),
+            &client,
+            &test_settings(),
+            &NoopMetrics,
+        )
         .await
-        .map_err(|e| LlmError::Parse(format!("Failed to parse response: {e}")))?;
+        .unwrap();
+        assert_eq!(summary.added, 3);
+        assert_eq!(summary.needs_review, 1);
+
+        let items = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let by_task = |task: &str| items.iter().find(|i| i.task == task).unwrap();
+        assert!(!by_task("milk").needs_review());
+        assert_eq!(by_task("milk").confidence, Some(0.95));
+        assert!(by_task("that green thing").needs_review());
+        assert!(!by_task("bread").needs_review());
+        assert_eq!(by_task("bread").confidence, None);
+    }
+
+    #[tokio::test]
+    async fn test_mentioned_prices_are_stored() {
+        let client = database::test_client().await;
+        let nest = MockNest::start(vec![
+            r#"{"list": [{"task": "2 l milk", "confidence": 0.9, "price": 1.2, "currency": "eur"}, {"task": "bread", "confidence": 0.9}, {"task": "tea", "price": -3}]}"#,
+        ])
+        .await;
+
+        simple_item_response(
+            &nest.provider(),
+            "2 litres of milk at 1.20 each, bread and tea",
+            "1".to_string(),
+            &client,
+            &test_settings(),
+            &NoopMetrics,
+        )
+        .await
+        .unwrap();
+
+        let items = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let price = |task: &str| {
+            let item = items.iter().find(|i| i.task == task).unwrap();
+            (item.price, item.currency.as_deref())
+        };
+        assert_eq!(price("milk"), (Some(1.2), Some("EUR")));
+        assert_eq!(price("bread"), (None, None));
+        assert_eq!(price("tea"), (None, None));
+    }
+
+    #[tokio::test]
+    async fn test_long_items_are_split_or_flagged() {
+        let client = database::test_client().await;
+        let splittable = format!(
+            "{}, and some eggs",
+            vec!["a bottle of fresh milk"; 20].join(", ")
+        );
+        let paragraph = "x".repeat(500);
+        assert!(splittable.len() > 400);
+        let tasks = vec![
+            TaskEntry::Plain(splittable),
+            TaskEntry::Plain(paragraph.clone()),
+        ];
+
+        let summary = store_items(&client, "1".to_string(), &[], &tasks, &test_settings())
+            .await
+            .unwrap();
+        assert_eq!(summary.added, 3);
+        assert_eq!(summary.duplicates_skipped, 19);
+        assert_eq!(summary.needs_review, 1);
+
+        let items = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let tasks: Vec<&str> = items.iter().map(|i| i.task.as_str()).collect();
+        assert_eq!(
+            tasks,
+            vec!["a bottle of fresh milk", "some eggs", paragraph.as_str()]
+        );
+        assert!(!items[0].needs_review());
+        assert!(items[2].needs_review());
+    }
+
+    async fn chat_with_filter(content_filter: ContentFilter) -> Result<ChatAnswer, LlmError> {
+        let client = database::test_client().await;
+        let nest =
+            MockNest::start(vec![r#"{"content": "Use the darn blender for the soup."}"#]).await;
+        let settings = LlmSettings {
+            content_filter,
+            ..test_settings()
+        };
+        simple_chat_response(
+            &nest.provider(),
+            "how do I make soup?",
+            "1".to_string(),
+            &client,
+            &settings,
+            &NoopMetrics,
+        )
+        .await
+    }
 
-    Ok(chat_response.content)
+    #[tokio::test]
+    async fn test_content_filter_masks_chat_output_when_enabled() {
+        let filter = ContentFilter::new(
+            &["darn".to_string()],
+            crate::content_filter::FilterAction::Mask,
+        );
+        let answer = chat_with_filter(filter).await.unwrap();
+        assert_eq!(answer.content, "Use the **** blender for the soup.");
+    }
+
+    #[tokio::test]
+    async fn test_content_filter_passes_through_when_disabled() {
+        let answer = chat_with_filter(ContentFilter::disabled()).await.unwrap();
+        assert_eq!(answer.content, "Use the darn blender for the soup.");
+    }
+
+    #[tokio::test]
+    async fn test_meal_plan_sums_shared_ingredients() {
+        let client = database::test_client().await;
+        database::items::create_items(&client, vec![test_item("1", "salt")])
+            .await
+            .unwrap();
+        let nest = MockNest::start(vec![
+            r#"{"content": "**Day 1**: onion soup\n\n**Day 2**: lentil curry"}"#,
+            r#"{"list": ["3 onion", "1 tbsp salt", "200 g lentils", "1 onion", "300 g lentils"]}"#,
+        ])
+        .await;
+
+        let plan = meal_plan_to_groceries(
+            &nest.provider(),
+            2,
+            "vegetarian",
+            "1".to_string(),
+            &client,
+            &test_settings(),
+            &NoopMetrics,
+        )
+        .await
+        .unwrap();
+
+        let requests = nest.requests();
+        assert!(requests[0].contains("next 2 days"));
+        assert!(requests[0].contains("vegetarian"));
+        assert!(requests[1].contains("lentil curry"));
+        assert!(plan.content.contains("onion soup"));
+        assert_eq!(plan.summary.added, 2);
+        assert_eq!(plan.summary.duplicates_skipped, 1);
+
+        let items = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        let groceries: Vec<(&str, Option<String>)> = items
+            .iter()
+            .map(|i| (i.task.as_str(), i.quantity_label()))
+            .collect();
+        assert_eq!(
+            groceries,
+            vec![
+                ("salt", None),
+                ("onion", Some("4".to_string())),
+                ("lentils", Some("500 g".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_into_chunks_keeps_lines_whole() {
+        assert_eq!(
+            split_into_chunks("milk\n\n  eggs \nbread\n", 10),
+            vec!["milk\neggs", "bread"]
+        );
+        let long = "flour ".repeat(4);
+        assert_eq!(
+            split_into_chunks(&format!("milk\n{long}"), 10),
+            vec!["milk".to_string(), long.trim().to_string()]
+        );
+        assert!(split_into_chunks(" \n", 10).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_chunked_extraction_reports_progress_per_chunk() {
+        use futures_util::StreamExt;
+
+        let client = database::test_client().await;
+        let nest = MockNest::start(vec![
+            r#"{"list": ["milk", "eggs"]}"#,
+            r#"{"list": ["eggs", "bread"]}"#,
+        ])
+        .await;
+        let provider = nest.provider();
+        let settings = test_settings();
+        let paste = format!("{}\n{}", "milk ".repeat(300), "eggs bread ".repeat(150));
+
+        let progress: Vec<ExtractionProgress> =
+            chunked_item_response(&provider, &paste, "1".to_string(), &client, &settings, 0)
+                .map(Result::unwrap)
+                .collect()
+                .await;
+
+        assert_eq!(
+            progress,
+            vec![
+                ExtractionProgress {
+                    chunk_index: 0,
+                    total_chunks: 2,
+                    items_so_far: 2,
+                },
+                ExtractionProgress {
+                    chunk_index: 1,
+                    total_chunks: 2,
+                    items_so_far: 3,
+                },
+            ]
+        );
+        assert_eq!(progress[1].message(Language::English), "2 of 2 processed.");
+        assert!(nest.requests()[1].contains("milk, eggs"));
+        let items = database::items::get_items(&client, "1".to_string())
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 3);
+    }
 }
@@ -0,0 +1,425 @@
+use futures_util::future::BoxFuture;
+use log::info;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::llm::{LlmError, TaskEntry};
+use crate::retry::{self, RetryPolicy};
+
+/// Wire format of an LLM API
+///
+/// The prompts are built by the flows in [`crate::llm`], a provider only
+/// sends them and extracts the answer. `model` is the model picked by the
+/// settings, `None` leaves the choice to the provider.
+pub trait LlmProvider: Send + Sync {
+    /// Asks for the grocery items in `prompt`
+    fn create_items<'a>(
+        &'a self,
+        client: &'a Client,
+        prompt: &'a str,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<TaskEntry>, LlmError>>;
+
+    /// Asks for a free-form markdown answer to `prompt`
+    fn chat<'a>(
+        &'a self,
+        client: &'a Client,
+        prompt: &'a str,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<String, LlmError>>;
+}
+
+/// The nest API, `{prompt}` in and `{list}` or `{content}` out
+pub struct NestProvider {
+    api: String,
+    api_key: String,
+}
+
+#[derive(Debug, Serialize)]
+struct NestPrompt<'a> {
+    prompt: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NestTaskList {
+    list: Vec<TaskEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NestChatResponse {
+    content: String,
+}
+
+impl NestProvider {
+    pub fn new(api: impl Into<String>, api_key: impl Into<String>) -> Self {
+        NestProvider {
+            api: api.into(),
+            api_key: api_key.into(),
+        }
+    }
+
+    fn request_body<'a>(prompt: &'a str, model: Option<&'a str>) -> NestPrompt<'a> {
+        NestPrompt { prompt, model }
+    }
+
+    fn parse_items(body: &str) -> Result<Vec<TaskEntry>, LlmError> {
+        parse_json::<NestTaskList>(body).map(|response| response.list)
+    }
+
+    fn parse_chat(body: &str) -> Result<String, LlmError> {
+        parse_json::<NestChatResponse>(body).map(|response| response.content)
+    }
+
+    async fn post(
+        &self,
+        client: &Client,
+        endpoint: &str,
+        prompt: &str,
+        model: Option<&str>,
+    ) -> Result<String, LlmError> {
+        let url = format!("{}{endpoint}", self.api);
+        let body = Self::request_body(prompt, model);
+        post_json(client, &url, "api-key", &self.api_key, &body).await
+    }
+}
+
+impl LlmProvider for NestProvider {
+    fn create_items<'a>(
+        &'a self,
+        client: &'a Client,
+        prompt: &'a str,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<TaskEntry>, LlmError>> {
+        Box::pin(async move {
+            let body = self.post(client, "/api/task", prompt, model).await?;
+            Self::parse_items(&body)
+        })
+    }
+
+    fn chat<'a>(
+        &'a self,
+        client: &'a Client,
+        prompt: &'a str,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<String, LlmError>> {
+        Box::pin(async move {
+            let body = self.post(client, "/api/chat", prompt, model).await?;
+            Self::parse_chat(&body)
+        })
+    }
+}
+
+/// Any OpenAI-compatible chat completions API, e.g. OpenAI itself, Ollama or vLLM
+pub struct OpenAiProvider {
+    /// Base URL including the version, e.g. `https://api.openai.com/v1`
+    api: String,
+    api_key: String,
+    /// Used when the settings don't pick a model, the API requires one
+    default_model: String,
+}
+
+/// Tells the model to answer in the shape [`NestTaskList`] has, which nest
+/// does on its own
+const ITEMS_FORMAT: &str =
+    "Answer with a JSON object of the form {\"list\": [...]} holding the items and nothing else.";
+
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatMessage<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletion {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChatCompletionMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionMessage {
+    content: Option<String>,
+}
+
+impl OpenAiProvider {
+    pub fn new(
+        api: impl Into<String>,
+        api_key: impl Into<String>,
+        default_model: impl Into<String>,
+    ) -> Self {
+        OpenAiProvider {
+            api: api.into(),
+            api_key: api_key.into(),
+            default_model: default_model.into(),
+        }
+    }
+
+    fn items_body<'a>(
+        &'a self,
+        prompt: &'a str,
+        model: Option<&'a str>,
+    ) -> ChatCompletionRequest<'a> {
+        ChatCompletionRequest {
+            model: model.unwrap_or(&self.default_model),
+            messages: vec![
+                ChatMessage {
+                    role: "system",
+                    content: ITEMS_FORMAT,
+                },
+                ChatMessage {
+                    role: "user",
+                    content: prompt,
+                },
+            ],
+            response_format: Some(ResponseFormat {
+                kind: "json_object",
+            }),
+        }
+    }
+
+    fn chat_body<'a>(
+        &'a self,
+        prompt: &'a str,
+        model: Option<&'a str>,
+    ) -> ChatCompletionRequest<'a> {
+        ChatCompletionRequest {
+            model: model.unwrap_or(&self.default_model),
+            messages: vec![ChatMessage {
+                role: "user",
+                content: prompt,
+            }],
+            response_format: None,
+        }
+    }
+
+    fn parse_chat(body: &str) -> Result<String, LlmError> {
+        let completion = parse_json::<ChatCompletion>(body)?;
+        completion
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or_else(|| LlmError::Parse("Response has no message content".to_string()))
+    }
+
+    fn parse_items(body: &str) -> Result<Vec<TaskEntry>, LlmError> {
+        let content = Self::parse_chat(body)?;
+        parse_json::<NestTaskList>(&content).map(|response| response.list)
+    }
+
+    async fn post(
+        &self,
+        client: &Client,
+        body: &ChatCompletionRequest<'_>,
+    ) -> Result<String, LlmError> {
+        let url = format!("{}/chat/completions", self.api.trim_end_matches('/'));
+        let bearer = format!("Bearer {}", self.api_key);
+        post_json(client, &url, "Authorization", &bearer, body).await
+    }
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn create_items<'a>(
+        &'a self,
+        client: &'a Client,
+        prompt: &'a str,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<Vec<TaskEntry>, LlmError>> {
+        Box::pin(async move {
+            let body = self.post(client, &self.items_body(prompt, model)).await?;
+            Self::parse_items(&body)
+        })
+    }
+
+    fn chat<'a>(
+        &'a self,
+        client: &'a Client,
+        prompt: &'a str,
+        model: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<String, LlmError>> {
+        Box::pin(async move {
+            let body = self.post(client, &self.chat_body(prompt, model)).await?;
+            Self::parse_chat(&body)
+        })
+    }
+}
+
+/// Characters of an unparsable body kept in the error, enough to tell a
+/// gateway's HTML page from JSON of the wrong shape
+const BODY_SNIPPET_LEN: usize = 300;
+
+fn parse_json<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, LlmError> {
+    serde_json::from_str(body).map_err(|e| {
+        let mut snippet: String = body.chars().take(BODY_SNIPPET_LEN).collect();
+        if snippet.len() < body.len() {
+            snippet.push_str("...");
+        }
+        LlmError::Parse(format!("Failed to parse response: {e}, body: {snippet}"))
+    })
+}
+
+/// Send failures and server errors of the LLM API are retried
+const LLM_RETRY: RetryPolicy = RetryPolicy {
+    max_attempts: 3,
+    base: Duration::from_millis(200),
+    max: Duration::from_secs(2),
+    jitter: 0.25,
+};
+
+/// Posts `body` and returns the response text, retrying transient failures;
+/// other error statuses, e.g. a rejected key, fail right away
+async fn post_json<B: Serialize>(
+    client: &Client,
+    url: &str,
+    auth_header: &str,
+    auth_value: &str,
+    body: &B,
+) -> Result<String, LlmError> {
+    retry::with_backoff(
+        &LLM_RETRY,
+        |err| matches!(err, LlmError::Request(_)),
+        |attempt| {
+            info!("calling: {url} (attempt {attempt})");
+            let request = client.post(url).header(auth_header, auth_value).json(body);
+            async move {
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| LlmError::Request(format!("Failed to send request: {e}")))?;
+                let status = response.status();
+                if status.is_success() {
+                    return response
+                        .text()
+                        .await
+                        .map_err(|e| LlmError::Request(format!("Failed to read response: {e}")));
+                }
+                let error_text = response.text().await.unwrap_or_default();
+                let message = format!("API returned status {status}: {error_text}");
+                if status.is_server_error() {
+                    Err(LlmError::Request(message))
+                } else {
+                    Err(LlmError::Auth(message))
+                }
+            }
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PROMPT: &str = "Create only grocery items: milk";
+
+    fn tasks(entries: &[TaskEntry]) -> Vec<(&str, Option<f32>)> {
+        entries.iter().map(|e| (e.task(), e.confidence())).collect()
+    }
+
+    fn openai() -> OpenAiProvider {
+        OpenAiProvider::new("https://api.example.com/v1", "key", "default-model")
+    }
+
+    #[test]
+    fn test_nest_serializes_prompt() {
+        let body = serde_json::to_value(NestProvider::request_body(PROMPT, Some("small"))).unwrap();
+        assert_eq!(body, json!({"prompt": PROMPT, "model": "small"}));
+        let body = serde_json::to_value(NestProvider::request_body(PROMPT, None)).unwrap();
+        assert_eq!(body, json!({"prompt": PROMPT}));
+    }
+
+    #[test]
+    fn test_openai_serializes_messages() {
+        let provider = openai();
+        let body = serde_json::to_value(provider.items_body(PROMPT, Some("small"))).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "model": "small",
+                "messages": [
+                    {"role": "system", "content": ITEMS_FORMAT},
+                    {"role": "user", "content": PROMPT},
+                ],
+                "response_format": {"type": "json_object"},
+            })
+        );
+        let body = serde_json::to_value(provider.chat_body(PROMPT, None)).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "model": "default-model",
+                "messages": [{"role": "user", "content": PROMPT}],
+            })
+        );
+    }
+
+    #[test]
+    fn test_adapters_parse_the_same_items() {
+        let nest =
+            NestProvider::parse_items(r#"{"list": ["milk", {"task": "eggs", "confidence": 0.5}]}"#)
+                .unwrap();
+        let openai = OpenAiProvider::parse_items(
+            &json!({
+                "choices": [{"message": {
+                    "role": "assistant",
+                    "content": r#"{"list": ["milk", {"task": "eggs", "confidence": 0.5}]}"#,
+                }}]
+            })
+            .to_string(),
+        )
+        .unwrap();
+        let expected = vec![("milk", None), ("eggs", Some(0.5))];
+        assert_eq!(tasks(&nest), expected);
+        assert_eq!(tasks(&openai), expected);
+    }
+
+    #[test]
+    fn test_adapters_parse_the_same_chat_answer() {
+        let nest = NestProvider::parse_chat(r#"{"content": "Make an *omelette*."}"#).unwrap();
+        let openai = OpenAiProvider::parse_chat(
+            r#"{"choices": [{"message": {"role": "assistant", "content": "Make an *omelette*."}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(nest, "Make an *omelette*.");
+        assert_eq!(openai, nest);
+        assert!(matches!(
+            OpenAiProvider::parse_chat(r#"{"choices": []}"#),
+            Err(LlmError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_error_shows_body_snippet() {
+        let page = format!(
+            "<html><body><h1>502 Bad Gateway</h1>{}</body></html>",
+            "x".repeat(1000)
+        );
+        let Err(LlmError::Parse(message)) = NestProvider::parse_items(&page) else {
+            panic!("HTML body should not parse");
+        };
+        assert!(message.contains("<html><body><h1>502 Bad Gateway</h1>"));
+        assert!(message.ends_with("..."));
+        assert!(message.len() < 400);
+    }
+}
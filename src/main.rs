@@ -4,15 +4,24 @@ use std::sync::{Arc, Mutex};
 
 use crate::{database::DBClient, view::items};
 
+mod checklist;
 mod config;
+mod content_filter;
 mod csv;
 mod database;
+mod i18n;
 mod llm;
+mod llm_provider;
+mod normalize;
+mod quantity;
+mod retry;
 mod routes;
 mod scrapy;
+mod similarity;
 mod unsafe_token_decode;
 mod user;
 mod view;
+mod webhooks;
 mod witch;
 
 pub struct Reload(bool);
@@ -46,10 +55,18 @@ async fn main() -> std::io::Result<()> {
     let c = config::from_env();
     let bind = c.clone();
 
-    let orm_db = database::create_orm_client(c.db_url(), c.db_token()).await;
+    let orm_db = database::create_orm_client(c.db_url(), c.db_token())
+        .await
+        .with_normalization(c.text_normalization());
 
     let shared_orm_db: DBClient = Arc::new(Mutex::new(orm_db));
     database::migrations::run(&shared_orm_db).await;
+    if let Some(every) = c.maintenance_interval() {
+        actix_web::rt::spawn(database::maintenance::run_periodically(
+            shared_orm_db.clone(),
+            every,
+        ));
+    }
 
     let reload: ReloadArc = Arc::new(Mutex::new(Reload::default()));
 
@@ -75,13 +92,48 @@ async fn main() -> std::io::Result<()> {
             .service(items::index_route)
             .service(routes::messages::send_message)
             .service(routes::items::create_item_with_ai)
+            .service(routes::items::create_items_chunked)
+            .service(routes::items::create_meal_plan)
             .service(routes::items::create_item)
+            .service(routes::items::append_item)
+            .service(routes::items::move_item)
+            .service(routes::items::reorder_items)
             .service(routes::items::toggle_item)
             .service(routes::items::delete_item)
             .service(routes::items::update_item)
             .service(routes::items::edit_item)
             .service(routes::items::cancel_edit_item)
             .service(routes::items::items_csv)
+            .service(routes::items::items_checklist)
+            .service(routes::items::items_events_ndjson)
+            .service(routes::items::dry_run_item_list)
+            .service(routes::items::active_items)
+            .service(routes::items::items_due_between)
+            .service(routes::items::quantity_totals)
+            .service(routes::items::list_total)
+            .service(routes::items::similar_items)
+            .service(routes::items::suggest_items)
+            .service(routes::items::claim_item)
+            .service(routes::items::release_claim)
+            .service(routes::attachments::attachment_counts)
+            .service(routes::attachments::add_attachment)
+            .service(routes::attachments::list_attachments)
+            .service(routes::attachments::remove_attachment)
+            .service(routes::shopping::begin_shopping_session)
+            .service(routes::shopping::session_items)
+            .service(routes::shopping::end_shopping_session)
+            .service(routes::stores::set_store_layout)
+            .service(routes::stores::store_items)
+            .service(routes::sync::apply_commands)
+            .service(routes::sync::commands_since)
+            .service(routes::tags::items_with_tags)
+            .service(routes::tags::items_with_tag)
+            .service(routes::tags::add_tag)
+            .service(routes::tags::remove_tag)
+            .service(routes::user_data::export_user_data)
+            .service(routes::user_data::delete_user_data)
+            .service(routes::webhooks::register_webhook)
+            .service(routes::webhooks::list_webhooks)
             .service(routes::technical::health)
             .service(routes::technical::should_reload)
             .service(routes::assets::scope())
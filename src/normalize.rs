@@ -0,0 +1,75 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Options for folding pasted item text into a comparable form
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextNormalization {
+    pub strip_emoji: bool,
+}
+
+/// NFC-normalizes `text`, folds curly quotes to straight ones and optionally drops emoji
+pub fn normalize_text(text: &str, options: &TextNormalization) -> String {
+    let folded: String = text
+        .nfc()
+        .filter(|c| !(options.strip_emoji && is_emoji(*c)))
+        .map(fold_quote)
+        .collect();
+    if options.strip_emoji {
+        folded.split_whitespace().collect::<Vec<_>>().join(" ")
+    } else {
+        folded.trim().to_string()
+    }
+}
+
+fn fold_quote(c: char) -> char {
+    match c {
+        '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' => '\'',
+        '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' => '"',
+        c => c,
+    }
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(
+        c,
+        '\u{1F000}'..='\u{1FAFF}' | '\u{2600}'..='\u{27BF}' | '\u{FE0F}' | '\u{200D}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curly_and_straight_quotes_match() {
+        let options = TextNormalization::default();
+        assert_eq!(
+            normalize_text("don\u{2019}t forget", &options),
+            normalize_text("don't forget", &options)
+        );
+        assert_eq!(
+            normalize_text("\u{201C}bio\u{201D} milk", &options),
+            "\"bio\" milk"
+        );
+    }
+
+    #[test]
+    fn test_nfc_composes_accents() {
+        let options = TextNormalization::default();
+        assert_eq!(normalize_text("cafe\u{0301}", &options), "caf\u{00E9}");
+    }
+
+    #[test]
+    fn test_emoji_stripping_is_optional() {
+        assert_eq!(
+            normalize_text("milk \u{1F95B} ", &TextNormalization::default()),
+            "milk \u{1F95B}"
+        );
+        assert_eq!(
+            normalize_text(
+                "milk \u{1F95B} and \u{2764}\u{FE0F} eggs",
+                &TextNormalization { strip_emoji: true }
+            ),
+            "milk and eggs"
+        );
+    }
+}
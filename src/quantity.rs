@@ -0,0 +1,171 @@
+use regex::Regex;
+
+use crate::i18n;
+
+/// Which characters separate decimals and thousands in numbers
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NumberLocale {
+    /// `1,500.5` (English and most of Asia)
+    #[default]
+    DecimalPoint,
+    /// `1.500,5` (German, French, Spanish, ...)
+    DecimalComma,
+}
+
+/// Languages writing decimals with a comma
+const DECIMAL_COMMA_LANGUAGES: &[&str] = &[
+    "de", "fr", "es", "it", "nl", "pt", "da", "sv", "nb", "no", "fi", "pl", "cs", "ru", "tr",
+];
+
+impl NumberLocale {
+    /// Locale of a language tag like `de-AT` or an `Accept-Language` value,
+    /// using its first language
+    pub fn from_language(language: &str) -> Self {
+        let primary = i18n::primary_language(language);
+        if DECIMAL_COMMA_LANGUAGES.contains(&primary.as_str()) {
+            NumberLocale::DecimalComma
+        } else {
+            NumberLocale::DecimalPoint
+        }
+    }
+
+    fn separators(&self) -> (char, char) {
+        match self {
+            NumberLocale::DecimalPoint => ('.', ','),
+            NumberLocale::DecimalComma => (',', '.'),
+        }
+    }
+}
+
+/// Parses a number written in `locale`
+///
+/// Returns `None` for input that doesn't fit the locale, e.g. `1,5` with a
+/// decimal point locale, instead of guessing which separator was meant.
+pub fn parse_number(text: &str, locale: NumberLocale) -> Option<f64> {
+    let (decimal, thousands) = locale.separators();
+    let (integer, fraction) = match text.split_once(decimal) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (text, None),
+    };
+
+    let groups: Vec<&str> = integer.split(thousands).collect();
+    let grouped_correctly = groups.len() == 1
+        || (!groups[0].is_empty()
+            && groups[0].len() <= 3
+            && groups[1..].iter().all(|g| g.len() == 3));
+    let all_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if !grouped_correctly || !groups.iter().all(|g| all_digits(g)) {
+        return None;
+    }
+    if fraction.is_some_and(|f| !all_digits(f)) {
+        return None;
+    }
+
+    let mut normalized = groups.concat();
+    if let Some(fraction) = fraction {
+        normalized.push('.');
+        normalized.push_str(fraction);
+    }
+    normalized.parse().ok()
+}
+
+/// Units recognised right after a leading quantity
+const UNITS: &[&str] = &[
+    "g", "kg", "mg", "l", "ml", "cl", "dl", "lb", "lbs", "oz", "pcs", "pc", "x", "tbsp", "tsp",
+    "cup", "cups", "pack", "packs", "can", "cans", "bottle", "bottles", "stk", "stück", "dose",
+    "dosen", "packung", "flasche", "el", "tl",
+];
+
+lazy_static::lazy_static! {
+    static ref LEADING_QUANTITY_REGEX: Regex =
+        Regex::new(r"^(\d[\d.,]*)(\s*)(\p{L}+\.?)?\s+(.+)$").unwrap();
+}
+
+/// Splits `"1,5 kg flour"` into `(Some(1.5), Some("kg"), "flour")`
+///
+/// Text without a leading quantity, or with one that doesn't parse in `locale`,
+/// is returned unchanged. A number only counts as a quantity when a space or
+/// a known unit follows it, so `"7up soda"` and `"3M tape"` stay names.
+pub fn split_quantity(task: &str, locale: NumberLocale) -> (Option<f64>, Option<String>, String) {
+    let task = task.trim();
+    let unchanged = (None, None, task.to_string());
+    let Some(captures) = LEADING_QUANTITY_REGEX.captures(task) else {
+        return unchanged;
+    };
+    let Some(quantity) = parse_number(&captures[1], locale) else {
+        return unchanged;
+    };
+
+    let spaced = !captures[2].is_empty();
+    let rest = captures[4].to_string();
+    match captures.get(3).map(|m| m.as_str()) {
+        Some(word) => {
+            let unit = word.trim_end_matches('.').to_lowercase();
+            if UNITS.contains(&unit.as_str()) {
+                (Some(quantity), Some(unit), rest)
+            } else if spaced {
+                (Some(quantity), None, format!("{word} {rest}"))
+            } else {
+                unchanged
+            }
+        }
+        None => (Some(quantity), None, rest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_comma_decimal_under_german_locale() {
+        let de = NumberLocale::from_language("de-DE,de;q=0.9,en;q=0.8");
+        assert_eq!(de, NumberLocale::DecimalComma);
+        assert_eq!(parse_number("1,5", de), Some(1.5));
+        assert_eq!(parse_number("1.500", de), Some(1500.0));
+        assert_eq!(parse_number("1.500,25", de), Some(1500.25));
+        assert_eq!(parse_number("1.5", de), None);
+    }
+
+    #[test]
+    fn test_parse_thousands_under_us_locale() {
+        let us = NumberLocale::from_language("en-US");
+        assert_eq!(us, NumberLocale::DecimalPoint);
+        assert_eq!(parse_number("1,500", us), Some(1500.0));
+        assert_eq!(parse_number("1,500.5", us), Some(1500.5));
+        assert_eq!(parse_number("2", us), Some(2.0));
+        assert_eq!(parse_number("1,5", us), None);
+        assert_eq!(parse_number("1.500,5", us), None);
+        assert_eq!(parse_number("1..5", us), None);
+    }
+
+    #[test]
+    fn test_split_quantity_and_unit() {
+        let de = NumberLocale::DecimalComma;
+        assert_eq!(
+            split_quantity("1,5 kg Mehl", de),
+            (Some(1.5), Some("kg".to_string()), "Mehl".to_string())
+        );
+        assert_eq!(
+            split_quantity("500g flour", NumberLocale::DecimalPoint),
+            (Some(500.0), Some("g".to_string()), "flour".to_string())
+        );
+        assert_eq!(
+            split_quantity("2 ripe avocados", de),
+            (Some(2.0), None, "ripe avocados".to_string())
+        );
+        assert_eq!(
+            split_quantity("1,5 kg flour", NumberLocale::DecimalPoint),
+            (None, None, "1,5 kg flour".to_string())
+        );
+        assert_eq!(split_quantity("milk", de), (None, None, "milk".to_string()));
+        assert_eq!(
+            split_quantity("7up soda", de),
+            (None, None, "7up soda".to_string())
+        );
+        assert_eq!(
+            split_quantity("3M tape", NumberLocale::DecimalPoint),
+            (None, None, "3M tape".to_string())
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+
+/// How often and how patiently an operation is retried
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts including the first one
+    pub max_attempts: u32,
+    /// Delay after the first failure, doubled after every further one
+    pub base: Duration,
+    /// Upper bound of a single delay
+    pub max: Duration,
+    /// Fraction of each delay that is randomized, `0.0` for fixed delays
+    pub jitter: f64,
+}
+
+impl RetryPolicy {
+    /// Delay before attempt `attempt + 1`, without jitter
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base.saturating_mul(factor).min(self.max)
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return backoff;
+        }
+        backoff.mul_f64(1.0 - rand::rng().random_range(0.0..jitter))
+    }
+}
+
+/// Runs `op` until it succeeds, fails with an error `is_retryable` rejects, or
+/// `policy.max_attempts` are used up; the last error is returned
+///
+/// `op` gets the 1-based attempt number.
+pub async fn with_backoff<T, E, Op, Fut>(
+    policy: &RetryPolicy,
+    is_retryable: impl Fn(&E) -> bool,
+    mut op: Op,
+) -> Result<T, E>
+where
+    Op: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match op(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(err) if attempt >= policy.max_attempts || !is_retryable(&err) => return Err(err),
+            Err(_) => {
+                tokio::time::sleep(policy.delay(attempt)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const FAST: RetryPolicy = RetryPolicy {
+        max_attempts: 3,
+        base: Duration::from_millis(1),
+        max: Duration::from_millis(5),
+        jitter: 0.5,
+    };
+
+    #[tokio::test]
+    async fn test_stops_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), String> = with_backoff(
+            &FAST,
+            |_| true,
+            |attempt| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async move { Err(format!("attempt {attempt} failed")) }
+            },
+        )
+        .await;
+        assert_eq!(result, Err("attempt 3 failed".to_string()));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_does_not_retry_non_retryable_errors() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), &str> = with_backoff(
+            &FAST,
+            |err: &&str| *err != "unauthorized",
+            |_| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async { Err("unauthorized") }
+            },
+        )
+        .await;
+        assert_eq!(result, Err("unauthorized"));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_returns_first_success() {
+        let result: Result<u32, &str> = with_backoff(
+            &FAST,
+            |_| true,
+            |attempt| async move {
+                if attempt < 2 {
+                    Err("busy")
+                } else {
+                    Ok(attempt)
+                }
+            },
+        )
+        .await;
+        assert_eq!(result, Ok(2));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            jitter: 0.0,
+        };
+        let delays: Vec<u128> = (1..=5).map(|a| policy.delay(a).as_millis()).collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+
+        let jittered = RetryPolicy {
+            jitter: 0.5,
+            ..policy
+        };
+        for attempt in 1..=5 {
+            let delay = jittered.delay(attempt);
+            assert!(delay <= policy.backoff(attempt));
+            assert!(delay >= policy.backoff(attempt) / 2);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, post, web};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{self, DBClient};
+
+#[derive(Deserialize)]
+pub struct AddAttachmentRequest {
+    pub url: String,
+    pub content_type: String,
+}
+
+/// An item with the number of attachments it holds
+#[derive(Serialize)]
+pub struct AttachmentCountResponse {
+    pub item: database::items::Item,
+    pub attachment_count: u64,
+}
+
+#[post("/items/{id}/attachments")]
+pub async fn add_attachment(
+    path: web::Path<i64>,
+    form: web::Form<AddAttachmentRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let form = form.into_inner();
+    let res = database::attachments::add_attachment(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+        form.url,
+        form.content_type,
+    )
+    .await;
+
+    match res {
+        Ok(attachment) => Ok(HttpResponse::Created().json(attachment)),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[get("/items/{id}/attachments")]
+pub async fn list_attachments(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let attachments = database::attachments::list_attachments(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(attachments))
+}
+
+#[delete("/attachments/{id}")]
+pub async fn remove_attachment(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::attachments::remove_attachment(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+    )
+    .await;
+
+    match res {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[get("/items/attachment-counts")]
+pub async fn attachment_counts(
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let counts = database::attachments::get_items_with_attachment_counts(
+        client.get_ref(),
+        user.id().to_string(),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(
+        counts
+            .into_iter()
+            .map(|(item, attachment_count)| AttachmentCountResponse {
+                item,
+                attachment_count,
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
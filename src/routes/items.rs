@@ -1,14 +1,20 @@
 use actix_web::error::ParseError;
 use actix_web::http::header::CONTENT_DISPOSITION;
-use actix_web::{HttpRequest, HttpResponse, Result, delete, get, patch, post, web};
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, patch, post, put, web};
 use chrono::Utc;
+use futures_util::StreamExt;
+use libsql_orm::{Pagination, SortOrder};
 use log::info;
 use maud::{Markup, html};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
+use crate::checklist;
 use crate::config::Server;
 use crate::csv;
+use crate::database::items::{ItemListQuery, ItemSort, MissingQuantity, RangeBounds};
 use crate::database::{self, DBClient};
+use crate::llm;
+use crate::similarity;
 use crate::view::{self, message, render_item};
 
 #[derive(Deserialize)]
@@ -30,14 +36,7 @@ pub async fn create_item(
     let client: &DBClient = client.get_ref();
     let user = super::get_user(req).unwrap();
 
-    let item = database::items::Item {
-        id: None,
-        owner_id: user.id().to_string(),
-        task: form.task.clone(),
-        completed: 0,
-        created_at: chrono::Utc::now(),
-        updated_at: chrono::Utc::now(),
-    };
+    let item = database::items::Item::new(user.id().to_string(), form.task.clone());
     let res = database::items::create_item(client, item.clone()).await;
 
     let Ok(item) = res else { return Ok(html!()) };
@@ -149,6 +148,8 @@ pub async fn create_item_with_ai(
     config: web::Data<Server>,
     req: HttpRequest,
 ) -> Result<Markup> {
+    let settings = form.llm_settings(&config, &req);
+    let language = super::language(&req);
     let user = super::get_user(req).unwrap();
     // delay if delay is on
     if config.delay() {
@@ -161,10 +162,11 @@ pub async fn create_item_with_ai(
     // Generate AI response
     let ai_response = super::generate_task_response(
         &form.message,
-        &config.nest_api(),
-        &config.nest_api_key(),
+        config.llm_provider().as_ref(),
         db_client,
         user.id().to_string(),
+        &settings,
+        language,
     )
     .await;
 
@@ -197,6 +199,45 @@ pub async fn create_item_with_ai(
     })
 }
 
+#[get("/items/events.ndjson")]
+pub async fn items_events_ndjson(
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let events = database::item_events::export_events_ndjson(
+        client.get_ref().clone(),
+        user.id().to_string(),
+    )
+    .map(|line| {
+        line.map(web::Bytes::from)
+            .map_err(actix_web::error::ErrorInternalServerError)
+    });
+
+    Ok(HttpResponse::Ok()
+        .append_header((
+            CONTENT_DISPOSITION,
+            "attachment; filename=\"item-events.ndjson\"",
+        ))
+        .content_type("application/x-ndjson")
+        .streaming(events))
+}
+
+#[get("/items/checklist.txt")]
+pub async fn items_checklist(
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let checklist = checklist::export_checklist(client.get_ref(), user.id().to_string())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(checklist))
+}
+
 #[get("/items/csv")]
 pub async fn items_csv(client: web::Data<DBClient>, req: HttpRequest) -> Result<HttpResponse> {
     let user = super::get_user(req).unwrap();
@@ -214,3 +255,411 @@ pub async fn items_csv(client: web::Data<DBClient>, req: HttpRequest) -> Result<
 
     Ok(response)
 }
+
+#[derive(Deserialize)]
+pub struct ItemListParams {
+    pub completed: Option<bool>,
+    #[serde(default)]
+    pub sort: ItemSort,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// SQL a list request would run, with its params redacted to their types
+#[derive(Serialize)]
+pub struct DryRunResponse {
+    pub sql: String,
+    pub params: Vec<String>,
+}
+
+/// Shows the SQL of the item list for the given filter, sort and page
+/// without running it, for debugging and support tickets
+#[get("/items/dry-run")]
+pub async fn dry_run_item_list(
+    params: web::Query<ItemListParams>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let params = params.into_inner();
+    let query = ItemListQuery {
+        completed: params.completed,
+        sort: params.sort,
+        order: params.order,
+        pagination: Pagination::page(params.page.unwrap_or(1), params.per_page.unwrap_or(20)),
+    };
+
+    match database::items::dry_run_item_list(user.id(), &query) {
+        Ok((sql, params)) => Ok(HttpResponse::Ok().json(DryRunResponse { sql, params })),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[get("/items/active")]
+pub async fn active_items(client: web::Data<DBClient>, req: HttpRequest) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let items = database::items::active_items(client.get_ref(), user.id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
+
+#[derive(Deserialize)]
+pub struct DueBetweenQuery {
+    pub start: chrono::DateTime<Utc>,
+    pub end: chrono::DateTime<Utc>,
+    #[serde(default)]
+    pub bounds: RangeBounds,
+}
+
+/// Items due in a range, e.g. `?start=2024-05-01T00:00:00Z&end=2024-05-02T00:00:00Z`
+/// for one calendar day
+#[get("/items/due")]
+pub async fn items_due_between(
+    query: web::Query<DueBetweenQuery>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let items = database::items::items_due_between(
+        client.get_ref(),
+        user.id(),
+        query.start,
+        query.end,
+        query.bounds,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
+
+/// Adds an item at the end of the manually ordered list
+#[post("/items/append")]
+pub async fn append_item(
+    form: web::Form<CreateTodoRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let item = database::items::Item::new(user.id().to_string(), form.task.clone());
+
+    match database::items::append_item(client.get_ref(), item).await {
+        Ok(item) => Ok(HttpResponse::Created().json(item)),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct MoveItemRequest {
+    pub position: i64,
+}
+
+#[patch("/items/{id}/position")]
+pub async fn move_item(
+    path: web::Path<i64>,
+    form: web::Form<MoveItemRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::items::move_item(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+        form.position,
+    )
+    .await;
+
+    match res {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+/// Rewrites the list order, the body lists every placed item id first to last
+#[put("/items/order")]
+pub async fn reorder_items(
+    ids: web::Json<Vec<i64>>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::items::reorder(client.get_ref(), user.id().to_string(), &ids).await;
+
+    match res {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct QuantityTotalsQuery {
+    #[serde(default)]
+    pub missing: MissingQuantity,
+}
+
+#[get("/items/quantities")]
+pub async fn quantity_totals(
+    query: web::Query<QuantityTotalsQuery>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let totals = database::items::quantity_totals(client.get_ref(), user.id(), query.missing)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(totals))
+}
+
+/// What the active items cost, one total per currency
+#[get("/items/total")]
+pub async fn list_total(client: web::Data<DBClient>, req: HttpRequest) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let totals = database::items::list_total(client.get_ref(), user.id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(totals))
+}
+
+#[derive(Deserialize)]
+pub struct SimilarItemsQuery {
+    pub task: String,
+    /// Allowed edits, by default the near-duplicate threshold of the task length
+    pub max_distance: Option<usize>,
+}
+
+/// Items spelled almost like `task`, for "is this already on my list?"
+#[get("/items/similar")]
+pub async fn similar_items(
+    query: web::Query<SimilarItemsQuery>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let max_distance = query
+        .max_distance
+        .unwrap_or_else(|| similarity::near_duplicate_distance(&query.task));
+    let similar = database::items::find_similar(
+        client.get_ref(),
+        user.id().to_string(),
+        &query.task,
+        max_distance,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(similar))
+}
+
+/// Claims one of the caller's items, 409 when it's held already and 404 when
+/// the caller has no such item
+#[post("/items/{id}/claim")]
+pub async fn claim_item(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let claimed =
+        database::items::claim_item(client.get_ref(), path.into_inner(), user.id().to_string())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    match claimed {
+        Some(true) => Ok(HttpResponse::NoContent().finish()),
+        Some(false) => Ok(HttpResponse::Conflict().body("Item is claimed already")),
+        None => Ok(HttpResponse::NotFound().body("Item not found")),
+    }
+}
+
+/// Releases the caller's claim, 404 when they hold none
+#[delete("/items/{id}/claim")]
+pub async fn release_claim(
+    path: web::Path<i64>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let released =
+        database::items::release_claim(client.get_ref(), path.into_inner(), user.id().to_string())
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    if released {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Ok(HttpResponse::NotFound().body("No claim to release"))
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SuggestionsQuery {
+    pub prefix: String,
+    pub limit: Option<u32>,
+    #[serde(default)]
+    pub exclude_active: bool,
+}
+
+/// Tasks added before that start with `prefix`, for auto-completion
+#[get("/items/suggestions")]
+pub async fn suggest_items(
+    query: web::Query<SuggestionsQuery>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let suggestions = database::item_events::suggest_items(
+        client.get_ref(),
+        user.id().to_string(),
+        &query.prefix,
+        query.limit.unwrap_or(10),
+        query.exclude_active,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(suggestions))
+}
+
+#[derive(Deserialize)]
+pub struct ChunkedItemsRequest {
+    pub message: String,
+    /// Chunk to start at, to resume an extraction that failed part way
+    #[serde(default)]
+    pub from_chunk: usize,
+}
+
+/// One line of the chunked extraction stream
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum ChunkedItemsEvent {
+    Progress {
+        #[serde(flatten)]
+        progress: llm::ExtractionProgress,
+        message: String,
+    },
+    Failed {
+        error: String,
+    },
+}
+
+/// Extracts the items of a long paste chunk by chunk, streaming one NDJSON
+/// progress line per stored chunk
+///
+/// The stream ends with an `error` line when a chunk fails; the client can
+/// resume with `from_chunk` set to the chunk after the last reported one.
+#[post("/ai/items/chunked")]
+pub async fn create_items_chunked(
+    form: web::Form<ChunkedItemsRequest>,
+    client: web::Data<DBClient>,
+    config: web::Data<Server>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let settings = config
+        .llm_settings()
+        .with_number_locale(super::number_locale(&req));
+    let language = super::language(&req);
+    let user = super::get_user(req).unwrap();
+    let provider = config.llm_provider();
+    let db_client = client.get_ref().clone();
+    let form = form.into_inner();
+
+    let (events, received) = tokio::sync::mpsc::channel(4);
+    actix_web::rt::spawn(async move {
+        let progress = llm::chunked_item_response(
+            provider.as_ref(),
+            &form.message,
+            user.id().to_string(),
+            &db_client,
+            &settings,
+            form.from_chunk,
+        );
+        futures_util::pin_mut!(progress);
+        while let Some(event) = progress.next().await {
+            let event = match event {
+                Ok(progress) => ChunkedItemsEvent::Progress {
+                    message: progress.message(language),
+                    progress,
+                },
+                Err(err) => {
+                    log::error!("chunked extraction failed: {err:?}");
+                    ChunkedItemsEvent::Failed {
+                        error: "Something went wrong contacting the agent".to_string(),
+                    }
+                }
+            };
+            if events.send(event).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let lines = futures_util::stream::unfold(received, |mut received| async move {
+        let event = received.recv().await?;
+        Some((event, received))
+    })
+    .map(|event| {
+        serde_json::to_string(&event)
+            .map(|line| web::Bytes::from(line + "\n"))
+            .map_err(actix_web::error::ErrorInternalServerError)
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(lines))
+}
+
+#[derive(Deserialize)]
+pub struct MealPlanRequest {
+    pub days: u8,
+    #[serde(default)]
+    pub preferences: String,
+}
+
+/// Plans meals for some days and puts their ingredients on the list
+#[post("/ai/meal-plan")]
+pub async fn create_meal_plan(
+    form: web::Form<MealPlanRequest>,
+    client: web::Data<DBClient>,
+    config: web::Data<Server>,
+    req: HttpRequest,
+) -> Result<Markup> {
+    let settings = config
+        .llm_settings()
+        .with_number_locale(super::number_locale(&req));
+    let language = super::language(&req);
+    let user = super::get_user(req).unwrap();
+
+    let plan = llm::meal_plan_to_groceries(
+        config.llm_provider().as_ref(),
+        form.days,
+        &form.preferences,
+        user.id().to_string(),
+        client.get_ref(),
+        &settings,
+        &llm::LogMetrics,
+    )
+    .await;
+    let content = match plan {
+        Ok(plan) => format!("{}\n\n{}", plan.content, plan.summary.message(language)),
+        Err(err) => {
+            log::error!("could not plan meals: {err:?}");
+            "Something went wrong contacting the agent".to_string()
+        }
+    };
+
+    let ai_message = database::messages::ChatMessage {
+        id: None,
+        content: content.clone(),
+        ai_response: content,
+        owner_id: "Agent".to_string(),
+        created_at: Utc::now(),
+        is_user: false,
+    };
+    Ok(message::render(&ai_message, None))
+}
@@ -8,11 +8,28 @@ use url::Url;
 use crate::config::Server;
 use crate::database::{self, DBClient};
 use crate::view::message;
-use crate::witch;
+use crate::{llm, witch};
 
 #[derive(Deserialize)]
 pub struct SendMessageRequest {
     pub message: String,
+    /// `cheap` or `strong` model of the configured policy instead of the one
+    /// it picks, other model names can't be chosen by clients
+    pub model: Option<llm::ModelTier>,
+}
+
+impl SendMessageRequest {
+    /// The server's LLM settings for this request's language and model
+    pub fn llm_settings(&self, config: &Server, req: &HttpRequest) -> llm::LlmSettings {
+        let settings = config
+            .llm_settings()
+            .with_number_locale(super::number_locale(req));
+        let model = match (self.model, &settings.model_policy) {
+            (Some(tier), Some(policy)) => policy.model(tier).to_string(),
+            _ => return settings,
+        };
+        settings.with_model(model)
+    }
 }
 
 #[post("chat")]
@@ -22,6 +39,8 @@ pub async fn send_message(
     config: web::Data<Server>,
     req: HttpRequest,
 ) -> Result<Markup> {
+    let settings = form.llm_settings(&config, &req);
+    let language = super::language(&req);
     let user = super::get_user(req).unwrap();
 
     log::info!("Received chat message: {}", form.message);
@@ -48,16 +67,23 @@ pub async fn send_message(
 
             super::generate_task_response(
                 &hex,
-                &config.nest_api(),
-                &config.nest_api_key(),
+                config.llm_provider().as_ref(),
                 db_client,
                 user.id().to_string(),
+                &settings,
+                language,
             )
             .await
         }
         Err(_) => {
-            super::generate_ai_response(&form.message, &config.nest_api(), &config.nest_api_key())
-                .await
+            super::generate_ai_response(
+                &form.message,
+                config.llm_provider().as_ref(),
+                db_client,
+                user.id().to_string(),
+                &settings,
+            )
+            .await
         }
     };
 
@@ -4,13 +4,23 @@ use log::error;
 use rand::Rng;
 
 use crate::database::DBClient;
+use crate::i18n::Language;
+use crate::llm_provider::LlmProvider;
+use crate::quantity::NumberLocale;
 use crate::{llm, unsafe_token_decode};
 
 pub mod assets;
+pub mod attachments;
 pub mod items;
 pub mod messages;
 pub mod recipes;
+pub mod shopping;
+pub mod stores;
+pub mod sync;
+pub mod tags;
 pub mod technical;
+pub mod user_data;
+pub mod webhooks;
 
 pub fn get_user(req: HttpRequest) -> Option<unsafe_token_decode::User> {
     req.extensions()
@@ -18,19 +28,53 @@ pub fn get_user(req: HttpRequest) -> Option<unsafe_token_decode::User> {
         .map(|u| u.as_ref().clone())
 }
 
+/// How the user writes numbers, from the request's `Accept-Language`
+pub fn number_locale(req: &HttpRequest) -> NumberLocale {
+    req.headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(NumberLocale::from_language)
+        .unwrap_or_default()
+}
+
+/// Language of the texts shown to the user, from the request's `Accept-Language`
+pub fn language(req: &HttpRequest) -> Language {
+    req.headers()
+        .get(actix_web::http::header::ACCEPT_LANGUAGE)
+        .and_then(|value| value.to_str().ok())
+        .map(Language::from_language)
+        .unwrap_or_default()
+}
+
 pub fn random_id() -> i64 {
     let mut rng = rand::rng();
     rng.random::<i64>()
 }
 
-async fn generate_ai_response(user_message: &str, nest_api: &str, nest_api_key: &str) -> String {
-    match llm::simple_chat_response(nest_api, nest_api_key, user_message).await {
-        Ok(a) => a,
+async fn generate_ai_response(
+    user_message: &str,
+    provider: &dyn LlmProvider,
+    db_client: &DBClient,
+    user_id: String,
+    settings: &llm::LlmSettings,
+) -> String {
+    match llm::simple_chat_response(
+        provider,
+        user_message,
+        user_id,
+        db_client,
+        settings,
+        &llm::LogMetrics,
+    )
+    .await
+    {
+        Ok(answer) => answer.content,
         Err(e) => {
             match e {
                 llm::LlmError::Request(error) => error!("{error}"),
                 llm::LlmError::Auth(error) => error!("{error}"),
                 llm::LlmError::Parse(error) => error!("{error}"),
+                llm::LlmError::Filtered(error) => error!("{error}"),
             };
 
             r"# Error
@@ -45,19 +89,29 @@ Something went wrong contacting the agent
 
 async fn generate_task_response(
     user_message: &str,
-    nest_api: &str,
-    nest_api_key: &str,
+    provider: &dyn LlmProvider,
     db_client: &DBClient,
     user_id: String,
+    settings: &llm::LlmSettings,
+    language: Language,
 ) -> String {
-    match llm::simple_item_response(nest_api, nest_api_key, user_message, user_id, db_client).await
+    match llm::simple_item_response(
+        provider,
+        user_message,
+        user_id,
+        db_client,
+        settings,
+        &llm::LogMetrics,
+    )
+    .await
     {
-        Ok(a) => a,
+        Ok(summary) => summary.message(language),
         Err(e) => {
             match e {
                 llm::LlmError::Request(error) => error!("{error}"),
                 llm::LlmError::Auth(error) => error!("{error}"),
                 llm::LlmError::Parse(error) => error!("{error}"),
+                llm::LlmError::Filtered(error) => error!("{error}"),
             };
 
             "Something went wrong contacting the agent".to_string()
@@ -0,0 +1,68 @@
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, post, web};
+use serde::Serialize;
+
+use crate::database::shopping_sessions::SessionId;
+use crate::database::{self, DBClient};
+
+#[derive(Serialize)]
+pub struct ShoppingSessionResponse {
+    pub id: SessionId,
+}
+
+#[post("/shopping-sessions")]
+pub async fn begin_shopping_session(
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::shopping_sessions::begin_shopping_session(
+        client.get_ref(),
+        user.id().to_string(),
+    )
+    .await;
+
+    match res {
+        Ok(id) => Ok(HttpResponse::Created().json(ShoppingSessionResponse { id })),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[get("/shopping-sessions/{id}/items")]
+pub async fn session_items(
+    path: web::Path<SessionId>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::shopping_sessions::session_items(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+    )
+    .await;
+
+    match res {
+        Ok(items) => Ok(HttpResponse::Ok().json(items)),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[delete("/shopping-sessions/{id}")]
+pub async fn end_shopping_session(
+    path: web::Path<SessionId>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::shopping_sessions::end_shopping_session(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+    )
+    .await;
+
+    match res {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
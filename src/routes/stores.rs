@@ -0,0 +1,45 @@
+use actix_web::{HttpRequest, HttpResponse, Result, get, put, web};
+
+use crate::database::{self, DBClient};
+
+/// Replaces the aisle order of a store, the body lists categories in walking
+/// order
+#[put("/stores/{store_id}/layout")]
+pub async fn set_store_layout(
+    path: web::Path<String>,
+    categories: web::Json<Vec<String>>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::store_layouts::set_store_layout(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+        categories.into_inner(),
+    )
+    .await;
+
+    match res {
+        Ok(()) => Ok(HttpResponse::NoContent().finish()),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[get("/stores/{store_id}/items")]
+pub async fn store_items(
+    path: web::Path<String>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let items = database::store_layouts::order_by_store_layout(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
@@ -0,0 +1,68 @@
+use actix_web::{HttpRequest, HttpResponse, Result, get, post, web};
+use serde::{Deserialize, Serialize};
+
+use crate::database::op_log::{Command, OpLogEntry, SyncCommand};
+use crate::database::{self, DBClient};
+
+#[derive(Deserialize)]
+pub struct CommandsSinceQuery {
+    /// The last clock the client has seen, 0 for everything
+    #[serde(default)]
+    pub after: i64,
+}
+
+/// A logged command as sent to a client catching up
+#[derive(Serialize)]
+pub struct LoggedCommandResponse {
+    pub clock: i64,
+    pub command_id: String,
+    pub item_id: Option<i64>,
+    pub command: Command,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl LoggedCommandResponse {
+    fn of(entry: OpLogEntry) -> Result<Self, String> {
+        Ok(LoggedCommandResponse {
+            command: entry.command()?,
+            clock: entry.clock,
+            command_id: entry.command_id,
+            item_id: entry.item_id,
+            created_at: entry.created_at,
+        })
+    }
+}
+
+#[post("/sync/commands")]
+pub async fn apply_commands(
+    commands: web::Json<Vec<SyncCommand>>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::op_log::apply_commands(client.get_ref(), user.id(), &commands).await;
+
+    match res {
+        Ok(report) => Ok(HttpResponse::Ok().json(report)),
+        Err(err) => Ok(HttpResponse::Conflict().body(err)),
+    }
+}
+
+#[get("/sync/commands")]
+pub async fn commands_since(
+    query: web::Query<CommandsSinceQuery>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let entries = database::op_log::commands_since(client.get_ref(), user.id(), query.after)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let commands = entries
+        .into_iter()
+        .map(LoggedCommandResponse::of)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(commands))
+}
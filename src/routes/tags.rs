@@ -0,0 +1,95 @@
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, post, web};
+use serde::Deserialize;
+
+use crate::database::tags::TagMatch;
+use crate::database::{self, DBClient};
+
+#[derive(Deserialize)]
+pub struct AddTagRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+pub struct TaggedItemsQuery {
+    /// Comma separated tag names
+    pub tags: String,
+    #[serde(default, rename = "match")]
+    pub matching: TagMatch,
+}
+
+#[post("/items/{id}/tags")]
+pub async fn add_tag(
+    path: web::Path<i64>,
+    form: web::Form<AddTagRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::tags::add_tag(
+        client.get_ref(),
+        user.id().to_string(),
+        path.into_inner(),
+        &form.name,
+    )
+    .await;
+
+    match res {
+        Ok(tag) => Ok(HttpResponse::Created().json(tag)),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[delete("/items/{id}/tags/{name}")]
+pub async fn remove_tag(
+    path: web::Path<(i64, String)>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let (item_id, name) = path.into_inner();
+    let user = super::get_user(req).unwrap();
+    database::tags::remove_tag(client.get_ref(), user.id().to_string(), item_id, &name)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/tags/{name}/items")]
+pub async fn items_with_tag(
+    path: web::Path<String>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let items = database::tags::items_with_tag(client.get_ref(), user.id().to_string(), &path)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
+
+/// Items carrying all or any of the tags, e.g. `?tags=dairy,organic&match=all`
+#[get("/items/tagged")]
+pub async fn items_with_tags(
+    query: web::Query<TaggedItemsQuery>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let tags: Vec<&str> = query
+        .tags
+        .split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    let items = database::tags::items_with_tags(
+        client.get_ref(),
+        user.id().to_string(),
+        &tags,
+        query.matching,
+    )
+    .await
+    .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(items))
+}
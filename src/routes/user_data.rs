@@ -0,0 +1,47 @@
+use actix_web::http::header::CONTENT_DISPOSITION;
+use actix_web::{HttpRequest, HttpResponse, Result, delete, get, web};
+use serde::Serialize;
+
+use crate::database::user_data::DeletionReport;
+use crate::database::{self, DBClient};
+
+#[derive(Serialize)]
+pub struct DeletionResponse {
+    pub deleted: u64,
+    #[serde(flatten)]
+    pub tables: DeletionReport,
+}
+
+#[get("/me/export")]
+pub async fn export_user_data(
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let bundle = database::user_data::export_user_bundle(client.get_ref(), user.id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok()
+        .append_header((
+            CONTENT_DISPOSITION,
+            "attachment; filename=\"rezi-export.json\"",
+        ))
+        .json(bundle))
+}
+
+#[delete("/me/data")]
+pub async fn delete_user_data(
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let report = database::user_data::delete_user_data(client.get_ref(), user.id())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(DeletionResponse {
+        deleted: report.total(),
+        tables: report,
+    }))
+}
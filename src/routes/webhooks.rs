@@ -0,0 +1,66 @@
+use actix_web::{HttpRequest, HttpResponse, Result, get, post, web};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{self, DBClient};
+
+#[derive(Deserialize)]
+pub struct RegisterWebhookRequest {
+    pub url: String,
+}
+
+/// A webhook as shown to its owner, the secret only once on registration
+#[derive(Serialize)]
+pub struct WebhookResponse {
+    pub id: i64,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl WebhookResponse {
+    fn of(webhook: database::webhooks::Webhook, with_secret: bool) -> Self {
+        WebhookResponse {
+            id: webhook.id(),
+            url: webhook.url,
+            secret: with_secret.then_some(webhook.secret),
+            created_at: webhook.created_at,
+        }
+    }
+}
+
+#[post("/webhooks")]
+pub async fn register_webhook(
+    form: web::Form<RegisterWebhookRequest>,
+    client: web::Data<DBClient>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let res = database::webhooks::register_webhook(
+        client.get_ref(),
+        user.id().to_string(),
+        form.url.clone(),
+        crate::webhooks::new_secret(),
+    )
+    .await;
+
+    match res {
+        Ok(webhook) => Ok(HttpResponse::Created().json(WebhookResponse::of(webhook, true))),
+        Err(err) => Ok(HttpResponse::BadRequest().body(err)),
+    }
+}
+
+#[get("/webhooks")]
+pub async fn list_webhooks(client: web::Data<DBClient>, req: HttpRequest) -> Result<HttpResponse> {
+    let user = super::get_user(req).unwrap();
+    let webhooks = database::webhooks::list_webhooks(client.get_ref(), user.id().to_string())
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(HttpResponse::Ok().json(
+        webhooks
+            .into_iter()
+            .map(|webhook| WebhookResponse::of(webhook, false))
+            .collect::<Vec<_>>(),
+    ))
+}
@@ -0,0 +1,42 @@
+/// Number of single-character insertions, deletions or substitutions turning
+/// `a` into `b`, case-insensitive
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// How far apart two item names may be and still count as the same item
+///
+/// Short names get no slack, "rice" and "ice" are different things.
+pub fn near_duplicate_distance(task: &str) -> usize {
+    match task.chars().count() {
+        0..=4 => 0,
+        5..=9 => 1,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levenshtein_counts_edits() {
+        assert_eq!(levenshtein("tomatos", "tomatoes"), 1);
+        assert_eq!(levenshtein("Milk", "milk"), 0);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "eggs"), 4);
+        assert_eq!(levenshtein("äpfel", "apfel"), 1);
+    }
+}
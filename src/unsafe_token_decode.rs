@@ -76,7 +76,7 @@ pub fn decode_jwt_unsafe(token: &str) -> Result<User, String> {
 
 fn add_base64_padding(input: &str) -> String {
     let mut padded = input.to_string();
-    while padded.len() % 4 != 0 {
+    while !padded.len().is_multiple_of(4) {
         padded.push('=');
     }
     padded
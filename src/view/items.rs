@@ -89,6 +89,14 @@ pub fn render_item_display(item: &Item) -> Markup {
             title="Click to edit" {
                 (item.task)
             }
+            @if let Some(quantity) = item.quantity_label() {
+                span class="badge badge-ghost badge-sm" { (quantity) }
+            }
+            @if item.needs_review() {
+                span class="badge badge-warning badge-sm" title="The assistant was unsure about this item" {
+                    "review"
+                }
+            }
             button class="btn btn-sm btn-error btn-outline"
                 hx-delete=(format!("/items/{}", item.id()))
                 hx-target="closest div"
@@ -60,7 +60,7 @@ pub fn render(message: &ChatMessage, user: Option<User>) -> Markup {
 
             }
             div class="chat-footer opacity-50"{
-                  (ai_btn(&message))
+                  (ai_btn(message))
             }
 
         }
@@ -0,0 +1,378 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use log::{error, info};
+use rand::Rng;
+use reqwest::Client;
+use ring::hmac;
+use serde::Serialize;
+use url::Url;
+
+use crate::database::items::Item;
+use crate::database::webhooks::Webhook;
+use crate::retry::{self, RetryPolicy};
+
+/// Header carrying `sha256=<hex HMAC of the body>`, keyed with the webhook secret
+pub const SIGNATURE_HEADER: &str = "X-Rezi-Signature";
+pub const EVENT_HEADER: &str = "X-Rezi-Event";
+
+/// Failed deliveries are retried with backoff before giving up
+const DELIVERY_RETRY: RetryPolicy = RetryPolicy {
+    max_attempts: 3,
+    base: Duration::from_millis(250),
+    max: Duration::from_secs(5),
+    jitter: 0.25,
+};
+
+/// Longest a single delivery attempt may take, connecting included
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Which URLs deliveries may go to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetPolicy {
+    /// `https` to hosts resolving to public addresses only
+    PublicHttps,
+    /// Also plain `http` to loopback, for the receivers of the tests
+    Loopback,
+}
+
+/// The owner picks the URL, so the server must not be made to call into its
+/// own network with it
+pub const TARGETS: TargetPolicy = if cfg!(test) {
+    TargetPolicy::Loopback
+} else {
+    TargetPolicy::PublicHttps
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload {
+    pub event: &'static str,
+    pub item: Item,
+}
+
+/// Random key for signing the deliveries of a new webhook, as hex
+pub fn new_secret() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Whether `ip` is reachable on the internet, i.e. not loopback, private,
+/// link-local, shared, multicast or otherwise reserved
+///
+/// IPv6 addresses that carry an IPv4 address, i.e. mapped, NAT64 and 6to4
+/// ones, are judged by that address.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // 100.64.0.0/10, carrier-grade NAT
+                || (a == 100 && (64..128).contains(&b))
+                // 0.0.0.0/8 and 240.0.0.0/4
+                || a == 0
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(v4));
+            }
+            let segments = ip.segments();
+            let embedded = |high: u16, low: u16| {
+                let [a, b] = high.to_be_bytes();
+                let [c, d] = low.to_be_bytes();
+                is_public(IpAddr::V4(std::net::Ipv4Addr::new(a, b, c, d)))
+            };
+            // 64:ff9b::/96, NAT64 with the IPv4 address in the last 32 bits
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                return embedded(segments[6], segments[7]);
+            }
+            // 2002::/16, 6to4 with the IPv4 address in the next 32 bits
+            if segments[0] == 0x2002 {
+                return embedded(segments[1], segments[2]);
+            }
+            let first = segments[0];
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // 2001:db8::/32, documentation
+                || segments[..2] == [0x2001, 0xdb8]
+                // fc00::/7, unique local
+                || (first & 0xfe00) == 0xfc00
+                // fe80::/10, link-local
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Resolves the host of `url` to the address deliveries connect to, failing
+/// for URLs the `policy` doesn't allow
+///
+/// Every address of the host has to be allowed, so a name can't smuggle a
+/// private address in next to a public one.
+pub async fn resolve_target(url: &str, policy: TargetPolicy) -> Result<SocketAddr, String> {
+    let url = Url::parse(url).map_err(|_| "Invalid webhook url".to_string())?;
+    let loopback_http = policy == TargetPolicy::Loopback && url.scheme() == "http";
+    if url.scheme() != "https" && !loopback_http {
+        return Err("Webhook url must use https".to_string());
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| "Webhook url has no host".to_string())?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| "Webhook url has no port".to_string())?;
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.trim_matches(['[', ']']), port))
+        .await
+        .map_err(|_| format!("Could not resolve webhook host {host}"))?
+        .collect();
+    let allowed =
+        |addr: &SocketAddr| is_public(addr.ip()) || (loopback_http && addr.ip().is_loopback());
+    match addrs.first() {
+        Some(first) if addrs.iter().all(allowed) => Ok(*first),
+        Some(_) => Err(format!("Webhook host {host} is not a public address")),
+        None => Err(format!("Could not resolve webhook host {host}")),
+    }
+}
+
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, body);
+    let hex: String = tag.as_ref().iter().map(|b| format!("{b:02x}")).collect();
+    format!("sha256={hex}")
+}
+
+/// Delivers the payload in the background, failures are only logged
+pub fn spawn_delivery(webhook: Webhook, payload: WebhookPayload) {
+    tokio::spawn(async move {
+        if let Err(err) = deliver(&webhook, &payload).await {
+            error!(
+                "giving up on {} webhook {} after {} attempts: {err}",
+                payload.event,
+                webhook.id(),
+                DELIVERY_RETRY.max_attempts
+            );
+        }
+    });
+}
+
+async fn deliver(webhook: &Webhook, payload: &WebhookPayload) -> Result<(), String> {
+    let body = serde_json::to_vec(payload).map_err(|e| format!("Could not serialize: {e}"))?;
+    let signature = sign(&webhook.secret, &body);
+    // Resolved again and pinned, the name may point elsewhere by now
+    let addr = resolve_target(&webhook.url, TARGETS).await?;
+    let host = Url::parse(&webhook.url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default();
+    let client = Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(&host, addr)
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    retry::with_backoff(
+        &DELIVERY_RETRY,
+        |_| true,
+        |_| {
+            let request = client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, payload.event)
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.clone());
+            async move {
+                let response = request.send().await.map_err(|err| err.to_string())?;
+                if !response.status().is_success() {
+                    return Err(format!("status {}", response.status()));
+                }
+                info!("delivered {} webhook {}", payload.event, webhook.id());
+                Ok(())
+            }
+        },
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{self, items};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Accepts connections, answering the first `failures` with a 500
+    async fn start_receiver(failures: usize) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            for attempt in 0.. {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut socket).await;
+                recorded.lock().unwrap().push(request);
+                let status = if attempt < failures {
+                    "500 Internal Server Error"
+                } else {
+                    "200 OK"
+                };
+                let reply =
+                    format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                socket.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = socket.read(&mut chunk).await.unwrap();
+            buf.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&buf).to_string();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|l| {
+                        let (name, value) = l.split_once(':')?;
+                        name.eq_ignore_ascii_case("content-length")
+                            .then(|| value.trim().parse::<usize>().ok())?
+                    })
+                    .unwrap_or(0);
+                if n == 0 || buf.len() >= header_end + 4 + content_length {
+                    return text;
+                }
+            } else if n == 0 {
+                return text;
+            }
+        }
+    }
+
+    async fn wait_for(requests: &Arc<Mutex<Vec<String>>>, count: usize) -> Vec<String> {
+        for _ in 0..100 {
+            let received = requests.lock().unwrap().clone();
+            if received.len() >= count {
+                return received;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("webhook was not called {count} times");
+    }
+
+    fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+        request.lines().find_map(|l| {
+            let (key, value) = l.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    #[tokio::test]
+    async fn test_resolve_target_rejects_internal_urls() {
+        for url in [
+            "http://example.com/hook",
+            "https://127.0.0.1/hook",
+            "https://localhost/hook",
+            "https://10.1.2.3/hook",
+            "https://192.168.0.10/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://100.64.0.1/hook",
+            "https://[::1]/hook",
+            "https://[fd00::1]/hook",
+            "https://[fe80::1]/hook",
+            "https://[::ffff:10.0.0.1]/hook",
+            "not a url",
+        ] {
+            assert!(
+                resolve_target(url, TargetPolicy::PublicHttps)
+                    .await
+                    .is_err(),
+                "{url} was accepted"
+            );
+        }
+        assert!(
+            resolve_target("https://93.184.215.14/hook", TargetPolicy::PublicHttps)
+                .await
+                .is_ok()
+        );
+        assert!(
+            resolve_target("http://127.0.0.1:8080/hook", TargetPolicy::Loopback)
+                .await
+                .is_ok()
+        );
+        assert!(
+            resolve_target("http://10.0.0.1/hook", TargetPolicy::Loopback)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_is_public_checks_embedded_ipv4() {
+        for (ip, public) in [
+            ("64:ff9b::a00:1", false),
+            ("64:ff9b::7f00:1", false),
+            ("64:ff9b::a9fe:a9fe", false),
+            ("64:ff9b::5db8:d70e", true),
+            ("2002:a00:1::1", false),
+            ("2002:c0a8:a::", false),
+            ("2002:5db8:d70e::1", true),
+            ("2001:db8::1", false),
+            ("2001:db8:ffff::", false),
+            ("2606:4700::1111", true),
+        ] {
+            let addr: IpAddr = ip.parse().unwrap();
+            assert_eq!(is_public(addr), public, "{ip}");
+        }
+    }
+
+    #[test]
+    fn test_sign_matches_known_hmac() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_item_creation_sends_signed_post() {
+        let client = database::test_client().await;
+        let (url, requests) = start_receiver(1).await;
+        database::webhooks::register_webhook(&client, "1".to_string(), url, "s3cret".to_string())
+            .await
+            .unwrap();
+
+        let item = items::create_item(&client, Item::new("1".to_string(), "milk".to_string()))
+            .await
+            .unwrap();
+        items::create_item(&client, Item::new("2".to_string(), "tea".to_string()))
+            .await
+            .unwrap();
+
+        let received = wait_for(&requests, 2).await;
+        let request = &received[1];
+        assert!(request.starts_with("POST /hook "));
+        assert_eq!(header(request, EVENT_HEADER), Some("created"));
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        assert_eq!(
+            header(request, SIGNATURE_HEADER),
+            Some(sign("s3cret", body.as_bytes()).as_str())
+        );
+        let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(payload["event"], "created");
+        assert_eq!(payload["item"]["id"], item.id());
+        assert_eq!(payload["item"]["task"], "milk");
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+}